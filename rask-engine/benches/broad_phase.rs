@@ -0,0 +1,113 @@
+#![feature(test)]
+
+extern crate test;
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use test::Bencher;
+
+use rask_engine::boxes::{BoundingBox, RBox};
+use rask_engine::collide::*;
+use rask_engine::math::Vec2;
+
+use common::Lcg;
+
+/// Scatter `count` small boxes over an arena that grows with the number of bodies,
+/// so the density stays roughly the same.
+fn bodies(count: usize) -> Vec<RBox> {
+    // a simple linear congruential generator keeps the benchmarks reproducible
+    let mut lcg = Lcg::new(42);
+    let mut random = move || lcg.next_f32();
+    let size = (count as f32).sqrt() * 4.0;
+    (0..count)
+        .map(|_| {
+            let pos = Vec2::new(random() * size, random() * size);
+            let orientation = Vec2::new(random() - 0.5, random() - 0.5);
+            RBox::new(pos, orientation.normalized() * 1.5, 1.0)
+        })
+        .collect()
+}
+
+fn brute_force(boxes: &[RBox]) -> usize {
+    let mut count = 0;
+    for (i, a) in boxes.iter().enumerate() {
+        for b in boxes[i + 1..].iter() {
+            if a.collides(b) {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+fn grid(boxes: &[RBox]) -> usize {
+    let mut grid = SpatialGrid::new(2.0);
+    for (i, rbox) in boxes.iter().enumerate() {
+        grid.insert(i, rbox.bounding_box());
+    }
+    grid.pairs()
+        .into_iter()
+        .filter(|&(a, b)| boxes[a].collides(&boxes[b]))
+        .count()
+}
+
+fn grid_update(grid: &mut SpatialGrid<usize>, boxes: &mut [RBox]) -> usize {
+    for (i, rbox) in boxes.iter_mut().enumerate() {
+        *rbox += Vec2::new(0.01, 0.0);
+        grid.update(i, rbox.bounding_box());
+    }
+    grid.pairs()
+        .into_iter()
+        .filter(|&(a, b)| boxes[a].collides(&boxes[b]))
+        .count()
+}
+
+#[bench]
+fn bench_brute_force_100(bencher: &mut Bencher) {
+    let boxes = bodies(100);
+    bencher.iter(|| brute_force(&boxes));
+}
+
+#[bench]
+fn bench_brute_force_400(bencher: &mut Bencher) {
+    let boxes = bodies(400);
+    bencher.iter(|| brute_force(&boxes));
+}
+
+#[bench]
+fn bench_brute_force_1000(bencher: &mut Bencher) {
+    let boxes = bodies(1000);
+    bencher.iter(|| brute_force(&boxes));
+}
+
+#[bench]
+fn bench_grid_100(bencher: &mut Bencher) {
+    let boxes = bodies(100);
+    assert_eq!(grid(&boxes), brute_force(&boxes));
+    bencher.iter(|| grid(&boxes));
+}
+
+#[bench]
+fn bench_grid_400(bencher: &mut Bencher) {
+    let boxes = bodies(400);
+    assert_eq!(grid(&boxes), brute_force(&boxes));
+    bencher.iter(|| grid(&boxes));
+}
+
+#[bench]
+fn bench_grid_1000(bencher: &mut Bencher) {
+    let boxes = bodies(1000);
+    assert_eq!(grid(&boxes), brute_force(&boxes));
+    bencher.iter(|| grid(&boxes));
+}
+
+#[bench]
+fn bench_grid_update_1000(bencher: &mut Bencher) {
+    let mut boxes = bodies(1000);
+    let mut spatial_grid = SpatialGrid::new(2.0);
+    for (i, rbox) in boxes.iter().enumerate() {
+        spatial_grid.insert(i, rbox.bounding_box());
+    }
+    bencher.iter(|| grid_update(&mut spatial_grid, &mut boxes));
+}
@@ -1,12 +1,18 @@
-//! The boxes module exposes AABox (axis-aligned box) and RBox (rotated box).
+//! The boxes module exposes AABox (axis-aligned box) and RBox (rotated box) as well as the
+//! rounded shapes Circle and Capsule and convex polygons.
 //! These are used to model objects.
+//! The BoundingBox trait encloses objects in an AABox, e.g. for broad phase collision checks.
 
+use core::convert::TryFrom;
 use core::ops;
 
+use serde::{Deserialize, Serialize};
+
 use crate::math::Vec2;
+use crate::EngineError;
 
 /// An axis-aligned box.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct AABox {
     /// The position of the box.
     pub pos: Vec2,
@@ -49,7 +55,7 @@ impl ops::SubAssign<Vec2> for AABox {
 }
 
 /// A rotated box.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct RBox {
     /// The origin.
     pub pos: Vec2,
@@ -71,6 +77,16 @@ impl RBox {
             v2: orth,
         }
     }
+
+    /// Returns the four corners of the box, ordered along its outline.
+    pub fn vertices(&self) -> [Vec2; 4] {
+        [
+            self.pos,
+            self.pos + self.v1,
+            self.pos + self.v1 + self.v2,
+            self.pos + self.v2,
+        ]
+    }
 }
 
 impl ops::Add<Vec2> for RBox {
@@ -127,3 +143,293 @@ impl From<&spine::skeleton::srt::SRT> for RBox {
         RBox { pos, v1, v2 }
     }
 }
+
+/// A circle.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Circle {
+    /// The center of the circle.
+    pub pos: Vec2,
+    /// The radius, it must be greater than zero.
+    pub radius: f32,
+}
+
+impl Circle {
+    /// Creates a new circle from its center and radius.
+    pub fn new(pos: Vec2, radius: f32) -> Self {
+        Self { pos, radius }
+    }
+}
+
+impl ops::Add<Vec2> for Circle {
+    type Output = Self;
+
+    fn add(self, other: Vec2) -> Self {
+        Self {
+            pos: self.pos + other,
+            radius: self.radius,
+        }
+    }
+}
+
+impl ops::AddAssign<Vec2> for Circle {
+    fn add_assign(&mut self, other: Vec2) {
+        self.pos += other
+    }
+}
+
+impl ops::Sub<Vec2> for Circle {
+    type Output = Self;
+
+    fn sub(self, other: Vec2) -> Self {
+        Self {
+            pos: self.pos - other,
+            radius: self.radius,
+        }
+    }
+}
+
+impl ops::SubAssign<Vec2> for Circle {
+    fn sub_assign(&mut self, other: Vec2) {
+        self.pos -= other
+    }
+}
+
+/// A capsule, i.e. all points within `radius` of the line segment from `start` to `end`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Capsule {
+    /// The start of the center line.
+    pub start: Vec2,
+    /// The end of the center line.
+    pub end: Vec2,
+    /// The radius, it must be greater than zero.
+    pub radius: f32,
+}
+
+impl Capsule {
+    /// Creates a new capsule from its center line and radius.
+    pub fn new(start: Vec2, end: Vec2, radius: f32) -> Self {
+        Self { start, end, radius }
+    }
+}
+
+impl ops::Add<Vec2> for Capsule {
+    type Output = Self;
+
+    fn add(self, other: Vec2) -> Self {
+        Self {
+            start: self.start + other,
+            end: self.end + other,
+            radius: self.radius,
+        }
+    }
+}
+
+impl ops::AddAssign<Vec2> for Capsule {
+    fn add_assign(&mut self, other: Vec2) {
+        self.start += other;
+        self.end += other;
+    }
+}
+
+impl ops::Sub<Vec2> for Capsule {
+    type Output = Self;
+
+    fn sub(self, other: Vec2) -> Self {
+        Self {
+            start: self.start - other,
+            end: self.end - other,
+            radius: self.radius,
+        }
+    }
+}
+
+impl ops::SubAssign<Vec2> for Capsule {
+    fn sub_assign(&mut self, other: Vec2) {
+        self.start -= other;
+        self.end -= other;
+    }
+}
+
+/// A convex polygon.
+/// It is serialized as the list of its vertices and validated when deserializing.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<Vec2>", into = "Vec<Vec2>")]
+pub struct Polygon {
+    // the vertices are stored in counterclockwise order
+    vertices: Vec<Vec2>,
+}
+
+impl Polygon {
+    /// Creates a new convex polygon from its vertices ordered along its outline.
+    /// Both clockwise and counterclockwise orders are accepted.
+    /// Returns an error if there are less than three vertices or the polygon is not convex.
+    pub fn new(mut vertices: Vec<Vec2>) -> Result<Self, EngineError> {
+        let len = vertices.len();
+        if len < 3 {
+            return Err(EngineError::MathError(format!(
+                "a polygon needs at least three vertices, got {}",
+                len
+            )));
+        }
+        let (mut left, mut right) = (false, false);
+        let mut angle = 0.0;
+        for i in 0..len {
+            let (a, b, c) = (
+                vertices[i],
+                vertices[(i + 1) % len],
+                vertices[(i + 2) % len],
+            );
+            let turn = (b - a).cross(c - b);
+            left |= turn > 0.0;
+            right |= turn < 0.0;
+            angle += turn.atan2((b - a).dot(c - b)).abs();
+        }
+        // a convex polygon only turns in one direction and goes around exactly once
+        if left == right || (angle - 2.0 * std::f32::consts::PI).abs() > 1e-3 {
+            return Err(EngineError::MathError(
+                "the vertices do not form a convex polygon".into(),
+            ));
+        }
+        if right {
+            // keep the first vertex in place
+            vertices[1..].reverse();
+        }
+        Ok(Self { vertices })
+    }
+
+    /// Returns the vertices in counterclockwise order.
+    pub fn vertices(&self) -> &[Vec2] {
+        &self.vertices
+    }
+}
+
+impl TryFrom<Vec<Vec2>> for Polygon {
+    type Error = EngineError;
+
+    fn try_from(vertices: Vec<Vec2>) -> Result<Self, Self::Error> {
+        Self::new(vertices)
+    }
+}
+
+impl From<Polygon> for Vec<Vec2> {
+    fn from(polygon: Polygon) -> Self {
+        polygon.vertices
+    }
+}
+
+impl From<AABox> for Polygon {
+    fn from(aabox: AABox) -> Self {
+        RBox::from(aabox).into()
+    }
+}
+
+impl From<RBox> for Polygon {
+    fn from(rbox: RBox) -> Self {
+        let mut vertices = rbox.vertices().to_vec();
+        if rbox.v1.cross(rbox.v2) < 0.0 {
+            vertices.reverse();
+        }
+        Self { vertices }
+    }
+}
+
+impl ops::Add<Vec2> for Polygon {
+    type Output = Self;
+
+    fn add(mut self, other: Vec2) -> Self {
+        self += other;
+        self
+    }
+}
+
+impl ops::AddAssign<Vec2> for Polygon {
+    fn add_assign(&mut self, other: Vec2) {
+        for vertex in self.vertices.iter_mut() {
+            *vertex += other;
+        }
+    }
+}
+
+impl ops::Sub<Vec2> for Polygon {
+    type Output = Self;
+
+    fn sub(mut self, other: Vec2) -> Self {
+        self -= other;
+        self
+    }
+}
+
+impl ops::SubAssign<Vec2> for Polygon {
+    fn sub_assign(&mut self, other: Vec2) {
+        for vertex in self.vertices.iter_mut() {
+            *vertex -= other;
+        }
+    }
+}
+
+/// A trait for objects that can be enclosed by an axis-aligned box.
+pub trait BoundingBox {
+    /// Returns the smallest `AABox` containing the object.
+    fn bounding_box(&self) -> AABox;
+}
+
+/// Returns the smallest `AABox` containing all vertices, `vertices` must not be empty.
+fn enclose(vertices: &[Vec2]) -> AABox {
+    let (min, max) = vertices[1..]
+        .iter()
+        .fold((vertices[0], vertices[0]), |(min, max), v| {
+            (
+                Vec2::new(min.x().min(v.x()), min.y().min(v.y())),
+                Vec2::new(max.x().max(v.x()), max.y().max(v.y())),
+            )
+        });
+    AABox {
+        pos: min,
+        size: max - min,
+    }
+}
+
+impl BoundingBox for AABox {
+    fn bounding_box(&self) -> AABox {
+        *self
+    }
+}
+
+impl BoundingBox for RBox {
+    fn bounding_box(&self) -> AABox {
+        enclose(&self.vertices())
+    }
+}
+
+impl BoundingBox for spine::skeleton::srt::SRT {
+    fn bounding_box(&self) -> AABox {
+        RBox::from(self).bounding_box()
+    }
+}
+
+impl BoundingBox for Circle {
+    fn bounding_box(&self) -> AABox {
+        let radius = Vec2::new(self.radius, self.radius);
+        AABox {
+            pos: self.pos - radius,
+            size: radius * 2.0,
+        }
+    }
+}
+
+impl BoundingBox for Capsule {
+    fn bounding_box(&self) -> AABox {
+        let radius = Vec2::new(self.radius, self.radius);
+        let aabox = enclose(&[self.start, self.end]);
+        AABox {
+            pos: aabox.pos - radius,
+            size: aabox.size + radius * 2.0,
+        }
+    }
+}
+
+impl BoundingBox for Polygon {
+    fn bounding_box(&self) -> AABox {
+        enclose(&self.vertices)
+    }
+}
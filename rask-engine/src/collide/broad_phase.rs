@@ -0,0 +1,210 @@
+//! The broad phase module provides a uniform grid to quickly find potentially colliding objects.
+//! The candidates returned by the grid only have overlapping bounding boxes and still have to be
+//! checked by the narrow phase, i.e. the `Collide` implementations.
+//!
+//! # Example
+//!
+//! ```
+//! use rask_engine::boxes::{BoundingBox, RBox};
+//! use rask_engine::collide::{Collide, SpatialGrid};
+//! use rask_engine::math::Vec2;
+//!
+//! let boxes = [
+//!     RBox::new(Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0), 0.1),
+//!     RBox::new(Vec2::new(0.55, 0.0), Vec2::new(0.0, 1.0), 0.1),
+//!     RBox::new(Vec2::new(0.8, 0.0), Vec2::new(0.2, 0.0), 0.2),
+//! ];
+//! let mut grid = SpatialGrid::new(2.0);
+//! for (i, rbox) in boxes.iter().enumerate() {
+//!     grid.insert(i, rbox.bounding_box());
+//! }
+//! let collisions: Vec<(usize, usize)> = grid
+//!     .pairs()
+//!     .into_iter()
+//!     .filter(|&(a, b)| boxes[a].collides(&boxes[b]))
+//!     .collect();
+//! assert_eq!(collisions, vec![(0, 1)]);
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+use crate::boxes::AABox;
+use crate::math::Vec2;
+
+type Cell = (i32, i32);
+
+/// A uniform grid storing the bounding boxes of objects identified by a key.
+/// Cells are stored sparsely, so the grid does not need to know the size of the world.
+#[derive(Clone, Debug)]
+pub struct SpatialGrid<K> {
+    cell_size: f32,
+    // a BTreeMap keeps the order of the query results independent of the hasher
+    cells: BTreeMap<Cell, Vec<K>>,
+    bounds: HashMap<K, AABox>,
+}
+
+/// Test if two boxes overlap, touching boxes are considered overlapping.
+fn overlaps(a: &AABox, b: &AABox) -> bool {
+    a.pos.x() <= b.pos.x() + b.size.x()
+        && b.pos.x() <= a.pos.x() + a.size.x()
+        && a.pos.y() <= b.pos.y() + b.size.y()
+        && b.pos.y() <= a.pos.y() + a.size.y()
+}
+
+fn contains(aabox: &AABox, point: Vec2) -> bool {
+    aabox.pos.x() <= point.x()
+        && point.x() <= aabox.pos.x() + aabox.size.x()
+        && aabox.pos.y() <= point.y()
+        && point.y() <= aabox.pos.y() + aabox.size.y()
+}
+
+impl<K: Copy + Eq + Hash> SpatialGrid<K> {
+    /// Creates a new empty grid, `cell_size` has to be greater than zero.
+    /// Good values are in the range of the size of a typical object.
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: BTreeMap::new(),
+            bounds: HashMap::new(),
+        }
+    }
+
+    /// Returns the cell size.
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// Returns the number of objects in the grid.
+    pub fn len(&self) -> usize {
+        self.bounds.len()
+    }
+
+    /// Returns `true` if the grid contains no objects.
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_empty()
+    }
+
+    /// Returns `true` if an object with the given key is stored in the grid.
+    pub fn contains(&self, key: K) -> bool {
+        self.bounds.contains_key(&key)
+    }
+
+    /// Returns the stored bounding box of an object.
+    pub fn bounds(&self, key: K) -> Option<AABox> {
+        self.bounds.get(&key).copied()
+    }
+
+    /// Removes all objects from the grid.
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.bounds.clear();
+    }
+
+    fn cell(&self, point: Vec2) -> Cell {
+        (
+            (point.x() / self.cell_size).floor() as i32,
+            (point.y() / self.cell_size).floor() as i32,
+        )
+    }
+
+    /// Returns the lowest and the highest cell covered by a box.
+    fn cell_range(&self, aabox: &AABox) -> (Cell, Cell) {
+        (self.cell(aabox.pos), self.cell(aabox.pos + aabox.size))
+    }
+
+    fn cells_in(range: (Cell, Cell)) -> impl Iterator<Item = Cell> {
+        let ((x1, y1), (x2, y2)) = range;
+        (x1..=x2).flat_map(move |x| (y1..=y2).map(move |y| (x, y)))
+    }
+
+    /// Inserts an object into the grid, an object with the same key is replaced.
+    pub fn insert(&mut self, key: K, bounds: AABox) {
+        self.remove(key);
+        for cell in Self::cells_in(self.cell_range(&bounds)) {
+            self.cells.entry(cell).or_default().push(key);
+        }
+        self.bounds.insert(key, bounds);
+    }
+
+    /// Updates the bounding box of an object, the object is inserted if it is not in the grid.
+    /// The cells are only touched if the object moved into a different cell.
+    pub fn update(&mut self, key: K, bounds: AABox) {
+        match self.bounds.get(&key) {
+            Some(old) if self.cell_range(old) == self.cell_range(&bounds) => {
+                self.bounds.insert(key, bounds);
+            }
+            _ => self.insert(key, bounds),
+        }
+    }
+
+    /// Removes an object from the grid and returns its bounding box.
+    pub fn remove(&mut self, key: K) -> Option<AABox> {
+        let bounds = self.bounds.remove(&key)?;
+        for cell in Self::cells_in(self.cell_range(&bounds)) {
+            if let Some(keys) = self.cells.get_mut(&cell) {
+                keys.retain(|k| *k != key);
+                if keys.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+        Some(bounds)
+    }
+
+    /// Returns all pairs of objects with overlapping bounding boxes. Every pair is only reported once.
+    pub fn pairs(&self) -> Vec<(K, K)> {
+        let mut pairs = Vec::new();
+        for (&cell, keys) in self.cells.iter() {
+            for (i, a) in keys.iter().enumerate() {
+                let box_a = &self.bounds[a];
+                for b in keys[i + 1..].iter() {
+                    let box_b = &self.bounds[b];
+                    // a pair sharing multiple cells is only reported by the cell
+                    // containing the lower left corner of the intersection
+                    let corner = Vec2::new(
+                        box_a.pos.x().max(box_b.pos.x()),
+                        box_a.pos.y().max(box_b.pos.y()),
+                    );
+                    if self.cell(corner) == cell && overlaps(box_a, box_b) {
+                        pairs.push((*a, *b));
+                    }
+                }
+            }
+        }
+        pairs
+    }
+
+    /// Returns all objects whose bounding boxes overlap the given region.
+    pub fn query_region(&self, region: &AABox) -> Vec<K> {
+        let mut result = Vec::new();
+        for cell in Self::cells_in(self.cell_range(region)) {
+            if let Some(keys) = self.cells.get(&cell) {
+                for key in keys.iter() {
+                    let bounds = &self.bounds[key];
+                    let corner = Vec2::new(
+                        bounds.pos.x().max(region.pos.x()),
+                        bounds.pos.y().max(region.pos.y()),
+                    );
+                    if self.cell(corner) == cell && overlaps(bounds, region) {
+                        result.push(*key);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Returns all objects whose bounding boxes contain the given point.
+    pub fn query_point(&self, point: Vec2) -> Vec<K> {
+        self.cells
+            .get(&self.cell(point))
+            .map(|keys| {
+                keys.iter()
+                    .filter(|key| contains(&self.bounds[key], point))
+                    .copied()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
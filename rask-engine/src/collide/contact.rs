@@ -0,0 +1,319 @@
+//! The contact module extends the SAT to calculate contact manifolds.
+//! Besides detecting an overlap, a manifold contains the minimum translation vector and the
+//! contact points, which are needed to resolve the collision.
+
+// For information on the contact point generation, see:
+// http://www.dyn4j.org/2011/11/contact-points-using-clipping/.
+
+use super::project_vertices;
+use crate::boxes::{AABox, RBox};
+use crate::math::Vec2;
+use spine::skeleton::srt::SRT;
+
+/// The tolerance used to decide if a clipped point lies behind the reference face.
+const CONTACT_TOLERANCE: f32 = 1e-4;
+
+/// Information about the overlap of two objects.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Manifold {
+    /// The normalized collision normal.
+    /// It points in the direction the first object has to be moved to resolve the overlap.
+    pub normal: Vec2,
+    /// The penetration depth along the normal.
+    pub depth: f32,
+    points: [Vec2; 2],
+    point_count: usize,
+}
+
+impl Manifold {
+    /// Creates a new `Manifold`, only the first two contact points are used.
+    pub fn new(normal: Vec2, depth: f32, points: &[Vec2]) -> Self {
+        let mut manifold = Self {
+            normal,
+            depth,
+            points: [Vec2::zero(); 2],
+            point_count: points.len().min(2),
+        };
+        manifold.points[..manifold.point_count].copy_from_slice(&points[..manifold.point_count]);
+        manifold
+    }
+
+    /// Returns the minimum translation vector, moving the first object by it resolves the overlap.
+    pub fn mtv(&self) -> Vec2 {
+        self.normal * self.depth
+    }
+
+    /// Returns the contact points, there are either one or two of them.
+    pub fn points(&self) -> &[Vec2] {
+        &self.points[..self.point_count]
+    }
+
+    /// Returns the same manifold seen from the second object.
+    pub fn flipped(self) -> Self {
+        Self {
+            normal: -self.normal,
+            ..self
+        }
+    }
+}
+
+/// A trait for objects that can calculate a contact manifold with other objects.
+pub trait Contact<Rhs: ?Sized = Self> {
+    /// Returns the contact manifold if the objects overlap or touch.
+    fn contact(&self, other: &Rhs) -> Option<Manifold>;
+}
+
+/// An edge of a polygon together with its vertex that is furthest in a given direction.
+struct Edge {
+    max: Vec2,
+    start: Vec2,
+    end: Vec2,
+}
+
+/// Find the edge of a polygon that is most perpendicular to `direction`
+/// and contains the vertex that is furthest in this direction.
+fn best_edge(vertices: &[Vec2], direction: Vec2) -> Edge {
+    let len = vertices.len();
+    let (index, _) =
+        vertices
+            .iter()
+            .enumerate()
+            .fold((0, f32::NEG_INFINITY), |(best, max), (i, vertex)| {
+                let p = vertex.dot(direction);
+                if p > max {
+                    (i, p)
+                } else {
+                    (best, max)
+                }
+            });
+    let max = vertices[index];
+    let prev = vertices[(index + len - 1) % len];
+    let next = vertices[(index + 1) % len];
+    if (max - prev).normalized().dot(direction).abs()
+        <= (max - next).normalized().dot(direction).abs()
+    {
+        Edge {
+            max,
+            start: prev,
+            end: max,
+        }
+    } else {
+        Edge {
+            max,
+            start: max,
+            end: next,
+        }
+    }
+}
+
+/// Clip the segment from `v1` to `v2`, keeping the points `p` with `p.dot(direction) >= offset`.
+fn clip(v1: Vec2, v2: Vec2, direction: Vec2, offset: f32) -> Vec<Vec2> {
+    let mut points = Vec::with_capacity(2);
+    let (d1, d2) = (v1.dot(direction) - offset, v2.dot(direction) - offset);
+    if d1 >= 0.0 {
+        points.push(v1);
+    }
+    if d2 >= 0.0 {
+        points.push(v2);
+    }
+    if d1 * d2 < 0.0 {
+        points.push(v1 + (v2 - v1) * (d1 / (d1 - d2)));
+    }
+    points
+}
+
+/// Calculate the contact points of two overlapping polygons.
+/// `normal` is the collision normal pointing from `b` to `a`.
+fn contact_points(a: &[Vec2], b: &[Vec2], normal: Vec2) -> Vec<Vec2> {
+    if a.len() == 1 {
+        return vec![a[0]];
+    }
+    if b.len() == 1 {
+        return vec![b[0]];
+    }
+    let (edge_a, edge_b) = (best_edge(a, -normal), best_edge(b, normal));
+    // the reference edge is the one most perpendicular to the normal
+    let (reference, incident, outward) =
+        if (edge_a.end - edge_a.start).normalized().dot(normal).abs()
+            <= (edge_b.end - edge_b.start).normalized().dot(normal).abs()
+        {
+            (edge_a, edge_b, -normal)
+        } else {
+            (edge_b, edge_a, normal)
+        };
+    let direction = (reference.end - reference.start).normalized();
+
+    let points = clip(
+        incident.start,
+        incident.end,
+        direction,
+        direction.dot(reference.start),
+    );
+    if points.len() < 2 {
+        return vec![incident.max];
+    }
+    let points = clip(
+        points[0],
+        points[1],
+        -direction,
+        -direction.dot(reference.end),
+    );
+    if points.len() < 2 {
+        return vec![incident.max];
+    }
+
+    // only keep the points behind the reference face
+    let mut face_normal = direction.perp();
+    if face_normal.dot(outward) < 0.0 {
+        face_normal = -face_normal;
+    }
+    let max = face_normal.dot(reference.max);
+    let points: Vec<Vec2> = points
+        .into_iter()
+        .filter(|p| face_normal.dot(*p) - max <= CONTACT_TOLERANCE)
+        .collect();
+    if points.is_empty() {
+        vec![incident.max]
+    } else {
+        points
+    }
+}
+
+/// Calculate the contact manifold of two convex polygons using the SAT.
+/// A polygon consisting of a single vertex is treated as a point.
+pub(super) fn polygon_contact(a: &[Vec2], b: &[Vec2]) -> Option<Manifold> {
+    let mut depth = f32::INFINITY;
+    let mut normal = Vec2::zero();
+    for vertices in [a, b].iter() {
+        let len = vertices.len();
+        for i in 0..len {
+            let edge = vertices[(i + 1) % len] - vertices[i];
+            if edge == Vec2::zero() {
+                continue;
+            }
+            let axis = edge.perp().normalized();
+            let (pa, pb) = (project_vertices(a, axis), project_vertices(b, axis));
+            // the distances `a` has to be moved along the axis or against it
+            let (forward, backward) = (pb.max - pa.min, pa.max - pb.min);
+            if forward < 0.0 || backward < 0.0 {
+                return None;
+            }
+            if forward < depth {
+                depth = forward;
+                normal = axis;
+            }
+            if backward < depth {
+                depth = backward;
+                normal = -axis;
+            }
+        }
+    }
+    if depth == f32::INFINITY {
+        // there are no axes, both objects are points
+        return if a[0] == b[0] {
+            Some(Manifold::new(Vec2::zero(), 0.0, &a[..1]))
+        } else {
+            None
+        };
+    }
+    Some(Manifold::new(normal, depth, &contact_points(a, b, normal)))
+}
+
+impl Contact for Vec2 {
+    fn contact(&self, other: &Self) -> Option<Manifold> {
+        polygon_contact(&[*self], &[*other])
+    }
+}
+
+impl Contact<AABox> for Vec2 {
+    fn contact(&self, other: &AABox) -> Option<Manifold> {
+        polygon_contact(&[*self], &RBox::from(*other).vertices())
+    }
+}
+
+impl Contact<RBox> for Vec2 {
+    fn contact(&self, other: &RBox) -> Option<Manifold> {
+        polygon_contact(&[*self], &other.vertices())
+    }
+}
+
+impl Contact<SRT> for Vec2 {
+    fn contact(&self, other: &SRT) -> Option<Manifold> {
+        polygon_contact(&[*self], &RBox::from(other).vertices())
+    }
+}
+
+impl Contact<Vec2> for AABox {
+    fn contact(&self, other: &Vec2) -> Option<Manifold> {
+        polygon_contact(&RBox::from(*self).vertices(), &[*other])
+    }
+}
+
+impl Contact for AABox {
+    fn contact(&self, other: &Self) -> Option<Manifold> {
+        polygon_contact(
+            &RBox::from(*self).vertices(),
+            &RBox::from(*other).vertices(),
+        )
+    }
+}
+
+impl Contact<RBox> for AABox {
+    fn contact(&self, other: &RBox) -> Option<Manifold> {
+        polygon_contact(&RBox::from(*self).vertices(), &other.vertices())
+    }
+}
+
+impl Contact<SRT> for AABox {
+    fn contact(&self, other: &SRT) -> Option<Manifold> {
+        polygon_contact(&RBox::from(*self).vertices(), &RBox::from(other).vertices())
+    }
+}
+
+impl Contact<Vec2> for RBox {
+    fn contact(&self, other: &Vec2) -> Option<Manifold> {
+        polygon_contact(&self.vertices(), &[*other])
+    }
+}
+
+impl Contact<AABox> for RBox {
+    fn contact(&self, other: &AABox) -> Option<Manifold> {
+        polygon_contact(&self.vertices(), &RBox::from(*other).vertices())
+    }
+}
+
+impl Contact for RBox {
+    fn contact(&self, other: &Self) -> Option<Manifold> {
+        polygon_contact(&self.vertices(), &other.vertices())
+    }
+}
+
+impl Contact<SRT> for RBox {
+    fn contact(&self, other: &SRT) -> Option<Manifold> {
+        polygon_contact(&self.vertices(), &RBox::from(other).vertices())
+    }
+}
+
+impl Contact<Vec2> for SRT {
+    fn contact(&self, other: &Vec2) -> Option<Manifold> {
+        polygon_contact(&RBox::from(self).vertices(), &[*other])
+    }
+}
+
+impl Contact<AABox> for SRT {
+    fn contact(&self, other: &AABox) -> Option<Manifold> {
+        polygon_contact(&RBox::from(self).vertices(), &RBox::from(*other).vertices())
+    }
+}
+
+impl Contact<RBox> for SRT {
+    fn contact(&self, other: &RBox) -> Option<Manifold> {
+        polygon_contact(&RBox::from(self).vertices(), &other.vertices())
+    }
+}
+
+impl Contact for SRT {
+    fn contact(&self, other: &Self) -> Option<Manifold> {
+        polygon_contact(&RBox::from(self).vertices(), &RBox::from(other).vertices())
+    }
+}
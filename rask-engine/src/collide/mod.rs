@@ -1,12 +1,27 @@
 //! The collide module provides the Collide trait for objects that can collide along with several
 //! implementations for various types.
 
+pub mod broad_phase;
+pub mod contact;
+pub mod ray;
+mod rounded;
+pub mod sweep;
+
 use core::ops::Range;
 
-use crate::boxes::{AABox, RBox};
+use crate::boxes::{AABox, Capsule, Circle, Polygon, RBox};
 use crate::math::Vec2;
 use spine::skeleton::srt::SRT;
 
+#[doc(inline)]
+pub use broad_phase::SpatialGrid;
+#[doc(inline)]
+pub use contact::{Contact, Manifold};
+#[doc(inline)]
+pub use ray::{Cast, Ray, RayHit, Segment};
+#[doc(inline)]
+pub use sweep::{Impact, Sweep};
+
 // For information on the SAT, see: http://www.dyn4j.org/2010/01/sat/.
 
 /// A trait for objects that can collide with other objects.
@@ -15,7 +30,16 @@ pub trait Collide<Rhs: ?Sized = Self> {
 }
 
 /// A trait for common objects to be collidable with other common objects.
-pub trait Collidable: Collide<RBox> + Collide<SRT> + Collide<AABox> + Collide<Vec2> {}
+pub trait Collidable:
+    Collide<RBox>
+    + Collide<SRT>
+    + Collide<AABox>
+    + Collide<Vec2>
+    + Collide<Circle>
+    + Collide<Capsule>
+    + Collide<Polygon>
+{
+}
 
 fn left_under(v1: Vec2, v2: Vec2) -> bool {
     v1.x() < v2.x() && v1.y() < v2.y()
@@ -54,6 +78,21 @@ fn project(rbox: &RBox, axis: Vec2) -> Projection {
     proj
 }
 
+/// Project an arbitrary set of vertices onto an axis, `vertices` must not be empty.
+fn project_vertices(vertices: &[Vec2], axis: Vec2) -> Projection {
+    let p = axis.dot(vertices[0]);
+    let mut proj = Projection { min: p, max: p };
+    for &vertex in vertices[1..].iter() {
+        let p = axis.dot(vertex);
+        if p < proj.min {
+            proj.min = p;
+        } else if p > proj.max {
+            proj.max = p;
+        }
+    }
+    proj
+}
+
 /// Calculate the bound in a line segment that collides an AABox projected onto an axis.
 /// `bound` is a tuple of the start and ending point of the AABB.
 /// `pos` is a component of the position vector of the line segment.
@@ -64,7 +103,12 @@ fn calculate_aabox_rbox_component_bounds(
     direction: f32,
 ) -> (f32, f32) {
     if direction == 0.0 {
-        return (0.0, 1.0);
+        // the line is parallel to the bound, so it is either always or never inside of it
+        return if bound.start <= pos && pos <= bound.end {
+            (f32::NEG_INFINITY, f32::INFINITY)
+        } else {
+            (f32::INFINITY, f32::NEG_INFINITY)
+        };
     }
     // get bounds of s by transforming "g(s) = pos + s * direction"
     // and applying the inequality g(s) >= bound.start and g(s) <= bound.end
@@ -0,0 +1,197 @@
+//! The ray module provides rays and line segments that can be cast against boxes.
+//! This is used for queries like line of sight checks or finding the ground below an object.
+
+use core::ops::Range;
+
+use super::calculate_aabox_rbox_component_bounds;
+use crate::boxes::{AABox, RBox};
+use crate::math::Vec2;
+use spine::skeleton::srt::SRT;
+
+/// A half-line starting at `origin`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    /// The starting point.
+    pub origin: Vec2,
+    /// The normalized direction.
+    pub direction: Vec2,
+}
+
+impl Ray {
+    /// Creates a new ray, `direction` must not be zero and gets normalized.
+    pub fn new(origin: Vec2, direction: Vec2) -> Self {
+        Self {
+            origin,
+            direction: direction.normalized(),
+        }
+    }
+
+    /// Returns the point at the given distance from the origin.
+    pub fn point_at(&self, distance: f32) -> Vec2 {
+        self.origin + self.direction * distance
+    }
+}
+
+/// A line segment from `start` to `end`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Segment {
+    /// The starting point.
+    pub start: Vec2,
+    /// The end point.
+    pub end: Vec2,
+}
+
+impl Segment {
+    /// Creates a new segment.
+    pub fn new(start: Vec2, end: Vec2) -> Self {
+        Self { start, end }
+    }
+
+    /// Returns the length of the segment.
+    pub fn length(&self) -> f32 {
+        (self.end - self.start).norm()
+    }
+}
+
+/// The first intersection of a ray or segment with an object.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    /// The distance from the start of the ray or segment to the hit point.
+    pub distance: f32,
+    /// The point of intersection.
+    pub point: Vec2,
+    /// The normalized surface normal at the hit point.
+    /// If the ray starts inside of the object, it points against the direction of the ray.
+    pub normal: Vec2,
+}
+
+/// A trait for rays and segments that can be cast against other objects.
+pub trait Cast<Rhs: ?Sized> {
+    /// Returns the first intersection with `other`.
+    fn cast(&self, other: &Rhs) -> Option<RayHit>;
+}
+
+/// Intersect the line `g(t) = pos + t * direction` with a box given by the bounds of its two axes
+/// and return the parameter `t` of the entry point together with the surface normal.
+/// `normals` are the outward normals of the upper bounds of the two axes.
+/// If `pos` lies inside of the box, the normal is zero.
+fn cast_bounds(
+    xbound: Range<f32>,
+    ybound: Range<f32>,
+    pos: Vec2,
+    direction: Vec2,
+    normals: [Vec2; 2],
+    max: f32,
+) -> Option<(f32, Vec2)> {
+    let (x1, x2) = calculate_aabox_rbox_component_bounds(xbound, pos.x(), direction.x());
+    let (y1, y2) = calculate_aabox_rbox_component_bounds(ybound, pos.y(), direction.y());
+    let (enter, exit) = (x1.max(y1), x2.min(y2));
+    if enter > exit || exit < 0.0 || enter > max {
+        return None;
+    }
+    if enter < 0.0 {
+        return Some((0.0, Vec2::zero()));
+    }
+    // the normal belongs to the axis entered last
+    let normal = if x1 >= y1 {
+        normals[0] * -direction.x().signum()
+    } else {
+        normals[1] * -direction.y().signum()
+    };
+    Some((enter, normal))
+}
+
+fn cast_aabox(origin: Vec2, direction: Vec2, max: f32, aabox: &AABox) -> Option<(f32, Vec2)> {
+    cast_bounds(
+        aabox.pos.x()..aabox.pos.x() + aabox.size.x(),
+        aabox.pos.y()..aabox.pos.y() + aabox.size.y(),
+        origin,
+        direction,
+        [Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0)],
+        max,
+    )
+}
+
+fn cast_rbox(origin: Vec2, direction: Vec2, max: f32, rbox: &RBox) -> Option<(f32, Vec2)> {
+    // transform the line into the coordinate system spanned by v1 and v2,
+    // in which the rbox is the unit square
+    let (n1, n2) = (rbox.v1.norm2(), rbox.v2.norm2());
+    let pos = origin - rbox.pos;
+    cast_bounds(
+        0.0..1.0,
+        0.0..1.0,
+        Vec2::new(pos.dot(rbox.v1) / n1, pos.dot(rbox.v2) / n2),
+        Vec2::new(direction.dot(rbox.v1) / n1, direction.dot(rbox.v2) / n2),
+        [rbox.v1.normalized(), rbox.v2.normalized()],
+        max,
+    )
+}
+
+/// Build the `RayHit` from the parameter `t` of the line `g(t) = origin + t * direction`.
+/// `scale` converts `t` into a distance.
+fn hit(origin: Vec2, direction: Vec2, scale: f32, (t, normal): (f32, Vec2)) -> RayHit {
+    let normal = if normal == Vec2::zero() && direction != Vec2::zero() {
+        -direction.normalized()
+    } else {
+        normal
+    };
+    RayHit {
+        distance: t * scale,
+        point: origin + direction * t,
+        normal,
+    }
+}
+
+impl Cast<AABox> for Ray {
+    fn cast(&self, other: &AABox) -> Option<RayHit> {
+        cast_aabox(self.origin, self.direction, f32::INFINITY, other)
+            .map(|t| hit(self.origin, self.direction, 1.0, t))
+    }
+}
+
+impl Cast<RBox> for Ray {
+    fn cast(&self, other: &RBox) -> Option<RayHit> {
+        cast_rbox(self.origin, self.direction, f32::INFINITY, other)
+            .map(|t| hit(self.origin, self.direction, 1.0, t))
+    }
+}
+
+impl Cast<SRT> for Ray {
+    fn cast(&self, other: &SRT) -> Option<RayHit> {
+        self.cast(&RBox::from(other))
+    }
+}
+
+impl Cast<AABox> for Segment {
+    fn cast(&self, other: &AABox) -> Option<RayHit> {
+        let direction = self.end - self.start;
+        cast_aabox(self.start, direction, 1.0, other)
+            .map(|t| hit(self.start, direction, self.length(), t))
+    }
+}
+
+impl Cast<RBox> for Segment {
+    fn cast(&self, other: &RBox) -> Option<RayHit> {
+        let direction = self.end - self.start;
+        cast_rbox(self.start, direction, 1.0, other)
+            .map(|t| hit(self.start, direction, self.length(), t))
+    }
+}
+
+impl Cast<SRT> for Segment {
+    fn cast(&self, other: &SRT) -> Option<RayHit> {
+        self.cast(&RBox::from(other))
+    }
+}
+
+impl<S: Cast<T>, T> Cast<[T]> for S {
+    fn cast(&self, other: &[T]) -> Option<RayHit> {
+        other
+            .iter()
+            .filter_map(|x| self.cast(x))
+            .fold(None, |nearest: Option<RayHit>, hit| match nearest {
+                Some(nearest) if nearest.distance <= hit.distance => Some(nearest),
+                _ => Some(hit),
+            })
+    }
+}
@@ -0,0 +1,193 @@
+//! Collisions and contact manifolds of the rounded shapes `Circle` and `Capsule` and of convex
+//! polygons.
+//! Every shape is described by a convex core, i.e. a point, a line segment or a polygon, and a
+//! radius around it. Two shapes collide if the distance between their cores is at most the
+//! sum of their radii.
+
+use super::contact::polygon_contact;
+use super::{Collidable, Collide, Contact, Manifold};
+use crate::boxes::{AABox, Capsule, Circle, Polygon, RBox};
+use crate::math::Vec2;
+use spine::skeleton::srt::SRT;
+
+/// A shape consisting of a convex core and a radius.
+trait Rounded {
+    /// Returns the vertices of the core, these are ordered along its outline.
+    fn core(&self) -> Vec<Vec2>;
+
+    fn radius(&self) -> f32 {
+        0.0
+    }
+}
+
+impl Rounded for Vec2 {
+    fn core(&self) -> Vec<Vec2> {
+        vec![*self]
+    }
+}
+
+impl Rounded for AABox {
+    fn core(&self) -> Vec<Vec2> {
+        RBox::from(*self).vertices().to_vec()
+    }
+}
+
+impl Rounded for RBox {
+    fn core(&self) -> Vec<Vec2> {
+        self.vertices().to_vec()
+    }
+}
+
+impl Rounded for SRT {
+    fn core(&self) -> Vec<Vec2> {
+        RBox::from(self).vertices().to_vec()
+    }
+}
+
+impl Rounded for Circle {
+    fn core(&self) -> Vec<Vec2> {
+        vec![self.pos]
+    }
+
+    fn radius(&self) -> f32 {
+        self.radius
+    }
+}
+
+impl Rounded for Capsule {
+    fn core(&self) -> Vec<Vec2> {
+        vec![self.start, self.end]
+    }
+
+    fn radius(&self) -> f32 {
+        self.radius
+    }
+}
+
+impl Rounded for Polygon {
+    fn core(&self) -> Vec<Vec2> {
+        self.vertices().to_vec()
+    }
+}
+
+/// Returns the edges of a core, a single point is treated as an edge of length zero.
+fn edges(core: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    let len = core.len();
+    let count = match len {
+        1 | 2 => 1,
+        _ => len,
+    };
+    (0..count).map(move |i| (core[i], core[(i + 1) % len]))
+}
+
+/// Returns the point on a line segment closest to the given point.
+fn closest_on_segment(point: Vec2, (start, end): (Vec2, Vec2)) -> Vec2 {
+    let direction = end - start;
+    let length = direction.norm2();
+    if length == 0.0 {
+        return start;
+    }
+    start + direction * ((point - start).dot(direction) / length).clamp(0.0, 1.0)
+}
+
+/// Test if two line segments intersect, touching segments are considered intersecting.
+fn segments_intersect((a1, a2): (Vec2, Vec2), (b1, b2): (Vec2, Vec2)) -> bool {
+    let (da, db) = (a2 - a1, b2 - b1);
+    let (d1, d2) = (da.cross(b1 - a1), da.cross(b2 - a1));
+    let (d3, d4) = (db.cross(a1 - b1), db.cross(a2 - b1));
+    // collinear segments are handled by the point segment distance
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+/// Test if a point lies inside of a convex polygon with at least three vertices.
+fn contains(polygon: &[Vec2], point: Vec2) -> bool {
+    let (mut left, mut right) = (false, false);
+    for (start, end) in edges(polygon) {
+        let side = (end - start).cross(point - start);
+        left |= side > 0.0;
+        right |= side < 0.0;
+    }
+    !(left && right)
+}
+
+/// Returns the closest points of two convex cores, or `None` if they overlap.
+fn closest_points(a: &[Vec2], b: &[Vec2]) -> Option<(Vec2, Vec2)> {
+    if (a.len() > 2 && contains(a, b[0])) || (b.len() > 2 && contains(b, a[0])) {
+        return None;
+    }
+    let mut closest = (f32::INFINITY, a[0], b[0]);
+    let mut consider = |pa: Vec2, pb: Vec2| {
+        let distance = (pa - pb).norm2();
+        if distance < closest.0 {
+            closest = (distance, pa, pb);
+        }
+    };
+    for edge_a in edges(a) {
+        for edge_b in edges(b) {
+            if segments_intersect(edge_a, edge_b) {
+                return None;
+            }
+            consider(edge_a.0, closest_on_segment(edge_a.0, edge_b));
+            consider(edge_a.1, closest_on_segment(edge_a.1, edge_b));
+            consider(closest_on_segment(edge_b.0, edge_a), edge_b.0);
+            consider(closest_on_segment(edge_b.1, edge_a), edge_b.1);
+        }
+    }
+    Some((closest.1, closest.2))
+}
+
+fn collide_rounded<A: Rounded, B: Rounded>(a: &A, b: &B) -> bool {
+    let distance = closest_points(&a.core(), &b.core()).map_or(0.0, |(pa, pb)| (pa - pb).norm());
+    distance <= a.radius() + b.radius()
+}
+
+fn rounded_contact<A: Rounded, B: Rounded>(a: &A, b: &B) -> Option<Manifold> {
+    let (core_a, core_b) = (a.core(), b.core());
+    let radius = a.radius() + b.radius();
+    if let Some((pa, pb)) = closest_points(&core_a, &core_b) {
+        let distance = (pa - pb).norm();
+        if distance > radius {
+            return None;
+        }
+        if distance > 0.0 {
+            let normal = (pa - pb) / distance;
+            // the contact point lies in the middle of the overlapping region
+            let point = (pa - normal * a.radius() + pb + normal * b.radius()) * 0.5;
+            return Some(Manifold::new(normal, radius - distance, &[point]));
+        }
+    }
+    // the cores overlap or touch, so the radii just add to the depth
+    polygon_contact(&core_a, &core_b)
+        .map(|manifold| Manifold::new(manifold.normal, manifold.depth + radius, manifold.points()))
+}
+
+macro_rules! impl_collide {
+    ($lhs:ty, $rhs:ty) => {
+        impl Collide<$rhs> for $lhs {
+            fn collides(&self, other: &$rhs) -> bool {
+                collide_rounded(self, other)
+            }
+        }
+
+        impl Contact<$rhs> for $lhs {
+            fn contact(&self, other: &$rhs) -> Option<Manifold> {
+                rounded_contact(self, other)
+            }
+        }
+    };
+    ($lhs:ty: $($rhs:ty),*) => {
+        $(impl_collide!($lhs, $rhs);)*
+    };
+}
+
+impl_collide!(Circle: Vec2, AABox, RBox, SRT, Circle, Capsule, Polygon);
+impl_collide!(Capsule: Vec2, AABox, RBox, SRT, Circle, Capsule, Polygon);
+impl_collide!(Polygon: Vec2, AABox, RBox, SRT, Circle, Capsule, Polygon);
+impl_collide!(Vec2: Circle, Capsule, Polygon);
+impl_collide!(AABox: Circle, Capsule, Polygon);
+impl_collide!(RBox: Circle, Capsule, Polygon);
+impl_collide!(SRT: Circle, Capsule, Polygon);
+
+impl Collidable for Circle {}
+impl Collidable for Capsule {}
+impl Collidable for Polygon {}
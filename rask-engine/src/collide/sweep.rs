@@ -0,0 +1,130 @@
+//! The sweep module provides continuous collision detection for moving boxes.
+//! Instead of only checking the final position, the whole displacement is tested, which prevents
+//! fast objects from tunneling through thin ones between two ticks.
+
+use super::{project, Collide, Projection};
+use crate::boxes::{AABox, RBox};
+use crate::math::Vec2;
+
+/// The first point of contact found by a swept collision test.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Impact {
+    /// The fraction of the displacement at which the objects first touch, in the range `[0, 1]`.
+    /// A value of `0.0` means that the objects were already overlapping before moving.
+    pub time: f32,
+    /// The normalized contact normal. It points away from the stationary object.
+    pub normal: Vec2,
+}
+
+/// A trait for objects that can be tested for collisions while moving.
+pub trait Sweep<Rhs: ?Sized = Self> {
+    /// Move `self` by `displacement` and return the first contact with the stationary `other`.
+    /// If both objects are moving, the relative displacement has to be used.
+    fn sweep(&self, displacement: Vec2, other: &Rhs) -> Option<Impact>;
+}
+
+const X_AXIS: Vec2 = Vec2::new(1.0, 0.0);
+const Y_AXIS: Vec2 = Vec2::new(0.0, 1.0);
+
+/// Calculate the time interval in which the moving projection `a` overlaps the projection `b`.
+/// `speed` is the displacement projected onto the same axis.
+fn overlap_interval(a: &Projection, b: &Projection, speed: f32) -> Option<(f32, f32)> {
+    if speed == 0.0 {
+        return if a.collides(b) {
+            Some((f32::NEG_INFINITY, f32::INFINITY))
+        } else {
+            None
+        };
+    }
+    let (t1, t2) = ((b.min - a.max) / speed, (b.max - a.min) / speed);
+    // if speed is negative, the order of the bounds is switched
+    if speed > 0.0 {
+        Some((t1, t2))
+    } else {
+        Some((t2, t1))
+    }
+}
+
+/// Swept version of the SAT: `a` moves by `displacement`, `b` is stationary and
+/// `axes` contains all potentially separating axes of the two boxes.
+fn sweep_sat(a: &RBox, displacement: Vec2, b: &RBox, axes: &[Vec2]) -> Option<Impact> {
+    let mut enter = f32::NEG_INFINITY;
+    let mut exit = f32::INFINITY;
+    let mut normal = Vec2::zero();
+    for &axis in axes {
+        let speed = axis.dot(displacement);
+        let (axis_enter, axis_exit) =
+            overlap_interval(&project(a, axis), &project(b, axis), speed)?;
+        if axis_enter > enter {
+            enter = axis_enter;
+            // the normal opposes the movement along the axis
+            normal = if speed > 0.0 { -axis } else { axis };
+        }
+        exit = exit.min(axis_exit);
+        if enter > exit {
+            return None;
+        }
+    }
+    // objects that only touch at the start and then separate do not collide
+    if enter > 1.0 || exit <= 0.0 {
+        return None;
+    }
+    Some(Impact {
+        time: enter.max(0.0),
+        normal: if normal == Vec2::zero() {
+            normal
+        } else {
+            normal.normalized()
+        },
+    })
+}
+
+impl Sweep for AABox {
+    fn sweep(&self, displacement: Vec2, other: &Self) -> Option<Impact> {
+        let (a, b): (RBox, RBox) = ((*self).into(), (*other).into());
+        sweep_sat(&a, displacement, &b, &[X_AXIS, Y_AXIS])
+    }
+}
+
+impl Sweep<RBox> for AABox {
+    fn sweep(&self, displacement: Vec2, other: &RBox) -> Option<Impact> {
+        let a: RBox = (*self).into();
+        sweep_sat(
+            &a,
+            displacement,
+            other,
+            &[X_AXIS, Y_AXIS, other.v1, other.v2],
+        )
+    }
+}
+
+impl Sweep<AABox> for RBox {
+    fn sweep(&self, displacement: Vec2, other: &AABox) -> Option<Impact> {
+        let b: RBox = (*other).into();
+        sweep_sat(self, displacement, &b, &[self.v1, self.v2, X_AXIS, Y_AXIS])
+    }
+}
+
+impl Sweep for RBox {
+    fn sweep(&self, displacement: Vec2, other: &Self) -> Option<Impact> {
+        sweep_sat(
+            self,
+            displacement,
+            other,
+            &[self.v1, self.v2, other.v1, other.v2],
+        )
+    }
+}
+
+impl<S: Sweep<T>, T> Sweep<[T]> for S {
+    /// Returns the earliest impact with any of the objects in `other`.
+    fn sweep(&self, displacement: Vec2, other: &[T]) -> Option<Impact> {
+        other
+            .iter()
+            .filter_map(|x| self.sweep(displacement, x))
+            .fold(None, |first: Option<Impact>, impact| match first {
+                Some(first) if first.time <= impact.time => Some(first),
+                _ => Some(impact),
+            })
+    }
+}
@@ -0,0 +1,231 @@
+//! The broadcast module publishes what happened during the simulation.
+//!
+//! The `BroadcastSystem` runs at the end of every simulation step and writes `EngineEvent`s
+//! into an `EventChannel`, e.g. for the server to send them to the clients or for the client to
+//! play sounds. The current state of the world can be pulled as a `WorldSnapshot`.
+
+use serde::{Deserialize, Serialize};
+use specs::hibitset::BitSet;
+use specs::prelude::*;
+use specs::shrev::{EventChannel, ReaderId};
+
+use super::combat::CombatEvent;
+use super::components::*;
+use super::rules::{MatchPhase, MatchState};
+use crate::math::Vec2;
+
+/// A sound to be played by the clients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SoundCue {
+    Clash,
+    Disarm,
+    Kill,
+}
+
+/// Something that happened during a simulation step.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "event")]
+pub enum EngineEvent {
+    /// A moving entity was created, `player` is set if it is a player.
+    Spawn {
+        entity: u32,
+        player: Option<u32>,
+    },
+    /// A moving entity was deleted.
+    Despawn {
+        entity: u32,
+    },
+    Kill {
+        killer: u32,
+        victim: u32,
+    },
+    Disarm {
+        by: u32,
+        victim: u32,
+    },
+    Sound {
+        cue: SoundCue,
+        pos: Vec2,
+    },
+    /// A player changed the state, e.g. started a lunge or was revived.
+    PlayerState {
+        player: u32,
+        state: PlayerState,
+    },
+    /// The match entered another phase.
+    MatchPhase {
+        phase: MatchPhase,
+    },
+}
+
+/// The state of a moving entity.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EntitySnapshot {
+    pub entity: u32,
+    pub pos: Vec2,
+    pub vel: Vec2,
+    pub player: Option<Player>,
+}
+
+/// A compact copy of the state of the world, it contains only the moving entities.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldSnapshot {
+    /// The simulation step the snapshot was taken after.
+    pub tick: u64,
+    pub entities: Vec<EntitySnapshot>,
+    pub match_state: Option<MatchState>,
+}
+
+impl WorldSnapshot {
+    /// Take a snapshot of the world.
+    pub fn new(world: &World, tick: u64) -> Self {
+        let (entities, pos, vel, player) = (
+            world.entities(),
+            world.read_storage::<Pos>(),
+            world.read_storage::<Vel>(),
+            world.read_storage::<Player>(),
+        );
+        Self {
+            tick,
+            entities: (&entities, &pos, &vel, player.maybe())
+                .join()
+                .map(|(entity, pos, vel, player)| EntitySnapshot {
+                    entity: entity.id(),
+                    pos: pos.0,
+                    vel: vel.0,
+                    player: player.copied(),
+                })
+                .collect(),
+            match_state: world
+                .try_fetch::<MatchState>()
+                .map(|state| MatchState::clone(&state)),
+        }
+    }
+
+    /// Returns the snapshot of an entity.
+    pub fn entity(&self, entity: u32) -> Option<&EntitySnapshot> {
+        self.entities.iter().find(|e| e.entity == entity)
+    }
+
+    /// Returns the snapshot of the entity of a player.
+    pub fn player(&self, player: u32) -> Option<&EntitySnapshot> {
+        self.entities
+            .iter()
+            .find(|e| e.player.map(|p| p.id) == Some(player))
+    }
+
+    /// Returns the snapshot of the given entity of another snapshot, e.g. one taken by another
+    /// engine. Players are identified by their id, other entities by the entity id.
+    pub fn counterpart(&self, entity: &EntitySnapshot) -> Option<&EntitySnapshot> {
+        match entity.player {
+            Some(player) => self.player(player.id),
+            None => self.entity(entity.entity).filter(|e| e.player.is_none()),
+        }
+    }
+}
+
+/// Publishes the `EngineEvent`s of a simulation step.
+/// It has to run after all other systems.
+#[derive(Default)]
+pub struct BroadcastSystem {
+    reader: Option<ReaderId<CombatEvent>>,
+    /// The moving entities of the last step.
+    known: BitSet,
+    /// The states of the players in the last step.
+    states: Vec<(u32, PlayerState)>,
+    phase: Option<MatchPhase>,
+}
+
+impl<'a> System<'a> for BroadcastSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Pos>,
+        ReadStorage<'a, Vel>,
+        ReadStorage<'a, Player>,
+        Option<Read<'a, MatchState>>,
+        Read<'a, EventChannel<CombatEvent>>,
+        Write<'a, EventChannel<EngineEvent>>,
+    );
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader = Some(
+            world
+                .fetch_mut::<EventChannel<CombatEvent>>()
+                .register_reader(),
+        );
+    }
+
+    fn run(&mut self, (entities, pos, vel, player, state, combat, mut events): Self::SystemData) {
+        let reader = self
+            .reader
+            .as_mut()
+            .expect("the broadcast system has not been set up");
+        let player_pos = |id: u32| {
+            (&player, &pos)
+                .join()
+                .find(|(player, _)| player.id == id)
+                .map_or(Vec2::zero(), |(_, pos)| pos.0)
+        };
+
+        // spawned and deleted entities
+        let mut current = BitSet::new();
+        for (entity, _, _, player) in (&entities, &pos, &vel, player.maybe()).join() {
+            current.add(entity.id());
+            if !self.known.contains(entity.id()) {
+                events.single_write(EngineEvent::Spawn {
+                    entity: entity.id(),
+                    player: player.map(|p| p.id),
+                });
+            }
+        }
+        for entity in (&self.known).join() {
+            if !current.contains(entity) {
+                events.single_write(EngineEvent::Despawn { entity });
+            }
+        }
+        self.known = current;
+
+        for event in combat.read(reader) {
+            let (event, cue, pos) = match *event {
+                CombatEvent::Kill { killer, victim } => (
+                    Some(EngineEvent::Kill { killer, victim }),
+                    SoundCue::Kill,
+                    player_pos(victim),
+                ),
+                CombatEvent::Disarm { by, victim } => (
+                    Some(EngineEvent::Disarm { by, victim }),
+                    SoundCue::Disarm,
+                    player_pos(victim),
+                ),
+                CombatEvent::Clash { a, b } => {
+                    (None, SoundCue::Clash, (player_pos(a) + player_pos(b)) * 0.5)
+                }
+            };
+            events.iter_write(event);
+            events.single_write(EngineEvent::Sound { cue, pos });
+        }
+
+        let states: Vec<(u32, PlayerState)> = player
+            .join()
+            .map(|player| (player.id, player.state))
+            .collect();
+        for &(id, state) in states.iter() {
+            if !self.states.contains(&(id, state)) {
+                events.single_write(EngineEvent::PlayerState { player: id, state });
+            }
+        }
+        self.states = states;
+
+        let phase = state.map(|state| state.phase);
+        let changed = match (self.phase, phase) {
+            (Some(old), Some(new)) => std::mem::discriminant(&old) != std::mem::discriminant(&new),
+            (old, new) => old.is_some() != new.is_some(),
+        };
+        if let (true, Some(phase)) = (changed, phase) {
+            events.single_write(EngineEvent::MatchPhase { phase });
+        }
+        self.phase = phase;
+    }
+}
@@ -0,0 +1,330 @@
+//! The combat module resolves sword fights between players.
+//!
+//! Every simulation step the `HitboxSystem` derives the hitboxes of all players from their
+//! animations. Characters can provide their hitboxes through named Spine attachments, the
+//! attachments are sampled once into `HitboxAnimation`s which are stored in the `HitboxSet`.
+//! Players without animation data use a simple default layout.
+//!
+//! The `CombatSystem` then checks the hitboxes of all pairs of players:
+//! * if two swords touch, a parrying player disarms the other one, otherwise both are pushed back
+//! * if a sword touches the body of another player, that player is killed
+//!
+//! The outcome is published as `CombatEvent`s in an `EventChannel`.
+
+use std::collections::HashMap;
+
+use specs::prelude::*;
+use specs::shrev::EventChannel;
+
+use super::components::*;
+use crate::boxes::{AABox, RBox};
+use crate::collide::Collide;
+use crate::math::Vec2;
+use crate::resources::Character;
+use crate::EngineError;
+
+/// The speed with which players are pushed apart if their swords clash.
+pub const KNOCKBACK_SPEED: f32 = 3.0;
+
+/// Something that happened during a fight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CombatEvent {
+    /// A player was killed by another one.
+    Kill { killer: u32, victim: u32 },
+    /// A player parried a sword and disarmed its owner.
+    Disarm { by: u32, victim: u32 },
+    /// The swords of two players clashed.
+    Clash { a: u32, b: u32 },
+}
+
+/// The kind of a hitbox.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitboxKind {
+    /// A part of the body that can be hit.
+    Hurt,
+    /// A part of the sword.
+    Weapon,
+}
+
+/// A hitbox relative to the feet of a player facing right.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hitbox {
+    pub kind: HitboxKind,
+    pub area: RBox,
+}
+
+/// Defines how the attachments of a character are turned into hitboxes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HitboxLayout {
+    /// The attachments belonging to the sword, all other attachments are part of the body.
+    pub weapon: Vec<String>,
+    /// Attachments which are ignored, e.g. hair or a cape.
+    pub ignored: Vec<String>,
+    /// The size of one skeleton unit in world units.
+    pub scale: f32,
+    /// The position of the feet in skeleton coordinates.
+    pub origin: Vec2,
+}
+
+/// The hitboxes of an animation sampled in fixed intervals.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HitboxAnimation {
+    duration: f32,
+    frames: Vec<Vec<Hitbox>>,
+}
+
+impl HitboxAnimation {
+    /// Creates a new animation from frames evenly distributed over the duration.
+    /// Returns an error if there are no frames.
+    pub fn new(duration: f32, frames: Vec<Vec<Hitbox>>) -> Result<Self, EngineError> {
+        if frames.is_empty() {
+            return Err(EngineError::Animation(
+                "a hitbox animation needs at least one frame".into(),
+            ));
+        }
+        Ok(Self { duration, frames })
+    }
+
+    /// Sample the attachments of a character animation.
+    pub fn from_character(
+        character: &Character,
+        animation: &str,
+        layout: &HitboxLayout,
+        frame_count: usize,
+    ) -> Result<Self, EngineError> {
+        let duration = character.duration(animation)?;
+        let frames = (0..frame_count.max(1))
+            .map(|frame| {
+                let time = duration * frame as f32 / frame_count.max(1) as f32;
+                Ok(character
+                    .attachment_boxes(time, animation)?
+                    .into_iter()
+                    .filter(|(name, _)| !layout.ignored.contains(name))
+                    .map(|(name, area)| Hitbox {
+                        kind: if layout.weapon.contains(&name) {
+                            HitboxKind::Weapon
+                        } else {
+                            HitboxKind::Hurt
+                        },
+                        area: RBox {
+                            pos: (area.pos - layout.origin) * layout.scale,
+                            v1: area.v1 * layout.scale,
+                            v2: area.v2 * layout.scale,
+                        },
+                    })
+                    .collect())
+            })
+            .collect::<Result<_, EngineError>>()?;
+        Self::new(duration, frames)
+    }
+
+    /// Returns the hitboxes at the given time, the animation is looped.
+    pub fn at(&self, time: f32) -> &[Hitbox] {
+        if self.duration <= 0.0 {
+            return &self.frames[0];
+        }
+        let progress = time.rem_euclid(self.duration) / self.duration;
+        let frame = (progress * self.frames.len() as f32) as usize;
+        &self.frames[frame.min(self.frames.len() - 1)]
+    }
+}
+
+/// The hitbox animations of the players, keyed by the name of the animation.
+#[derive(Debug, Clone, Default)]
+pub struct HitboxSet(pub HashMap<String, HitboxAnimation>);
+
+/// Returns the name of the animation played in a state.
+pub fn animation_name(state: PlayerState) -> &'static str {
+    match state {
+        PlayerState::Idle => "standing",
+        PlayerState::Run => "walking",
+        PlayerState::Jump => "jumping",
+        PlayerState::Lunge => "lunging",
+        PlayerState::Parry => "parrying",
+        PlayerState::Dead => "dead",
+    }
+}
+
+/// Returns the height of the sword above the feet in a stance.
+fn stance_height(stance: Stance) -> f32 {
+    match stance {
+        Stance::Low => 0.5,
+        Stance::Mid => 0.9,
+        Stance::High => 1.3,
+    }
+}
+
+/// The hitboxes used if there is no animation for a state.
+fn default_hitboxes(player: &Player) -> Vec<Hitbox> {
+    let mut hitboxes = vec![Hitbox {
+        kind: HitboxKind::Hurt,
+        area: AABox {
+            pos: Vec2::new(-0.3, 0.0),
+            size: Vec2::new(0.6, 1.5),
+        }
+        .into(),
+    }];
+    if player.armed {
+        let reach = if player.state == PlayerState::Lunge {
+            1.2
+        } else {
+            0.9
+        };
+        hitboxes.push(Hitbox {
+            kind: HitboxKind::Weapon,
+            area: AABox {
+                pos: Vec2::new(0.2, stance_height(player.stance) - 0.05),
+                size: Vec2::new(reach, 0.1),
+            }
+            .into(),
+        });
+    }
+    hitboxes
+}
+
+/// Mirror a box relative to a player facing right to the facing of the player and move it
+/// to the position of the player.
+fn place(area: RBox, facing: Facing, pos: Vec2) -> RBox {
+    match facing {
+        Facing::Right => area + pos,
+        // mirroring flips the winding, swapping the vectors restores it
+        Facing::Left => RBox {
+            pos: Vec2::new(-area.pos.x(), area.pos.y()) + pos,
+            v1: Vec2::new(-area.v2.x(), area.v2.y()),
+            v2: Vec2::new(-area.v1.x(), area.v1.y()),
+        },
+    }
+}
+
+/// Updates the `Hitboxes` of all players.
+pub struct HitboxSystem;
+
+impl<'a> System<'a> for HitboxSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Pos>,
+        WriteStorage<'a, Hitboxes>,
+        Read<'a, HitboxSet>,
+    );
+
+    fn run(&mut self, (entities, player, pos, mut hitboxes, set): Self::SystemData) {
+        for (entity, player, pos) in (&entities, &player, &pos).join() {
+            let mut boxes = Hitboxes::default();
+            if player.state != PlayerState::Dead {
+                let animation = set.0.get(animation_name(player.state));
+                let default;
+                let frame = match animation {
+                    Some(animation) => animation.at(player.state_time),
+                    None => {
+                        default = default_hitboxes(player);
+                        &default
+                    }
+                };
+                for hitbox in frame {
+                    let area = place(hitbox.area, player.facing, pos.0);
+                    match hitbox.kind {
+                        HitboxKind::Hurt => boxes.hurt.push(area),
+                        HitboxKind::Weapon if player.armed => boxes.weapon.push(area),
+                        HitboxKind::Weapon => (),
+                    }
+                }
+            }
+            hitboxes.insert(entity, boxes).unwrap();
+        }
+    }
+}
+
+fn touches(a: &[RBox], b: &[RBox]) -> bool {
+    a.iter().any(|a| b.iter().any(|b| a.collides(b)))
+}
+
+/// Resolves the hits between players.
+/// It has to run after the hitboxes are updated.
+pub struct CombatSystem;
+
+impl<'a> System<'a> for CombatSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Player>,
+        WriteStorage<'a, Vel>,
+        ReadStorage<'a, Hitboxes>,
+        Write<'a, EventChannel<CombatEvent>>,
+    );
+
+    fn run(&mut self, (entities, mut player, mut vel, hitboxes, mut events): Self::SystemData) {
+        let fighters: Vec<(Entity, Player)> = (&entities, &player, &hitboxes)
+            .join()
+            .filter(|(_, player, _)| player.state != PlayerState::Dead)
+            .map(|(entity, player, _)| (entity, *player))
+            .collect();
+        let mut killed = Vec::new();
+        let mut disarmed = Vec::new();
+        let mut knocked_back = Vec::new();
+
+        for (i, &(a, player_a)) in fighters.iter().enumerate() {
+            for &(b, player_b) in fighters[i + 1..].iter() {
+                let (boxes_a, boxes_b) = (hitboxes.get(a).unwrap(), hitboxes.get(b).unwrap());
+                // crossed swords protect both players
+                if touches(&boxes_a.weapon, &boxes_b.weapon) {
+                    let parry_a = player_a.state == PlayerState::Parry;
+                    let parry_b = player_b.state == PlayerState::Parry;
+                    if parry_a && !parry_b {
+                        disarmed.push(b);
+                        events.single_write(CombatEvent::Disarm {
+                            by: player_a.id,
+                            victim: player_b.id,
+                        });
+                    } else if parry_b && !parry_a {
+                        disarmed.push(a);
+                        events.single_write(CombatEvent::Disarm {
+                            by: player_b.id,
+                            victim: player_a.id,
+                        });
+                    } else {
+                        knocked_back.push(a);
+                        knocked_back.push(b);
+                        events.single_write(CombatEvent::Clash {
+                            a: player_a.id,
+                            b: player_b.id,
+                        });
+                    }
+                    continue;
+                }
+                for &(attacker, victim, player_attacker, player_victim) in
+                    [(a, b, player_a, player_b), (b, a, player_b, player_a)].iter()
+                {
+                    let (weapon, hurt) = (
+                        &hitboxes.get(attacker).unwrap().weapon,
+                        &hitboxes.get(victim).unwrap().hurt,
+                    );
+                    if touches(weapon, hurt) && !killed.contains(&victim) {
+                        killed.push(victim);
+                        events.single_write(CombatEvent::Kill {
+                            killer: player_attacker.id,
+                            victim: player_victim.id,
+                        });
+                    }
+                }
+            }
+        }
+
+        for entity in disarmed {
+            let player = player.get_mut(entity).unwrap();
+            player.armed = false;
+            player.set_state(PlayerState::Idle);
+        }
+        for entity in knocked_back {
+            let player = player.get_mut(entity).unwrap();
+            if player.state == PlayerState::Lunge {
+                player.set_state(PlayerState::Idle);
+            }
+            if let Some(vel) = vel.get_mut(entity) {
+                vel.0 = Vec2::new(-player.facing.sign() * KNOCKBACK_SPEED, vel.0.y());
+            }
+        }
+        for entity in killed {
+            player.get_mut(entity).unwrap().set_state(PlayerState::Dead);
+        }
+    }
+}
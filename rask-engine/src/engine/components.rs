@@ -0,0 +1,278 @@
+//! The components that can be attached to entities of the game world.
+
+use serde::{Deserialize, Serialize};
+use specs::{prelude::*, Component};
+
+use crate::boxes::{AABox, BoundingBox, Capsule, Circle, Polygon, RBox};
+use crate::collide::{Collide, Contact, Manifold};
+use crate::math::Vec2;
+
+/// The velocity of an entity.
+#[derive(Debug, Clone, Copy, Component)]
+#[storage(VecStorage)]
+pub struct Vel(pub Vec2);
+
+/// The position of an entity.
+#[derive(Debug, Clone, Copy, Component)]
+#[storage(VecStorage)]
+pub struct Pos(pub Vec2);
+
+/// The position of an entity before the last simulation step.
+/// It is used to interpolate between two steps when rendering.
+#[derive(Debug, Clone, Copy, Component)]
+#[storage(VecStorage)]
+pub struct PrevPos(pub Vec2);
+
+/// Marks an entity that is neither affected by gravity nor by collisions.
+#[derive(Debug, Default, Clone, Copy, Component)]
+#[storage(NullStorage)]
+pub struct Static;
+
+/// The mass of an entity, it has to be greater than zero.
+/// Entities with a collider, but without a mass can't be moved by collisions.
+#[derive(Debug, Clone, Copy, Component)]
+#[storage(VecStorage)]
+pub struct Mass(pub f32);
+
+impl Mass {
+    /// Returns the inverse of the mass, which is zero for an infinite mass.
+    pub fn inverse(self) -> f32 {
+        1.0 / self.0
+    }
+}
+
+/// The surface properties of an entity used to resolve collisions.
+#[derive(Debug, Clone, Copy, PartialEq, Component, Serialize, Deserialize)]
+#[storage(VecStorage)]
+#[serde(default)]
+pub struct Material {
+    /// The bounciness, `0.0` means that no energy is kept, `1.0` is a perfectly elastic collision.
+    pub restitution: f32,
+    /// The friction coefficient.
+    pub friction: f32,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            restitution: 0.0,
+            friction: 0.5,
+        }
+    }
+}
+
+/// Marks an entity created by loading a level, it is removed when the next level is loaded.
+#[derive(Debug, Default, Clone, Copy, Component)]
+#[storage(NullStorage)]
+pub struct LevelEntity;
+
+/// A textured area drawn behind the game.
+#[derive(Debug, Clone, Copy, PartialEq, Component, Serialize, Deserialize)]
+#[storage(DenseVecStorage)]
+pub struct Background {
+    /// The resource id of the texture.
+    pub texture: u32,
+    /// The area covered by the texture.
+    pub area: AABox,
+}
+
+/// A point at which a player can enter the game, the position is given by `Pos`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+#[storage(DenseVecStorage)]
+pub struct SpawnPoint {
+    /// The player using this spawn point.
+    pub player: u32,
+}
+
+/// An area a player has to reach to win.
+#[derive(Debug, Clone, Copy, PartialEq, Component, Serialize, Deserialize)]
+#[storage(DenseVecStorage)]
+pub struct Goal {
+    /// The player that wins by reaching the goal.
+    pub player: u32,
+    /// The area of the goal.
+    pub area: AABox,
+}
+
+/// The direction a player is looking at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Facing {
+    Left,
+    Right,
+}
+
+impl Facing {
+    /// Returns `-1.0` for left and `1.0` for right.
+    pub fn sign(self) -> f32 {
+        match self {
+            Facing::Left => -1.0,
+            Facing::Right => 1.0,
+        }
+    }
+}
+
+/// The height at which a player holds the sword.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Stance {
+    Low,
+    Mid,
+    High,
+}
+
+impl Stance {
+    /// Returns the next higher stance.
+    pub fn up(self) -> Self {
+        match self {
+            Stance::Low => Stance::Mid,
+            _ => Stance::High,
+        }
+    }
+
+    /// Returns the next lower stance.
+    pub fn down(self) -> Self {
+        match self {
+            Stance::High => Stance::Mid,
+            _ => Stance::Low,
+        }
+    }
+}
+
+/// The action a player is currently performing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlayerState {
+    Idle,
+    Run,
+    Jump,
+    /// A thrust with the sword combined with a step forward.
+    Lunge,
+    Parry,
+    Dead,
+}
+
+/// A player controlled by the input actions of the player with the same id.
+#[derive(Debug, Clone, Copy, PartialEq, Component, Serialize, Deserialize)]
+#[storage(DenseVecStorage)]
+pub struct Player {
+    pub id: u32,
+    pub facing: Facing,
+    /// `true` if the player stands on something.
+    pub grounded: bool,
+    pub stance: Stance,
+    pub state: PlayerState,
+    /// `false` if the player lost the sword.
+    pub armed: bool,
+    /// The time in seconds the player has spent in the current state.
+    pub state_time: f32,
+}
+
+impl Player {
+    /// Creates a new idle player.
+    pub fn new(id: u32, facing: Facing) -> Self {
+        Self {
+            id,
+            facing,
+            grounded: false,
+            stance: Stance::Mid,
+            state: PlayerState::Idle,
+            armed: true,
+            state_time: 0.0,
+        }
+    }
+
+    /// Switch to another state, this resets the state time.
+    pub fn set_state(&mut self, state: PlayerState) {
+        if self.state != state {
+            self.state = state;
+            self.state_time = 0.0;
+        }
+    }
+}
+
+/// The areas of a player used for combat, in world coordinates.
+/// They are derived from the animation of the player every simulation step.
+#[derive(Debug, Default, Clone, PartialEq, Component)]
+#[storage(DenseVecStorage)]
+pub struct Hitboxes {
+    /// The areas in which the player can be hit.
+    pub hurt: Vec<RBox>,
+    /// The areas covered by the sword.
+    pub weapon: Vec<RBox>,
+}
+
+/// The shape of an entity used for collisions.
+/// The shape is relative to the position of the entity.
+#[derive(Debug, Clone, PartialEq, Component, Serialize, Deserialize)]
+#[storage(DenseVecStorage)]
+#[serde(rename_all = "lowercase")]
+pub enum Collider {
+    AABox(AABox),
+    RBox(RBox),
+    Circle(Circle),
+    Capsule(Capsule),
+    Polygon(Polygon),
+}
+
+/// A shape that can calculate a contact manifold with every shape used by a `Collider`.
+trait ContactAll:
+    Contact<AABox> + Contact<RBox> + Contact<Circle> + Contact<Capsule> + Contact<Polygon>
+{
+}
+
+impl<T> ContactAll for T where
+    T: Contact<AABox> + Contact<RBox> + Contact<Circle> + Contact<Capsule> + Contact<Polygon>
+{
+}
+
+impl Collider {
+    /// Returns the shape moved to the given position.
+    pub fn at(&self, pos: Vec2) -> Self {
+        match self {
+            Collider::AABox(shape) => Collider::AABox(*shape + pos),
+            Collider::RBox(shape) => Collider::RBox(*shape + pos),
+            Collider::Circle(shape) => Collider::Circle(*shape + pos),
+            Collider::Capsule(shape) => Collider::Capsule(*shape + pos),
+            Collider::Polygon(shape) => Collider::Polygon(shape.clone() + pos),
+        }
+    }
+
+    /// Calculate the contact manifold of `other` with this collider.
+    fn contact_from<T: ContactAll + ?Sized>(&self, other: &T) -> Option<Manifold> {
+        match self {
+            Collider::AABox(shape) => other.contact(shape),
+            Collider::RBox(shape) => other.contact(shape),
+            Collider::Circle(shape) => other.contact(shape),
+            Collider::Capsule(shape) => other.contact(shape),
+            Collider::Polygon(shape) => other.contact(shape),
+        }
+    }
+}
+
+impl Contact for Collider {
+    fn contact(&self, other: &Self) -> Option<Manifold> {
+        match self {
+            Collider::AABox(shape) => other.contact_from(shape),
+            Collider::RBox(shape) => other.contact_from(shape),
+            Collider::Circle(shape) => other.contact_from(shape),
+            Collider::Capsule(shape) => other.contact_from(shape),
+            Collider::Polygon(shape) => other.contact_from(shape),
+        }
+    }
+}
+
+impl Collide for Collider {
+    fn collides(&self, other: &Self) -> bool {
+        self.contact(other).is_some()
+    }
+}
+
+impl BoundingBox for Collider {
+    fn bounding_box(&self) -> AABox {
+        match self {
+            Collider::AABox(shape) => shape.bounding_box(),
+            Collider::RBox(shape) => shape.bounding_box(),
+            Collider::Circle(shape) => shape.bounding_box(),
+            Collider::Capsule(shape) => shape.bounding_box(),
+            Collider::Polygon(shape) => shape.bounding_box(),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! The level module defines the level format and its validation.
+//!
+//! Levels are stored as JSON, all positions are given as `[x, y]` arrays and shapes as objects
+//! named after the shape type.
+//!
+//! # Example
+//!
+//! ```
+//! use rask_engine::engine::Level;
+//!
+//! let level = Level::from_json(
+//!     r#"{
+//!         "name": "example",
+//!         "backgrounds": [{ "texture": 0, "area": { "pos": [-2, -1], "size": [4, 2] } }],
+//!         "geometry": [{ "aabox": { "pos": [-2, -1], "size": [4, 0.2] } }],
+//!         "spawns": [{ "player": 0, "pos": [-1, 0] }, { "player": 1, "pos": [1, 0] }],
+//!         "goals": [{ "player": 0, "area": { "pos": [1.5, -1], "size": [0.5, 2] } }]
+//!     }"#,
+//! )
+//! .unwrap();
+//! assert_eq!(level.spawns.len(), 2);
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use super::components::{Background, Collider, Goal, Material};
+use crate::boxes::AABox;
+use crate::math::Vec2;
+use crate::resources::RESOURCE_COUNT;
+use crate::EngineError;
+
+/// A point at which a player enters the level.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Spawn {
+    pub player: u32,
+    pub pos: Vec2,
+}
+
+/// A dynamic object of the level.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Body {
+    pub pos: Vec2,
+    /// The shape relative to `pos`.
+    pub collider: Collider,
+    pub mass: f32,
+    #[serde(default)]
+    pub material: Material,
+    #[serde(default)]
+    pub velocity: Vec2,
+}
+
+/// The description of a level.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Level {
+    pub name: String,
+    /// The textures drawn behind the level, the first one is drawn first.
+    #[serde(default)]
+    pub backgrounds: Vec<Background>,
+    /// The static geometry in world coordinates.
+    #[serde(default)]
+    pub geometry: Vec<Collider>,
+    #[serde(default)]
+    pub spawns: Vec<Spawn>,
+    #[serde(default)]
+    pub goals: Vec<Goal>,
+    #[serde(default)]
+    pub bodies: Vec<Body>,
+}
+
+fn check_area(area: &AABox) -> Result<(), String> {
+    if area.size.x() > 0.0 && area.size.y() > 0.0 {
+        Ok(())
+    } else {
+        Err(format!("the size {:?} is not positive", area.size))
+    }
+}
+
+fn check_collider(collider: &Collider) -> Result<(), String> {
+    match collider {
+        Collider::AABox(aabox) => check_area(aabox),
+        Collider::RBox(rbox) => {
+            if rbox.v1 == Vec2::zero() || rbox.v2 == Vec2::zero() {
+                Err("the sides of the rbox must not be zero".into())
+            } else if rbox.v1.normalized().dot(rbox.v2.normalized()).abs() > 1e-4 {
+                Err("the sides of the rbox are not orthogonal".into())
+            } else {
+                Ok(())
+            }
+        }
+        Collider::Circle(circle) if circle.radius <= 0.0 => {
+            Err(format!("the radius {} is not positive", circle.radius))
+        }
+        Collider::Capsule(capsule) if capsule.radius <= 0.0 => {
+            Err(format!("the radius {} is not positive", capsule.radius))
+        }
+        // polygons are already validated when they are created
+        _ => Ok(()),
+    }
+}
+
+impl Level {
+    /// Parse and validate a level stored as JSON.
+    pub fn from_json(json: &str) -> Result<Self, EngineError> {
+        let level: Self = serde_json::from_str(json)?;
+        level.validate()?;
+        Ok(level)
+    }
+
+    /// Parse and validate a level stored as JSON.
+    pub fn from_slice(json: &[u8]) -> Result<Self, EngineError> {
+        let level: Self = serde_json::from_slice(json)?;
+        level.validate()?;
+        Ok(level)
+    }
+
+    /// Serialize the level as JSON.
+    pub fn to_json(&self) -> Result<String, EngineError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Check the values that are syntactically correct, but can't be used in the game.
+    pub fn validate(&self) -> Result<(), EngineError> {
+        let error = |kind: &str, i: usize, message: String| {
+            EngineError::ResourceFormat(format!(
+                "level {:?}: {} {}: {}",
+                self.name, kind, i, message
+            ))
+        };
+        for (i, background) in self.backgrounds.iter().enumerate() {
+            if background.texture >= RESOURCE_COUNT {
+                return Err(error(
+                    "background",
+                    i,
+                    format!("unknown texture id {}", background.texture),
+                ));
+            }
+            check_area(&background.area).map_err(|e| error("background", i, e))?;
+        }
+        for (i, collider) in self.geometry.iter().enumerate() {
+            check_collider(collider).map_err(|e| error("geometry", i, e))?;
+        }
+        for (i, goal) in self.goals.iter().enumerate() {
+            check_area(&goal.area).map_err(|e| error("goal", i, e))?;
+        }
+        for (i, body) in self.bodies.iter().enumerate() {
+            if !(body.mass > 0.0 && body.mass.is_finite()) {
+                return Err(error(
+                    "body",
+                    i,
+                    format!("the mass {} is not positive", body.mass),
+                ));
+            }
+            check_collider(&body.collider).map_err(|e| error("body", i, e))?;
+        }
+        Ok(())
+    }
+}
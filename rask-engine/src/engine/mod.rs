@@ -0,0 +1,499 @@
+pub mod broadcast;
+pub mod combat;
+pub mod components;
+pub mod level;
+pub mod physics;
+pub mod player;
+pub mod replay;
+pub mod resources;
+pub mod rules;
+pub mod state;
+pub mod systems;
+
+use crate::events::Event;
+use crate::input::{ActionState, InputHandler};
+use crate::math::Vec2;
+use crate::EngineError;
+use core::time::Duration;
+use specs::prelude::*;
+use specs::shrev::{EventChannel, ReaderId};
+use specs::WorldExt;
+
+#[doc(inline)]
+pub use broadcast::{BroadcastSystem, EngineEvent, WorldSnapshot};
+#[doc(inline)]
+pub use combat::{CombatEvent, CombatSystem, HitboxSet, HitboxSystem};
+use components::*;
+#[doc(inline)]
+pub use level::Level;
+#[doc(inline)]
+pub use physics::PhysicsSystem;
+#[doc(inline)]
+pub use player::PlayerSystem;
+#[doc(inline)]
+pub use replay::{Replay, ReplayPlayer, ReplayRecorder};
+use resources::*;
+#[doc(inline)]
+pub use rules::{MatchRules, MatchState, MatchSystem};
+#[doc(inline)]
+pub use state::SavedState;
+use systems::*;
+
+const GRAVITY: Vec2 = Vec2::new(0.0, -9.807);
+
+/// An interface for the game server to interact with the game.
+pub trait GameEngine {
+    /// Create a new game.
+    fn new(pool: std::sync::Arc<rayon::ThreadPool>) -> Self;
+
+    /// Replace the current level with the given one.
+    /// May cause an `EngineError` if the level is invalid.
+    fn load_level(&mut self, level: Level) -> Result<(), EngineError>;
+
+    /// Let the game engine handle the given event during the next simulation step.
+    /// May cause an `EngineError`.
+    fn handle_event(&mut self, event: Event) -> Result<(), EngineError>;
+
+    /// Advance the game by the given time, this runs as many simulation steps as fit into it.
+    /// May cause an `EngineError`.
+    fn tick(&mut self, dt: core::time::Duration) -> Result<(), EngineError>;
+
+    /// Returns the events emitted by the simulation since the last call.
+    /// The events are kept until they are polled, so this should be called regularly.
+    fn poll_events(&mut self) -> Vec<EngineEvent>;
+
+    /// Returns a compact copy of the current state of the game.
+    fn snapshot(&self) -> WorldSnapshot;
+}
+
+/// The default duration of a simulation step.
+pub const DEFAULT_TIMESTEP: Duration = Duration::from_millis(10);
+/// The maximum number of simulation steps done in a single tick.
+/// If the engine falls behind further, the remaining time is dropped to let it catch up,
+/// unless the engine is deterministic.
+const MAX_STEPS_PER_TICK: u32 = 10;
+
+/// The rask specific implementation of the `GameEngine`.
+/// The simulation runs in fixed steps, the time passed to `tick` is accumulated and used up in
+/// steps of `timestep`.
+pub struct RaskEngine {
+    world: World,
+    tick_dispatcher: Dispatcher<'static, 'static>,
+    timestep: Duration,
+    accumulator: Duration,
+    /// The reader used by `poll_events`, the events are kept until they are polled.
+    event_reader: ReaderId<EngineEvent>,
+    /// Steps are never skipped, see `new_deterministic`.
+    deterministic: bool,
+}
+
+impl RaskEngine {
+    /// Create a game that is simulated bit for bit reproducibly.
+    /// The systems run one after another on the calling thread and `tick` never skips steps, so
+    /// the state only depends on the inputs of each step. This is needed for rollbacks and for
+    /// comparing checksums with other engines.
+    pub fn new_deterministic() -> Self {
+        // the systems run in the order they are added
+        let tick_dispatcher = DispatcherBuilder::new()
+            .with_thread_local(InputSystem)
+            .with_thread_local(PrevPosSystem)
+            .with_thread_local(PlayerSystem)
+            .with_thread_local(GravitationSystem)
+            .with_thread_local(VelocitySystem)
+            .with_thread_local(PhysicsSystem::default())
+            .with_thread_local(HitboxSystem)
+            .with_thread_local(CombatSystem)
+            .with_thread_local(MatchSystem::default())
+            .with_thread_local(BroadcastSystem::default())
+            .build();
+        Self::with_dispatcher(tick_dispatcher, true)
+    }
+
+    fn with_dispatcher(
+        mut tick_dispatcher: Dispatcher<'static, 'static>,
+        deterministic: bool,
+    ) -> Self {
+        let mut world: specs::World = specs::WorldExt::new();
+        world.insert(Gravitation(GRAVITY));
+        world.insert(DeltaTime(DEFAULT_TIMESTEP));
+        world.insert(Tick::default());
+        world.insert(Seed::default());
+        world.insert(EventQueue::default());
+        world.insert(ActionQueue::default());
+
+        tick_dispatcher.setup(&mut world);
+        world.register::<LevelEntity>();
+        world.register::<Background>();
+        world.register::<SpawnPoint>();
+        world.register::<Goal>();
+        let event_reader = world
+            .write_resource::<EventChannel<EngineEvent>>()
+            .register_reader();
+        Self {
+            world,
+            tick_dispatcher,
+            timestep: DEFAULT_TIMESTEP,
+            accumulator: Duration::from_secs(0),
+            event_reader,
+            deterministic,
+        }
+    }
+
+    /// Returns `true` if the engine was created by `new_deterministic`.
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Returns a checksum of the replicated state, see `WorldSnapshot::checksum`.
+    pub fn checksum(&self) -> u64 {
+        self.snapshot().checksum()
+    }
+
+    /// Returns the game world.
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Returns the game world, e.g. to create entities.
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    /// Returns the duration of a simulation step.
+    pub fn timestep(&self) -> Duration {
+        self.timestep
+    }
+
+    /// Sets the duration of a simulation step, panics if it is zero.
+    pub fn set_timestep(&mut self, timestep: Duration) {
+        assert!(
+            timestep > Duration::from_secs(0),
+            "the timestep has to be greater than zero"
+        );
+        self.timestep = timestep;
+        *self.world.write_resource::<DeltaTime>() = DeltaTime(timestep);
+    }
+
+    /// Returns the seed reserved for random numbers of the simulation, see `Seed`.
+    pub fn seed(&self) -> u64 {
+        self.world.read_resource::<Seed>().0
+    }
+
+    /// Set the seed reserved for random numbers of the simulation.
+    /// It does not change the simulation yet, no system reads it.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.world.insert(Seed(seed));
+    }
+
+    /// Returns the number of simulation steps done so far.
+    pub fn tick_nr(&self) -> u64 {
+        self.world.read_resource::<Tick>().0
+    }
+
+    /// Returns how far the time has advanced towards the next simulation step, in the range
+    /// `[0, 1)`. It is used to interpolate between the previous and the current state.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.timestep.as_secs_f32()
+    }
+
+    /// Returns the position of an entity interpolated between the last two simulation steps.
+    pub fn interpolated_pos(&self, entity: Entity) -> Option<Vec2> {
+        let pos = self.world.read_storage::<Pos>().get(entity)?.0;
+        let prev_pos = self
+            .world
+            .read_storage::<PrevPos>()
+            .get(entity)
+            .map_or(pos, |prev_pos| prev_pos.0);
+        let alpha = self.alpha();
+        Some(prev_pos * (1.0 - alpha) + pos * alpha)
+    }
+
+    /// Create a player at the spawn point of the current level for the given player id.
+    /// Players without a spawn point start at the origin.
+    pub fn spawn_player(&mut self, id: u32) -> Entity {
+        let pos = (
+            &self.world.read_storage::<Pos>(),
+            &self.world.read_storage::<SpawnPoint>(),
+        )
+            .join()
+            .find(|(_, spawn)| spawn.player == id)
+            .map_or(Vec2::zero(), |(pos, _)| pos.0);
+        let facing = rules::facing_towards_center(pos);
+        self.world
+            .create_entity()
+            .with(Pos(pos))
+            .with(PrevPos(pos))
+            .with(Vel(Vec2::zero()))
+            .with(player::player_collider())
+            .with(Mass(player::PLAYER_MASS))
+            .with(Material {
+                restitution: 0.0,
+                friction: 0.0,
+            })
+            .with(Player::new(id, facing))
+            .build()
+    }
+
+    /// Delete the player with the given id, returns `false` if there is no such player.
+    pub fn despawn_player(&mut self, id: u32) -> bool {
+        let entity = (&self.world.entities(), &self.world.read_storage::<Player>())
+            .join()
+            .find(|(_, player)| player.id == id)
+            .map(|(entity, _)| entity);
+        match entity {
+            Some(entity) => {
+                // the entity was just found, so it is alive
+                self.world.delete_entity(entity).unwrap();
+                self.world.maintain();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Start a new match with the given rules, the current level is used as the arena.
+    pub fn start_match(&mut self, rules: MatchRules) {
+        let arena = rules::arena_bounds(&self.world);
+        self.world.insert(MatchState::new(rules, arena));
+    }
+
+    /// Returns the state of the current match, if a match is running.
+    pub fn match_state(&self) -> Option<MatchState> {
+        self.world
+            .try_fetch::<MatchState>()
+            .map(|state| MatchState::clone(&state))
+    }
+
+    /// Register an additional reader for the engine events.
+    /// Only events emitted after the registration are read.
+    pub fn register_event_reader(&mut self) -> ReaderId<EngineEvent> {
+        self.world
+            .write_resource::<EventChannel<EngineEvent>>()
+            .register_reader()
+    }
+
+    /// Returns the engine events emitted since the last call with the same reader.
+    pub fn events(&self, reader: &mut ReaderId<EngineEvent>) -> Vec<EngineEvent> {
+        self.world
+            .read_resource::<EventChannel<EngineEvent>>()
+            .read(reader)
+            .copied()
+            .collect()
+    }
+
+    /// Returns the action state of a player in the last simulation step.
+    pub fn actions(&self, player: u32) -> ActionState {
+        self.world.read_resource::<InputHandler>().player(player)
+    }
+
+    /// Set the action state of a player for the next simulation step, it replaces the state
+    /// resulting from the events. This is used for inputs received over the network.
+    pub fn set_actions(&mut self, player: u32, actions: ActionState) {
+        self.world
+            .write_resource::<ActionQueue>()
+            .0
+            .push((player, actions));
+    }
+
+    /// Reset the moving entities and the match to the state of a snapshot, e.g. one received
+    /// from the server. Players are identified by their id, other entities by the entity id.
+    /// Entities of the snapshot that don't exist in this world are ignored.
+    pub fn restore(&mut self, snapshot: &WorldSnapshot) {
+        let local = self.snapshot();
+        {
+            let entities = self.world.entities();
+            let mut pos = self.world.write_storage::<Pos>();
+            let mut prev_pos = self.world.write_storage::<PrevPos>();
+            let mut vel = self.world.write_storage::<Vel>();
+            let mut player = self.world.write_storage::<Player>();
+            for state in snapshot.entities.iter() {
+                let entity = match local.counterpart(state) {
+                    Some(local) => entities.entity(local.entity),
+                    None => {
+                        log::debug!("the entity {} of the snapshot is unknown", state.entity);
+                        continue;
+                    }
+                };
+                // the entity already exists, so the insertion can't fail
+                pos.insert(entity, Pos(state.pos)).unwrap();
+                prev_pos.insert(entity, PrevPos(state.pos)).unwrap();
+                vel.insert(entity, Vel(state.vel)).unwrap();
+                if let Some(state) = state.player {
+                    player.insert(entity, state).unwrap();
+                }
+            }
+        }
+        match &snapshot.match_state {
+            Some(state) => self.world.insert(state.clone()),
+            None => {
+                self.world.remove::<MatchState>();
+            }
+        }
+        self.world.write_resource::<Tick>().0 = snapshot.tick;
+    }
+
+    /// Save the simulation state, e.g. to roll back to it later on.
+    pub fn save_state(&self) -> SavedState {
+        SavedState::new(&self.world)
+    }
+
+    /// Reset the simulation to a state saved by `save_state`.
+    pub fn load_state(&mut self, state: &SavedState) {
+        state.restore(&mut self.world);
+    }
+
+    /// Add the time to the accumulator and return the number of simulation steps that are due.
+    /// The time of these steps is taken from the accumulator, so the caller has to do them.
+    pub fn advance(&mut self, dt: Duration) -> u32 {
+        self.accumulator += dt;
+        let steps = (self.accumulator.as_nanos() / self.timestep.as_nanos()) as u32;
+        // keep the fraction of a step to not disturb the interpolation
+        self.accumulator =
+            Duration::from_nanos((self.accumulator.as_nanos() % self.timestep.as_nanos()) as u64);
+        if self.deterministic {
+            return steps;
+        }
+        if steps > MAX_STEPS_PER_TICK {
+            log::warn!(
+                "the engine is falling behind, skipping {:?}",
+                self.timestep * (steps - MAX_STEPS_PER_TICK)
+            );
+        }
+        steps.min(MAX_STEPS_PER_TICK)
+    }
+
+    /// Replace the hitbox animations of the players.
+    pub fn set_hitboxes(&mut self, hitboxes: HitboxSet) {
+        self.world.insert(hitboxes);
+    }
+
+    /// Register a reader for the combat events, e.g. to keep the score.
+    /// Only events emitted after the registration are read.
+    pub fn register_combat_reader(&mut self) -> ReaderId<CombatEvent> {
+        self.world
+            .write_resource::<EventChannel<CombatEvent>>()
+            .register_reader()
+    }
+
+    /// Returns the combat events emitted since the last call with the same reader.
+    pub fn combat_events(&self, reader: &mut ReaderId<CombatEvent>) -> Vec<CombatEvent> {
+        self.world
+            .read_resource::<EventChannel<CombatEvent>>()
+            .read(reader)
+            .copied()
+            .collect()
+    }
+
+    /// Do a single simulation step, regardless of the accumulated time.
+    pub fn step(&mut self) {
+        self.tick_dispatcher.dispatch(&self.world);
+        self.world.maintain();
+        self.world.write_resource::<EventQueue>().0.clear();
+        self.world.write_resource::<ActionQueue>().0.clear();
+        self.world.write_resource::<Tick>().0 += 1;
+    }
+}
+
+impl GameEngine for RaskEngine {
+    fn new(pool: std::sync::Arc<rayon::ThreadPool>) -> Self {
+        let tick_dispatcher = DispatcherBuilder::new()
+            .with_pool(pool)
+            .with(InputSystem, "input", &[])
+            .with(PrevPosSystem, "prev_pos", &[])
+            .with(PlayerSystem, "player", &["input"])
+            .with(GravitationSystem, "gravitation", &["prev_pos", "player"])
+            .with(VelocitySystem, "velocity", &["gravitation"])
+            .with(PhysicsSystem::default(), "physics", &["velocity"])
+            .with(HitboxSystem, "hitboxes", &["physics"])
+            .with(CombatSystem, "combat", &["hitboxes"])
+            .with(MatchSystem::default(), "match", &["combat"])
+            .with(BroadcastSystem::default(), "broadcast", &["match"])
+            .build();
+        Self::with_dispatcher(tick_dispatcher, false)
+    }
+
+    fn load_level(&mut self, level: Level) -> Result<(), EngineError> {
+        level.validate()?;
+        let old: Vec<Entity> = (
+            &self.world.entities(),
+            &self.world.read_storage::<LevelEntity>(),
+        )
+            .join()
+            .map(|(entity, _)| entity)
+            .collect();
+        self.world
+            .delete_entities(&old)
+            .map_err(|e| EngineError::Misc(format!("{}", e)))?;
+        self.world.maintain();
+
+        for background in level.backgrounds {
+            self.world
+                .create_entity()
+                .with(background)
+                .with(LevelEntity)
+                .build();
+        }
+        for collider in level.geometry {
+            self.world
+                .create_entity()
+                .with(Pos(Vec2::zero()))
+                .with(collider)
+                .with(Static)
+                .with(LevelEntity)
+                .build();
+        }
+        for spawn in level.spawns {
+            self.world
+                .create_entity()
+                .with(Pos(spawn.pos))
+                .with(SpawnPoint {
+                    player: spawn.player,
+                })
+                .with(LevelEntity)
+                .build();
+        }
+        for goal in level.goals {
+            self.world
+                .create_entity()
+                .with(goal)
+                .with(LevelEntity)
+                .build();
+        }
+        for body in level.bodies {
+            self.world
+                .create_entity()
+                .with(Pos(body.pos))
+                .with(Vel(body.velocity))
+                .with(body.collider)
+                .with(Mass(body.mass))
+                .with(body.material)
+                .with(LevelEntity)
+                .build();
+        }
+        Ok(())
+    }
+
+    fn handle_event(&mut self, event: Event) -> Result<(), EngineError> {
+        self.world.write_resource::<EventQueue>().0.push(event);
+        Ok(())
+    }
+
+    fn poll_events(&mut self) -> Vec<EngineEvent> {
+        self.world
+            .read_resource::<EventChannel<EngineEvent>>()
+            .read(&mut self.event_reader)
+            .copied()
+            .collect()
+    }
+
+    fn snapshot(&self) -> WorldSnapshot {
+        WorldSnapshot::new(&self.world, self.tick_nr())
+    }
+
+    fn tick(&mut self, dt: Duration) -> Result<(), EngineError> {
+        for _ in 0..self.advance(dt) {
+            self.step();
+        }
+        Ok(())
+    }
+}
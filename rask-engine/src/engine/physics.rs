@@ -0,0 +1,127 @@
+//! The physics module resolves collisions between entities with a `Collider`.
+//! Potentially colliding entities are found with a `SpatialGrid`, the contacts are calculated
+//! with the `Contact` trait and resolved by applying impulses to the velocities and by pushing
+//! the entities apart.
+
+use specs::prelude::*;
+
+use super::components::*;
+use super::resources::*;
+use crate::boxes::BoundingBox;
+use crate::collide::{Contact, SpatialGrid};
+use crate::math::Vec2;
+
+/// The fraction of the penetration depth that is corrected in each step.
+const CORRECTION_PERCENT: f32 = 0.8;
+/// The penetration depth that is allowed without correction, this prevents jittering.
+const CORRECTION_SLOP: f32 = 0.01;
+
+/// The system detecting and resolving collisions.
+/// It has to run after the velocities are applied to the positions.
+pub struct PhysicsSystem {
+    grid: SpatialGrid<Entity>,
+}
+
+impl PhysicsSystem {
+    /// Creates a new physics system, the cell size of the broad phase should be about the size of
+    /// a typical collider.
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            grid: SpatialGrid::new(cell_size),
+        }
+    }
+}
+
+impl Default for PhysicsSystem {
+    fn default() -> Self {
+        Self::new(2.0)
+    }
+}
+
+/// The data of a single body needed to resolve its collisions.
+struct Body {
+    collider: Collider,
+    inv_mass: f32,
+    material: Material,
+}
+
+impl<'a> System<'a> for PhysicsSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Pos>,
+        WriteStorage<'a, Vel>,
+        ReadStorage<'a, Collider>,
+        ReadStorage<'a, Mass>,
+        ReadStorage<'a, Material>,
+        ReadStorage<'a, Static>,
+        Write<'a, Collisions>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, mut pos, mut vel, collider, mass, material, is_static, mut collisions): Self::SystemData,
+    ) {
+        collisions.0.clear();
+        self.grid.clear();
+        for (entity, pos, collider) in (&entities, &pos, &collider).join() {
+            self.grid.insert(entity, collider.at(pos.0).bounding_box());
+        }
+
+        for (a, b) in self.grid.pairs() {
+            let body = |entity| Body {
+                collider: collider.get(entity).unwrap().at(pos.get(entity).unwrap().0),
+                inv_mass: match (is_static.get(entity), mass.get(entity)) {
+                    (None, Some(mass)) => mass.inverse(),
+                    _ => 0.0,
+                },
+                material: material.get(entity).copied().unwrap_or_default(),
+            };
+            let (body_a, body_b) = (body(a), body(b));
+            let inv_mass = body_a.inv_mass + body_b.inv_mass;
+            let manifold = match body_a.collider.contact(&body_b.collider) {
+                Some(manifold) => manifold,
+                None => continue,
+            };
+            collisions.0.push(Collision { a, b, manifold });
+            if inv_mass == 0.0 || manifold.normal == Vec2::zero() {
+                continue;
+            }
+            let normal = manifold.normal;
+
+            // resolve the velocities
+            let velocity = |entity| vel.get(entity).map_or(Vec2::zero(), |vel| vel.0);
+            let relative = velocity(a) - velocity(b);
+            let normal_speed = relative.dot(normal);
+            if normal_speed < 0.0 {
+                let restitution = body_a.material.restitution.max(body_b.material.restitution);
+                let j = -(1.0 + restitution) * normal_speed / inv_mass;
+                let mut impulse = normal * j;
+
+                let tangent = relative - normal * normal_speed;
+                if tangent != Vec2::zero() {
+                    let tangent = tangent.normalized();
+                    let friction = (body_a.material.friction * body_b.material.friction).sqrt();
+                    // the friction can at most stop the tangential movement
+                    let jt = (-relative.dot(tangent) / inv_mass).max(-j * friction);
+                    impulse += tangent * jt;
+                }
+                if let Some(vel) = vel.get_mut(a) {
+                    vel.0 += impulse * body_a.inv_mass;
+                }
+                if let Some(vel) = vel.get_mut(b) {
+                    vel.0 -= impulse * body_b.inv_mass;
+                }
+            }
+
+            // push the bodies apart
+            let correction = normal
+                * ((manifold.depth - CORRECTION_SLOP).max(0.0) / inv_mass * CORRECTION_PERCENT);
+            if let Some(pos) = pos.get_mut(a) {
+                pos.0 += correction * body_a.inv_mass;
+            }
+            if let Some(pos) = pos.get_mut(b) {
+                pos.0 -= correction * body_b.inv_mass;
+            }
+        }
+    }
+}
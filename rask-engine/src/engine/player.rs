@@ -0,0 +1,125 @@
+//! The player module contains the character controller.
+//! The `PlayerSystem` runs the state machine of every `Player` using the input actions of the
+//! current step and sets the velocity accordingly.
+
+use specs::prelude::*;
+
+use super::components::*;
+use super::resources::*;
+use super::rules::MatchState;
+use crate::boxes::AABox;
+use crate::input::{Action, ActionState, InputHandler};
+use crate::math::Vec2;
+
+/// The horizontal speed while running.
+pub const RUN_SPEED: f32 = 4.0;
+/// The vertical speed at the start of a jump.
+pub const JUMP_SPEED: f32 = 6.0;
+/// The horizontal speed during a lunge.
+pub const LUNGE_SPEED: f32 = 6.0;
+/// The duration of a lunge in seconds.
+pub const LUNGE_TIME: f32 = 0.25;
+/// The duration of a parry in seconds.
+pub const PARRY_TIME: f32 = 0.3;
+/// The mass of a player.
+pub const PLAYER_MASS: f32 = 70.0;
+/// The minimal vertical component of a contact normal for the player to stand on it.
+const GROUND_NORMAL: f32 = 0.7;
+
+/// Returns the collider of a player relative to the position of its feet.
+pub fn player_collider() -> Collider {
+    Collider::AABox(AABox {
+        pos: Vec2::new(-0.3, 0.0),
+        size: Vec2::new(0.6, 1.5),
+    })
+}
+
+/// Runs the state machine of the players.
+/// It has to run after the input is updated and before the velocities are applied.
+pub struct PlayerSystem;
+
+/// Advance the state machine of a player by one step and return the new velocity.
+fn update(player: &mut Player, input: ActionState, mut vel: Vec2, dt: f32) -> Vec2 {
+    player.state_time += dt;
+    let direction = input.direction();
+
+    match player.state {
+        PlayerState::Dead => return Vec2::new(0.0, vel.y()),
+        PlayerState::Lunge if player.state_time < LUNGE_TIME => {
+            return Vec2::new(player.facing.sign() * LUNGE_SPEED, vel.y());
+        }
+        PlayerState::Parry if player.state_time < PARRY_TIME => {
+            return Vec2::new(0.0, vel.y());
+        }
+        PlayerState::Lunge | PlayerState::Parry => player.set_state(PlayerState::Idle),
+        PlayerState::Jump if player.grounded && vel.y() <= 0.0 => {
+            player.set_state(PlayerState::Idle)
+        }
+        _ => (),
+    }
+
+    if input.pressed(Action::Up) {
+        player.stance = player.stance.up();
+    }
+    if input.pressed(Action::Down) {
+        player.stance = player.stance.down();
+    }
+    if direction < 0.0 {
+        player.facing = Facing::Left;
+    } else if direction > 0.0 {
+        player.facing = Facing::Right;
+    }
+    vel = Vec2::new(direction * RUN_SPEED, vel.y());
+
+    if input.pressed(Action::Thrust) && player.armed {
+        player.set_state(PlayerState::Lunge);
+        Vec2::new(player.facing.sign() * LUNGE_SPEED, vel.y())
+    } else if input.pressed(Action::Parry) && player.armed {
+        player.set_state(PlayerState::Parry);
+        Vec2::new(0.0, vel.y())
+    } else if player.state == PlayerState::Jump {
+        vel
+    } else if input.pressed(Action::Jump) && player.grounded {
+        player.set_state(PlayerState::Jump);
+        player.grounded = false;
+        Vec2::new(vel.x(), JUMP_SPEED)
+    } else if direction != 0.0 {
+        player.set_state(PlayerState::Run);
+        vel
+    } else {
+        player.set_state(PlayerState::Idle);
+        vel
+    }
+}
+
+impl<'a> System<'a> for PlayerSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Player>,
+        WriteStorage<'a, Vel>,
+        Read<'a, InputHandler>,
+        Read<'a, Collisions>,
+        Read<'a, DeltaTime>,
+        Option<Read<'a, MatchState>>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, mut player, mut vel, input, collisions, dt, state): Self::SystemData,
+    ) {
+        // the players can only move while a match is played
+        let frozen = matches!(state, Some(state) if !state.is_playing());
+        for (entity, player, vel) in (&entities, &mut player, &mut vel).join() {
+            // the contacts of the last step decide if the player stands on something
+            player.grounded = collisions
+                .of(entity)
+                .any(|(_, manifold)| manifold.normal.y() > GROUND_NORMAL);
+            let input = if frozen {
+                ActionState::default()
+            } else {
+                input.player(player.id)
+            };
+            vel.0 = update(player, input, vel.0, dt.0.as_secs_f32());
+        }
+    }
+}
@@ -0,0 +1,404 @@
+//! The replay module records matches and plays them back.
+//!
+//! A `Replay` contains everything a deterministic engine needs to reproduce a match: the seed
+//! reserved for random numbers, the level, the rules and players the match was started with,
+//! and the actions of every player in every simulation step. Only the actions that differ from
+//! the held actions of the step before are stored, so a replay stays small even for long matches.
+//! The checksum of the world is stored every `CHECKSUM_INTERVAL` steps and after the last one,
+//! to verify the playback and to find the step a simulation started to diverge at.
+//!
+//! The players are spawned and the match is started before the first step, players joining
+//! later on are not recorded.
+//!
+//! All numbers are little endian, the level and the rules are stored as JSON.
+
+use std::time::Duration;
+
+use super::{GameEngine, Level, MatchRules, RaskEngine, DEFAULT_TIMESTEP};
+use crate::error::EngineError;
+use crate::input::ActionState;
+use crate::network::packet::{add_u32_to_vec, Reader, Serialize};
+
+/// The first bytes of an encoded replay.
+const REPLAY_MAGIC: &[u8; 4] = b"RRPL";
+/// The version of the encoding.
+pub const REPLAY_VERSION: u8 = 1;
+/// The number of steps between two stored checksums.
+pub const CHECKSUM_INTERVAL: u32 = 100;
+
+/// The actions of a player in a simulation step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayInput {
+    pub step: u32,
+    pub player: u32,
+    pub actions: ActionState,
+}
+
+/// A recorded match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    /// The seed of the engine, reserved for random numbers, see `resources::Seed`.
+    pub seed: u64,
+    pub timestep: Duration,
+    pub level: Level,
+    /// The rules of the match, `None` if no match was started.
+    pub rules: Option<MatchRules>,
+    /// The ids of the players, they are spawned in this order.
+    pub players: Vec<u32>,
+    steps: u32,
+    /// The recorded actions, ordered by step.
+    inputs: Vec<ReplayInput>,
+    /// The checksums of the world after a step, ordered by step.
+    checksums: Vec<(u32, u64)>,
+}
+
+impl Replay {
+    /// Creates an empty replay of a match of the given players in the level.
+    pub fn new(level: Level, players: Vec<u32>, rules: Option<MatchRules>) -> Self {
+        Self {
+            seed: 0,
+            timestep: DEFAULT_TIMESTEP,
+            level,
+            rules,
+            players,
+            steps: 0,
+            inputs: Vec::new(),
+            checksums: Vec::new(),
+        }
+    }
+
+    /// Returns the number of recorded steps.
+    pub fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    /// Returns the actions that differ from the held actions of the step before.
+    pub fn inputs(&self) -> &[ReplayInput] {
+        &self.inputs
+    }
+
+    /// Returns the checksum of the world after the last step.
+    pub fn checksum(&self) -> Option<u64> {
+        match self.checksums.last() {
+            Some(&(step, checksum)) if step == self.steps => Some(checksum),
+            _ => None,
+        }
+    }
+
+    /// Play the replay on a new deterministic engine and return the final checksum.
+    /// Fails if the playback diverges from the recording.
+    pub fn verify(&self) -> Result<u64, EngineError> {
+        let mut engine = RaskEngine::new_deterministic();
+        let mut player = ReplayPlayer::new(self.clone(), &mut engine)?;
+        while player.step(&mut engine) {}
+        match player.first_mismatch() {
+            Some(step) => Err(EngineError::Misc(format!(
+                "the replay diverged at step {}",
+                step
+            ))),
+            None => Ok(engine.checksum()),
+        }
+    }
+
+    /// Reset the engine to the start of the replay.
+    fn setup(&self, engine: &mut RaskEngine) -> Result<(), EngineError> {
+        engine.set_seed(self.seed);
+        engine.set_timestep(self.timestep);
+        engine.load_level(self.level.clone())?;
+        for player in self.players.iter() {
+            engine.spawn_player(*player);
+        }
+        if let Some(rules) = self.rules {
+            engine.start_match(rules);
+        }
+        Ok(())
+    }
+
+    /// Decode a replay.
+    pub fn deserialize(buf: &[u8]) -> Result<Self, EngineError> {
+        let mut reader = Reader::new(buf, || {
+            EngineError::ResourceFormat("the replay is truncated".into())
+        });
+        if reader.bytes(4)? != REPLAY_MAGIC {
+            return Err(EngineError::ResourceFormat("not a replay".into()));
+        }
+        let version = reader.bytes(1)?[0];
+        if version != REPLAY_VERSION {
+            return Err(EngineError::ResourceFormat(format!(
+                "unsupported replay version {}",
+                version
+            )));
+        }
+        let seed = reader.u64()?;
+        let timestep = Duration::from_nanos(reader.u64()?);
+        let len = reader.len(1)?;
+        let level = Level::from_slice(reader.bytes(len)?)?;
+        let rules = match reader.len(1)? {
+            0 => None,
+            len => Some(serde_json::from_slice(reader.bytes(len)?)?),
+        };
+        let players = (0..reader.len(4)?)
+            .map(|_| reader.u32())
+            .collect::<Result<Vec<_>, _>>()?;
+        let steps = reader.u32()?;
+        let inputs = (0..reader.len(12)?)
+            .map(|_| {
+                Ok(ReplayInput {
+                    step: reader.u32()?,
+                    player: reader.u32()?,
+                    actions: ActionState::from_bits(reader.u32()?),
+                })
+            })
+            .collect::<Result<Vec<_>, EngineError>>()?;
+        let checksums = (0..reader.len(12)?)
+            .map(|_| Ok((reader.u32()?, reader.u64()?)))
+            .collect::<Result<Vec<_>, EngineError>>()?;
+
+        if timestep == Duration::from_secs(0) {
+            return Err(EngineError::ResourceFormat(
+                "the timestep of the replay is zero".into(),
+            ));
+        }
+        let ordered = inputs.windows(2).all(|w| w[0].step <= w[1].step)
+            && checksums.windows(2).all(|w| w[0].0 < w[1].0);
+        let valid = inputs
+            .iter()
+            .all(|input| input.step < steps && players.contains(&input.player))
+            && checksums.iter().all(|(step, _)| *step <= steps);
+        if !ordered || !valid {
+            return Err(EngineError::ResourceFormat(
+                "the inputs of the replay are invalid".into(),
+            ));
+        }
+        Ok(Self {
+            seed,
+            timestep,
+            level,
+            rules,
+            players,
+            steps,
+            inputs,
+            checksums,
+        })
+    }
+}
+
+impl Serialize for Replay {
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(REPLAY_MAGIC);
+        buf.push(REPLAY_VERSION);
+        buf.extend_from_slice(&self.seed.to_le_bytes());
+        buf.extend_from_slice(&(self.timestep.as_nanos() as u64).to_le_bytes());
+        // the level and the rules only contain serializable types
+        let level = serde_json::to_vec(&self.level).unwrap();
+        add_u32_to_vec(buf, level.len() as u32);
+        buf.extend(level);
+        match self.rules {
+            Some(rules) => {
+                let rules = serde_json::to_vec(&rules).unwrap();
+                add_u32_to_vec(buf, rules.len() as u32);
+                buf.extend(rules);
+            }
+            None => add_u32_to_vec(buf, 0),
+        }
+        add_u32_to_vec(buf, self.players.len() as u32);
+        for player in self.players.iter() {
+            add_u32_to_vec(buf, *player);
+        }
+        add_u32_to_vec(buf, self.steps);
+        add_u32_to_vec(buf, self.inputs.len() as u32);
+        for input in self.inputs.iter() {
+            add_u32_to_vec(buf, input.step);
+            add_u32_to_vec(buf, input.player);
+            add_u32_to_vec(buf, input.actions.to_bits());
+        }
+        add_u32_to_vec(buf, self.checksums.len() as u32);
+        for (step, checksum) in self.checksums.iter() {
+            add_u32_to_vec(buf, *step);
+            buf.extend_from_slice(&checksum.to_le_bytes());
+        }
+    }
+}
+
+/// Records the actions of the players in every simulation step.
+#[derive(Debug)]
+pub struct ReplayRecorder {
+    replay: Replay,
+    /// The actions of the players if they don't change, in the order of `replay.players`.
+    expected: Vec<ActionState>,
+}
+
+impl ReplayRecorder {
+    /// Reset the engine to the start of the replay and start recording.
+    /// The engine should be a new deterministic engine, otherwise the replay may diverge.
+    pub fn new(mut replay: Replay, engine: &mut RaskEngine) -> Result<Self, EngineError> {
+        if !engine.is_deterministic() {
+            log::warn!("recording a replay of an engine that is not deterministic");
+        }
+        replay.setup(engine)?;
+        replay.steps = 0;
+        replay.inputs.clear();
+        replay.checksums.clear();
+        Ok(Self {
+            expected: vec![ActionState::default(); replay.players.len()],
+            replay,
+        })
+    }
+
+    /// Returns the replay recorded so far.
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Advance the engine by the given time and record the steps.
+    /// Returns the number of steps done.
+    pub fn tick(&mut self, engine: &mut RaskEngine, dt: Duration) -> u32 {
+        let steps = engine.advance(dt);
+        for _ in 0..steps {
+            self.step(engine);
+        }
+        steps
+    }
+
+    /// Do a single simulation step and record it.
+    pub fn step(&mut self, engine: &mut RaskEngine) {
+        engine.step();
+        self.record(engine);
+    }
+
+    /// Record the actions of the last simulation step.
+    /// This has to be called after every step done without the recorder.
+    pub fn record(&mut self, engine: &RaskEngine) {
+        let step = self.replay.steps;
+        for (player, expected) in self.replay.players.iter().zip(self.expected.iter_mut()) {
+            let actions = engine.actions(*player);
+            if actions != *expected {
+                self.replay.inputs.push(ReplayInput {
+                    step,
+                    player: *player,
+                    actions,
+                });
+            }
+            *expected = actions;
+            expected.next_tick();
+        }
+        self.replay.steps += 1;
+        let last = self.replay.checksums.last().map_or(0, |(step, _)| *step);
+        if self.replay.steps - last == CHECKSUM_INTERVAL {
+            self.replay
+                .checksums
+                .push((self.replay.steps, engine.checksum()));
+        }
+    }
+
+    /// Stop recording and return the replay, the engine has to be in the state after the last
+    /// recorded step.
+    pub fn finish(mut self, engine: &RaskEngine) -> Replay {
+        if self.replay.checksum().is_none() {
+            self.replay
+                .checksums
+                .push((self.replay.steps, engine.checksum()));
+        }
+        self.replay
+    }
+}
+
+/// Feeds the recorded actions of a replay into an engine.
+#[derive(Debug)]
+pub struct ReplayPlayer {
+    replay: Replay,
+    /// The number of played steps.
+    step: u32,
+    /// The index of the next recorded input.
+    next_input: usize,
+    /// The index of the next checksum to compare.
+    next_checksum: usize,
+    /// The actions of the players in the next step, in the order of `replay.players`.
+    actions: Vec<ActionState>,
+    first_mismatch: Option<u32>,
+}
+
+impl ReplayPlayer {
+    /// Reset the engine to the start of the replay, it should be a new deterministic engine.
+    pub fn new(replay: Replay, engine: &mut RaskEngine) -> Result<Self, EngineError> {
+        replay.setup(engine)?;
+        Ok(Self {
+            actions: vec![ActionState::default(); replay.players.len()],
+            replay,
+            step: 0,
+            next_input: 0,
+            next_checksum: 0,
+            first_mismatch: None,
+        })
+    }
+
+    /// Returns the replay.
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Returns the number of played steps.
+    pub fn position(&self) -> u32 {
+        self.step
+    }
+
+    /// Returns `true` if all steps were played.
+    pub fn is_finished(&self) -> bool {
+        self.step >= self.replay.steps
+    }
+
+    /// Returns the first step after which the checksum of the engine differed from the recorded
+    /// one.
+    pub fn first_mismatch(&self) -> Option<u32> {
+        self.first_mismatch
+    }
+
+    /// Advance the engine by the given time, but not beyond the end of the replay.
+    /// Returns the number of steps done.
+    pub fn tick(&mut self, engine: &mut RaskEngine, dt: Duration) -> u32 {
+        let steps = engine.advance(dt);
+        (0..steps).take_while(|_| self.step(engine)).count() as u32
+    }
+
+    /// Play the next step, returns `false` if the replay is finished.
+    /// The engine events of the previous steps are dropped, so they don't pile up while a whole
+    /// replay is verified. Poll them after every step to keep them.
+    pub fn step(&mut self, engine: &mut RaskEngine) -> bool {
+        if self.is_finished() {
+            return false;
+        }
+        let step = self.step;
+        let inputs = &self.replay.inputs[self.next_input..];
+        for input in inputs.iter().take_while(|input| input.step == step) {
+            // the players were checked when decoding the replay
+            if let Some(index) = self.replay.players.iter().position(|p| *p == input.player) {
+                self.actions[index] = input.actions;
+            }
+            self.next_input += 1;
+        }
+        for (player, actions) in self.replay.players.iter().zip(self.actions.iter_mut()) {
+            engine.set_actions(*player, *actions);
+            actions.next_tick();
+        }
+        engine.poll_events();
+        engine.step();
+        self.step += 1;
+
+        if let Some(&(step, recorded)) = self.replay.checksums.get(self.next_checksum) {
+            if step == self.step {
+                self.next_checksum += 1;
+                let checksum = engine.checksum();
+                if checksum != recorded && self.first_mismatch.is_none() {
+                    log::error!(
+                        "the replay diverged at step {}: checksum {:016x}, recorded {:016x}",
+                        step,
+                        checksum,
+                        recorded
+                    );
+                    self.first_mismatch = Some(step);
+                }
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,62 @@
+//! The resources shared by the systems of the game world.
+
+use specs::Entity;
+
+use crate::collide::Manifold;
+use crate::math::Vec2;
+
+/// The gravitational acceleration.
+#[derive(Debug, Default)]
+pub struct Gravitation(pub Vec2);
+
+/// The duration of a fixed simulation step.
+#[derive(Debug, Default)]
+pub struct DeltaTime(pub std::time::Duration);
+
+/// The number of fixed simulation steps done so far.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Tick(pub u64);
+
+/// The seed reserved for random numbers of the simulation.
+/// No system reads it yet, the simulation does not make random decisions. It is stored in replays
+/// so systems using random numbers can be added without changing the replay format.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Seed(pub u64);
+
+/// The events received since the last simulation step.
+/// They are handled and cleared during the next step.
+#[derive(Debug, Default)]
+pub struct EventQueue(pub Vec<crate::events::Event>);
+
+/// Action states of players set directly for the next simulation step, e.g. inputs received
+/// over the network. They replace the states resulting from the events.
+#[derive(Debug, Default)]
+pub struct ActionQueue(pub Vec<(u32, crate::input::ActionState)>);
+
+/// A contact between two entities found in the last physics step.
+#[derive(Debug, Clone, Copy)]
+pub struct Collision {
+    pub a: Entity,
+    pub b: Entity,
+    /// The contact manifold, its normal points from `b` to `a`.
+    pub manifold: Manifold,
+}
+
+/// All contacts found in the last physics step.
+#[derive(Debug, Default)]
+pub struct Collisions(pub Vec<Collision>);
+
+impl Collisions {
+    /// Returns the contacts of an entity, the normals always point towards the given entity.
+    pub fn of(&self, entity: Entity) -> impl Iterator<Item = (Entity, Manifold)> + '_ {
+        self.0.iter().filter_map(move |collision| {
+            if collision.a == entity {
+                Some((collision.b, collision.manifold))
+            } else if collision.b == entity {
+                Some((collision.a, collision.manifold.flipped()))
+            } else {
+                None
+            }
+        })
+    }
+}
@@ -0,0 +1,443 @@
+//! The rules module implements the game mode of a duel.
+//!
+//! The arena is divided into screens of equal width, only the current screen is playable.
+//! After a kill the killer gets the "right of way": the screen follows the killer towards the
+//! goal of the killer, while the other player respawns ahead of the advancing player.
+//! The first player to reach its goal wins the round. A match consists of several rounds and
+//! goes through the phases countdown, playing and round over until it is over.
+
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use specs::shrev::{EventChannel, ReaderId};
+
+use super::combat::CombatEvent;
+use super::components::*;
+use super::resources::*;
+use crate::boxes::BoundingBox;
+use crate::collide::Collide;
+use crate::math::Vec2;
+use crate::EngineError;
+
+/// The configuration of a match.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MatchRules {
+    /// The width of a screen in world units.
+    pub screen_width: f32,
+    /// The time in seconds before a round starts.
+    pub countdown: f32,
+    /// The time in seconds a killed player has to wait before respawning.
+    pub respawn_time: f32,
+    /// How far ahead of the advancing player a killed player respawns.
+    pub respawn_distance: f32,
+    /// The time in seconds between the end of a round and the countdown of the next one.
+    pub round_over_time: f32,
+    /// The number of won rounds needed to win the match.
+    pub rounds_to_win: u32,
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        Self {
+            screen_width: 16.0,
+            countdown: 3.0,
+            respawn_time: 1.5,
+            respawn_distance: 5.0,
+            round_over_time: 2.0,
+            rounds_to_win: 3,
+        }
+    }
+}
+
+/// The phase of a match.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "phase")]
+pub enum MatchPhase {
+    /// The round starts once the remaining time is over, the players can't move.
+    Countdown {
+        remaining: f32,
+    },
+    Playing,
+    /// A player has won the round, the next round starts after the remaining time.
+    RoundOver {
+        winner: u32,
+        remaining: f32,
+    },
+    MatchOver {
+        winner: u32,
+    },
+}
+
+/// A killed player waiting to respawn.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Respawn {
+    pub player: u32,
+    /// The remaining time in seconds.
+    pub remaining: f32,
+}
+
+/// The state of a match, it is stored as a resource while a match is running.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchState {
+    pub rules: MatchRules,
+    pub phase: MatchPhase,
+    /// The player that is allowed to advance.
+    pub right_of_way: Option<u32>,
+    /// The index of the current screen, the screen `0` is centered at the origin.
+    pub screen: i32,
+    /// The range of screens of the arena.
+    pub screens: (i32, i32),
+    pub respawns: Vec<Respawn>,
+    /// The number of rounds won by every player, indexed by the player id.
+    pub wins: Vec<u32>,
+    /// The number of kills of every player, indexed by the player id.
+    pub kills: Vec<u32>,
+}
+
+impl MatchState {
+    /// Creates a new match in an arena spanning the given horizontal range.
+    pub fn new(rules: MatchRules, arena: (f32, f32)) -> Self {
+        let screen_of = |x: f32| (x + rules.screen_width / 2.0) / rules.screen_width;
+        // the screens at the ends have to overlap with the arena
+        let first = screen_of(arena.0).floor() as i32;
+        let last = screen_of(arena.1).ceil() as i32 - 1;
+        Self {
+            rules,
+            phase: MatchPhase::Countdown {
+                remaining: rules.countdown,
+            },
+            right_of_way: None,
+            screen: 0i32.max(first).min(last),
+            screens: (first, last),
+            respawns: Vec::new(),
+            wins: Vec::new(),
+            kills: Vec::new(),
+        }
+    }
+
+    /// Returns `true` if the players can move.
+    pub fn is_playing(&self) -> bool {
+        self.phase == MatchPhase::Playing
+    }
+
+    /// Returns the horizontal range of the current screen.
+    pub fn screen_bounds(&self) -> (f32, f32) {
+        let center = self.screen as f32 * self.rules.screen_width;
+        let half = self.rules.screen_width / 2.0;
+        (center - half, center + half)
+    }
+
+    /// Returns the number of rounds won by a player.
+    pub fn wins_of(&self, player: u32) -> u32 {
+        self.wins.get(player as usize).copied().unwrap_or(0)
+    }
+
+    /// Returns the number of kills of a player.
+    pub fn kills_of(&self, player: u32) -> u32 {
+        self.kills.get(player as usize).copied().unwrap_or(0)
+    }
+
+    /// Serialize the state, e.g. to send it to the clients.
+    pub fn to_json(&self) -> Result<String, EngineError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Deserialize a state created by `to_json`.
+    pub fn from_json(json: &str) -> Result<Self, EngineError> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+fn increment(counts: &mut Vec<u32>, player: u32) -> u32 {
+    let index = player as usize;
+    if counts.len() <= index {
+        counts.resize(index + 1, 0);
+    }
+    counts[index] += 1;
+    counts[index]
+}
+
+/// Returns the horizontal direction in which a player has to advance to reach the own goal.
+fn advance_direction(goals: &ReadStorage<Goal>, player: u32) -> f32 {
+    (goals)
+        .join()
+        .find(|goal| goal.player == player)
+        .map(|goal| goal.area.pos.x() + goal.area.size.x() / 2.0)
+        .map_or(if player & 1 == 0 { 1.0 } else { -1.0 }, f32::signum)
+}
+
+/// Put a player back into the game at the given position.
+fn revive(player: &mut Player, pos: &mut Pos, vel: &mut Vel, at: Vec2, facing: Facing) {
+    *player = Player::new(player.id, facing);
+    pos.0 = at;
+    vel.0 = Vec2::zero();
+}
+
+/// Returns the spawn position of a player, the origin if the player has no spawn point.
+fn spawn_of(spawns: &[(u32, Vec2)], player: u32) -> Vec2 {
+    spawns
+        .iter()
+        .find(|(id, _)| *id == player)
+        .map_or(Vec2::zero(), |(_, pos)| *pos)
+}
+
+/// Returns the facing of a player standing at the given position towards the center.
+pub(crate) fn facing_towards_center(pos: Vec2) -> Facing {
+    if pos.x() > 0.0 {
+        Facing::Left
+    } else {
+        Facing::Right
+    }
+}
+
+/// Returns the horizontal range covered by the static geometry.
+pub(crate) fn arena_bounds(world: &World) -> (f32, f32) {
+    let (pos, collider, fixed) = (
+        world.read_storage::<Pos>(),
+        world.read_storage::<Collider>(),
+        world.read_storage::<Static>(),
+    );
+    (&pos, &collider, &fixed)
+        .join()
+        .map(|(pos, collider, _)| collider.at(pos.0).bounding_box())
+        .fold(None, |bounds: Option<(f32, f32)>, bbox| {
+            let (min, max) = (bbox.pos.x(), bbox.pos.x() + bbox.size.x());
+            Some(bounds.map_or((min, max), |(a, b)| (a.min(min), b.max(max))))
+        })
+        .unwrap_or((-f32::INFINITY, f32::INFINITY))
+}
+
+/// Runs the match state machine, it does nothing if there is no `MatchState`.
+/// It has to run after the combat is resolved.
+#[derive(Default)]
+pub struct MatchSystem {
+    reader: Option<ReaderId<CombatEvent>>,
+}
+
+impl<'a> System<'a> for MatchSystem {
+    type SystemData = (
+        Entities<'a>,
+        Option<Write<'a, MatchState>>,
+        Read<'a, EventChannel<CombatEvent>>,
+        Read<'a, DeltaTime>,
+        WriteStorage<'a, Player>,
+        WriteStorage<'a, Pos>,
+        WriteStorage<'a, Vel>,
+        ReadStorage<'a, SpawnPoint>,
+        ReadStorage<'a, Goal>,
+        ReadStorage<'a, Collider>,
+    );
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader = Some(
+            world
+                .fetch_mut::<EventChannel<CombatEvent>>()
+                .register_reader(),
+        );
+    }
+
+    fn run(
+        &mut self,
+        (entities, state, events, dt, mut player, mut pos, mut vel, spawn, goal, collider): Self::SystemData,
+    ) {
+        let reader = self
+            .reader
+            .as_mut()
+            .expect("the match system has not been set up");
+        // read the events in any case to not receive them when the match starts
+        let events: Vec<CombatEvent> = events.read(reader).copied().collect();
+        let mut state = match state {
+            Some(state) => state,
+            None => return,
+        };
+        let dt = dt.0.as_secs_f32();
+        let spawns: Vec<(u32, Vec2)> = (&pos, &spawn)
+            .join()
+            .map(|(pos, spawn)| (spawn.player, pos.0))
+            .collect();
+
+        match state.phase {
+            MatchPhase::Countdown { remaining } => {
+                state.phase = if remaining > dt {
+                    MatchPhase::Countdown {
+                        remaining: remaining - dt,
+                    }
+                } else {
+                    MatchPhase::Playing
+                };
+                return;
+            }
+            MatchPhase::RoundOver { winner, remaining } if remaining > dt => {
+                state.phase = MatchPhase::RoundOver {
+                    winner,
+                    remaining: remaining - dt,
+                };
+                return;
+            }
+            MatchPhase::RoundOver { .. } => {
+                // start the next round
+                let countdown = state.rules.countdown;
+                state.phase = MatchPhase::Countdown {
+                    remaining: countdown,
+                };
+                state.right_of_way = None;
+                state.screen = 0i32.max(state.screens.0).min(state.screens.1);
+                state.respawns.clear();
+                let starts: Vec<(Entity, Vec2)> = (&entities, &player)
+                    .join()
+                    .map(|(entity, player)| (entity, spawn_of(&spawns, player.id)))
+                    .collect();
+                for (entity, start) in starts {
+                    if let (Some(player), Some(pos), Some(vel)) = (
+                        player.get_mut(entity),
+                        pos.get_mut(entity),
+                        vel.get_mut(entity),
+                    ) {
+                        revive(player, pos, vel, start, facing_towards_center(start));
+                    }
+                }
+                return;
+            }
+            MatchPhase::MatchOver { .. } => return,
+            MatchPhase::Playing => (),
+        }
+
+        for event in events {
+            if let CombatEvent::Kill { killer, victim } = event {
+                increment(&mut state.kills, killer);
+                state.right_of_way = Some(killer);
+                let respawn_time = state.rules.respawn_time;
+                state.respawns.retain(|respawn| respawn.player != victim);
+                state.respawns.push(Respawn {
+                    player: victim,
+                    remaining: respawn_time,
+                });
+            }
+        }
+
+        // the position of the advancing player
+        let leader = state.right_of_way.and_then(|id| {
+            (&player, &pos)
+                .join()
+                .find(|(player, _)| player.id == id && player.state != PlayerState::Dead)
+                .map(|(_, pos)| (id, pos.0, advance_direction(&goal, id)))
+        });
+
+        // the screen follows the advancing player
+        if let Some((_, leader_pos, direction)) = leader {
+            let (left, right) = state.screen_bounds();
+            let next = if direction > 0.0 && leader_pos.x() > right {
+                state.screen + 1
+            } else if direction < 0.0 && leader_pos.x() < left {
+                state.screen - 1
+            } else {
+                state.screen
+            };
+            if next >= state.screens.0 && next <= state.screens.1 && next != state.screen {
+                state.screen = next;
+            }
+        }
+
+        // respawn the players whose time is up and the players left behind by the screen
+        let (left, right) = state.screen_bounds();
+        let mut due = Vec::new();
+        for respawn in state.respawns.iter_mut() {
+            respawn.remaining -= dt;
+            if respawn.remaining <= 0.0 {
+                due.push(respawn.player);
+            }
+        }
+        state.respawns.retain(|respawn| respawn.remaining > 0.0);
+        for (player, pos) in (&player, &pos).join() {
+            let outside = pos.0.x() < left || pos.0.x() > right;
+            let leading = Some(player.id) == state.right_of_way;
+            if outside && !leading && player.state != PlayerState::Dead && leader.is_some() {
+                due.push(player.id);
+            }
+        }
+        let margin = 1.0;
+        for id in due {
+            let entity = match (&entities, &player)
+                .join()
+                .find(|(_, player)| player.id == id)
+            {
+                Some((entity, _)) => entity,
+                None => continue,
+            };
+            let start = spawn_of(&spawns, id);
+            let (at, facing) = match leader {
+                Some((_, leader_pos, direction)) => {
+                    let x = (leader_pos.x() + direction * state.rules.respawn_distance)
+                        .max(left + margin)
+                        .min(right - margin);
+                    let facing = if direction > 0.0 {
+                        Facing::Left
+                    } else {
+                        Facing::Right
+                    };
+                    (Vec2::new(x, start.y()), facing)
+                }
+                None => (start, facing_towards_center(start)),
+            };
+            if let (Some(player), Some(pos), Some(vel)) = (
+                player.get_mut(entity),
+                pos.get_mut(entity),
+                vel.get_mut(entity),
+            ) {
+                revive(player, pos, vel, at, facing);
+            }
+        }
+
+        // nobody can leave the current screen, except the advancing player at the front edge
+        let screens = state.screens;
+        for (player, pos, vel) in (&player, &mut pos, &mut vel).join() {
+            let front = match leader {
+                Some((id, _, direction)) if id == player.id => {
+                    let next = state.screen + direction as i32;
+                    if next >= screens.0 && next <= screens.1 {
+                        Some(direction)
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            };
+            let x = pos.0.x();
+            let clamped = match front {
+                Some(direction) if direction > 0.0 => x.max(left),
+                Some(_) => x.min(right),
+                None => x.max(left).min(right),
+            };
+            if clamped != x {
+                pos.0 = Vec2::new(clamped, pos.0.y());
+                vel.0 = Vec2::new(0.0, vel.0.y());
+            }
+        }
+
+        // the first player inside the own goal wins the round
+        let winner = (&player, &pos, collider.maybe())
+            .join()
+            .filter(|(player, _, _)| player.state != PlayerState::Dead)
+            .find(|(player, pos, collider)| {
+                goal.join()
+                    .filter(|goal| goal.player == player.id)
+                    .any(|goal| match collider {
+                        Some(collider) => collider.at(pos.0).collides(&Collider::AABox(goal.area)),
+                        None => pos.0.collides(&goal.area),
+                    })
+            })
+            .map(|(player, _, _)| player.id);
+        if let Some(winner) = winner {
+            let wins = increment(&mut state.wins, winner);
+            state.phase = if wins >= state.rules.rounds_to_win {
+                MatchPhase::MatchOver { winner }
+            } else {
+                MatchPhase::RoundOver {
+                    winner,
+                    remaining: state.rules.round_over_time,
+                }
+            };
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! The state module saves and restores the simulation state of a world.
+//!
+//! Unlike a `WorldSnapshot`, a `SavedState` contains everything the following simulation steps
+//! depend on, so restoring it and repeating the same inputs leads to the same result. It refers
+//! to the entities directly and is only valid for the world it was taken from, as long as no
+//! moving entities were created or deleted in between.
+
+use specs::prelude::*;
+
+use super::components::*;
+use super::resources::*;
+use super::rules::MatchState;
+use crate::input::InputHandler;
+
+/// The state of a moving entity.
+#[derive(Debug, Clone, Copy)]
+struct EntityState {
+    entity: Entity,
+    pos: Pos,
+    prev_pos: Option<PrevPos>,
+    vel: Vel,
+    player: Option<Player>,
+}
+
+/// A copy of the simulation state of a world.
+#[derive(Debug, Clone)]
+pub struct SavedState {
+    tick: u64,
+    entities: Vec<EntityState>,
+    match_state: Option<MatchState>,
+    /// The contacts of the last step, the players use them to check if they are on the ground.
+    collisions: Vec<Collision>,
+    input: InputHandler,
+}
+
+impl SavedState {
+    /// Save the state of the world.
+    pub fn new(world: &World) -> Self {
+        let (entities, pos, prev_pos, vel, player) = (
+            world.entities(),
+            world.read_storage::<Pos>(),
+            world.read_storage::<PrevPos>(),
+            world.read_storage::<Vel>(),
+            world.read_storage::<Player>(),
+        );
+        Self {
+            tick: world.read_resource::<Tick>().0,
+            entities: (&entities, &pos, prev_pos.maybe(), &vel, player.maybe())
+                .join()
+                .map(|(entity, pos, prev_pos, vel, player)| EntityState {
+                    entity,
+                    pos: *pos,
+                    prev_pos: prev_pos.copied(),
+                    vel: *vel,
+                    player: player.copied(),
+                })
+                .collect(),
+            match_state: world
+                .try_fetch::<MatchState>()
+                .map(|state| MatchState::clone(&state)),
+            collisions: world.read_resource::<Collisions>().0.clone(),
+            input: InputHandler::clone(&world.read_resource::<InputHandler>()),
+        }
+    }
+
+    /// Returns the simulation step the state was saved after.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Reset the world to the saved state.
+    /// Entities deleted since the state was saved are ignored.
+    pub fn restore(&self, world: &mut World) {
+        {
+            let entities = world.entities();
+            let mut pos = world.write_storage::<Pos>();
+            let mut prev_pos = world.write_storage::<PrevPos>();
+            let mut vel = world.write_storage::<Vel>();
+            let mut player = world.write_storage::<Player>();
+            for state in self.entities.iter().filter(|s| entities.is_alive(s.entity)) {
+                // the entity is alive, so the insertions can't fail
+                pos.insert(state.entity, state.pos).unwrap();
+                vel.insert(state.entity, state.vel).unwrap();
+                match state.prev_pos {
+                    Some(p) => prev_pos.insert(state.entity, p).map(|_| ()).unwrap(),
+                    None => {
+                        prev_pos.remove(state.entity);
+                    }
+                }
+                match state.player {
+                    Some(p) => player.insert(state.entity, p).map(|_| ()).unwrap(),
+                    None => {
+                        player.remove(state.entity);
+                    }
+                }
+            }
+        }
+        match &self.match_state {
+            Some(state) => world.insert(state.clone()),
+            None => {
+                world.remove::<MatchState>();
+            }
+        }
+        world.write_resource::<Collisions>().0 = self.collisions.clone();
+        *world.write_resource::<InputHandler>() = self.input.clone();
+        world.write_resource::<Tick>().0 = self.tick;
+    }
+}
@@ -0,0 +1,77 @@
+//! The systems moving the entities of the game world.
+
+use specs::prelude::*;
+
+use super::components::*;
+use super::resources::*;
+use crate::input::InputHandler;
+
+pub struct VelocitySystem;
+pub struct GravitationSystem;
+/// Remembers the positions before a simulation step, it has to run before any system
+/// changing them.
+pub struct PrevPosSystem;
+
+/// Updates the action states of the players with the events and actions of the current step.
+pub struct InputSystem;
+
+impl<'a> System<'a> for InputSystem {
+    type SystemData = (
+        Read<'a, EventQueue>,
+        Read<'a, ActionQueue>,
+        Write<'a, InputHandler>,
+    );
+
+    fn run(&mut self, (events, actions, mut input): Self::SystemData) {
+        input.next_tick();
+        for event in events.0.iter() {
+            input.handle_event(event);
+        }
+        for &(player, state) in actions.0.iter() {
+            input.set_player(player, state);
+        }
+    }
+}
+
+impl<'a> System<'a> for PrevPosSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Pos>,
+        WriteStorage<'a, PrevPos>,
+    );
+
+    fn run(&mut self, (entities, pos, mut prev_pos): Self::SystemData) {
+        for (entity, pos) in (&entities, &pos).join() {
+            prev_pos.insert(entity, PrevPos(pos.0)).unwrap();
+        }
+    }
+}
+
+impl<'a> System<'a> for VelocitySystem {
+    type SystemData = (
+        WriteStorage<'a, Pos>,
+        ReadStorage<'a, Vel>,
+        Read<'a, DeltaTime>,
+    );
+
+    fn run(&mut self, (mut pos, vel, dt): Self::SystemData) {
+        for (vel, pos) in (&vel, &mut pos).join() {
+            pos.0 += vel.0 * dt.0.as_secs_f32();
+        }
+    }
+}
+
+impl<'a> System<'a> for GravitationSystem {
+    type SystemData = (
+        WriteStorage<'a, Vel>,
+        ReadStorage<'a, Static>,
+        Read<'a, Gravitation>,
+        Read<'a, DeltaTime>,
+    );
+
+    fn run(&mut self, (mut vel, is_static, g, dt): Self::SystemData) {
+        for (vel, ()) in (&mut vel, !&is_static).join() {
+            vel.0 += g.0 * dt.0.as_secs_f32();
+        }
+    }
+}
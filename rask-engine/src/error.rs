@@ -49,3 +49,4 @@ derive_from!(image::error::ImageError, ResourceType);
 derive_from!(spine::skeleton::error::SkeletonError, ResourceFormat);
 derive_from!(spine::atlas::AtlasError, ResourceFormat);
 derive_from!(std::io::Error, FileError);
+derive_from!(serde_json::Error, ResourceFormat);
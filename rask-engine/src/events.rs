@@ -1,7 +1,7 @@
 #![allow(clippy::unreadable_literal)]
 //! this module contains The game input/output event definition
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub struct Key(u32);
 
 impl From<u32> for Key {
@@ -10,6 +10,12 @@ impl From<u32> for Key {
     }
 }
 
+impl From<Key> for u32 {
+    fn from(x: Key) -> Self {
+        x.0
+    }
+}
+
 impl Key {
     pub const UNKNOWN: Key = Key(0);
     pub const CONTROL_LEFT: Key = Key(4247333604);
@@ -97,7 +103,7 @@ impl Key {
     pub const F12: Key = Key(68839);
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Event {
     KeyDown(KeyModifier, Key),
     KeyUp(KeyModifier, Key),
@@ -106,11 +112,37 @@ pub enum Event {
     MouseUp(MouseEvent),
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash)]
 #[repr(C)]
 pub struct KeyModifier(u8);
 
 impl KeyModifier {
+    pub const NONE: KeyModifier = KeyModifier(0);
+    pub const SHIFT: KeyModifier = KeyModifier(1);
+    pub const CONTROL: KeyModifier = KeyModifier(1 << 1);
+    pub const ALT: KeyModifier = KeyModifier(1 << 2);
+    pub const META: KeyModifier = KeyModifier(1 << 3);
+
+    /// Creates the modifiers from a bitmask, see `bits`.
+    pub const fn from_bits(bits: u8) -> Self {
+        KeyModifier(bits)
+    }
+
+    /// Returns the bitmask of the set modifiers.
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Returns `true` if all modifiers of `other` are also set in `self`.
+    pub const fn contains(self, other: KeyModifier) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns the number of set modifiers.
+    pub const fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
     pub const fn shift(self) -> bool {
         self.0 & 1 != 0
     }
@@ -128,7 +160,15 @@ impl KeyModifier {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+impl core::ops::BitOr for KeyModifier {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        KeyModifier(self.0 | other.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(C)]
 pub struct MouseEvent {
     buttons: u8,
@@ -138,6 +178,21 @@ pub struct MouseEvent {
 }
 
 impl MouseEvent {
+    /// Creates a new mouse event, `buttons` is a bitmask of the pressed buttons.
+    pub const fn new(buttons: u8, modifier: KeyModifier, x: i32, y: i32) -> Self {
+        Self {
+            buttons,
+            modifier,
+            x,
+            y,
+        }
+    }
+
+    /// Returns the bitmask of all pressed buttons.
+    pub const fn buttons(&self) -> u8 {
+        self.buttons
+    }
+
     pub fn left_mb(&self) -> bool {
         self.buttons & 1 != 0
     }
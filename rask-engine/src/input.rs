@@ -0,0 +1,361 @@
+//! The input module maps raw input events to game actions.
+//!
+//! An `InputMap` binds keys and mouse buttons, optionally combined with modifiers, to the actions
+//! of a player. Several players can share one keyboard by using different keys.
+//! The `InputHandler` applies the bindings to incoming events and tracks for every player which
+//! actions are held and which were pressed or released during the current tick.
+
+use crate::events::{Event, Key, KeyModifier, MouseEvent};
+
+/// An action a player can perform.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[repr(u8)]
+pub enum Action {
+    Left,
+    Right,
+    /// Raise the weapon.
+    Up,
+    /// Lower the weapon.
+    Down,
+    Jump,
+    Thrust,
+    Parry,
+}
+
+impl Action {
+    /// All actions.
+    pub const ALL: [Action; 7] = [
+        Action::Left,
+        Action::Right,
+        Action::Up,
+        Action::Down,
+        Action::Jump,
+        Action::Thrust,
+        Action::Parry,
+    ];
+
+    const fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// A mouse button.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
+impl MouseButton {
+    const ALL: [MouseButton; 3] = [MouseButton::Left, MouseButton::Right, MouseButton::Middle];
+
+    /// Returns the bit of the button in `MouseEvent::buttons`.
+    const fn bit(self) -> u8 {
+        match self {
+            MouseButton::Left => 1,
+            MouseButton::Right => 1 << 1,
+            MouseButton::Middle => 1 << 2,
+        }
+    }
+}
+
+/// A physical input that can be bound to an action.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Input {
+    Key(Key),
+    Mouse(MouseButton),
+}
+
+/// A binding of an input to an action of a player.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Binding {
+    pub input: Input,
+    /// The modifiers that have to be held to trigger the binding.
+    pub modifier: KeyModifier,
+    pub player: u32,
+    pub action: Action,
+}
+
+/// The bindings of all players.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputMap {
+    bindings: Vec<Binding>,
+}
+
+impl InputMap {
+    /// Creates a new map without any bindings.
+    pub fn new() -> Self {
+        Self {
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Returns all bindings.
+    pub fn bindings(&self) -> &[Binding] {
+        &self.bindings
+    }
+
+    /// Add a binding, an existing binding of the same input and modifiers is replaced.
+    pub fn bind(&mut self, player: u32, action: Action, input: Input, modifier: KeyModifier) {
+        self.unbind(input, modifier);
+        self.bindings.push(Binding {
+            input,
+            modifier,
+            player,
+            action,
+        });
+    }
+
+    /// Replace all bindings of an action of a player by a new one.
+    pub fn rebind(&mut self, player: u32, action: Action, input: Input, modifier: KeyModifier) {
+        self.bindings
+            .retain(|b| !(b.player == player && b.action == action));
+        self.bind(player, action, input, modifier);
+    }
+
+    /// Remove the binding of an input with the given modifiers.
+    pub fn unbind(&mut self, input: Input, modifier: KeyModifier) {
+        self.bindings
+            .retain(|b| !(b.input == input && b.modifier == modifier));
+    }
+
+    /// Returns the bindings of an action of a player.
+    pub fn bindings_of(&self, player: u32, action: Action) -> impl Iterator<Item = &Binding> {
+        self.bindings
+            .iter()
+            .filter(move |b| b.player == player && b.action == action)
+    }
+
+    /// Find the binding triggered by an input while the given modifiers are held.
+    /// If several bindings match, the one requiring the most modifiers is used,
+    /// e.g. shift + A is preferred over A.
+    pub fn lookup(&self, input: Input, modifier: KeyModifier) -> Option<&Binding> {
+        self.bindings
+            .iter()
+            .filter(|b| b.input == input && modifier.contains(b.modifier))
+            .max_by_key(|b| b.modifier.count())
+    }
+}
+
+impl Default for InputMap {
+    /// Two players sharing one keyboard, the first one on the left and the second one on the
+    /// right side.
+    fn default() -> Self {
+        let mut map = Self::new();
+        let layouts = [
+            (
+                0,
+                [
+                    (Action::Left, Key::KEY_A),
+                    (Action::Right, Key::KEY_D),
+                    (Action::Up, Key::KEY_W),
+                    (Action::Down, Key::KEY_S),
+                    (Action::Jump, Key::KEY_F),
+                    (Action::Thrust, Key::KEY_G),
+                    (Action::Parry, Key::KEY_H),
+                ],
+            ),
+            (
+                1,
+                [
+                    (Action::Left, Key::ARROW_LEFT),
+                    (Action::Right, Key::ARROW_RIGHT),
+                    (Action::Up, Key::ARROW_UP),
+                    (Action::Down, Key::ARROW_DOWN),
+                    (Action::Jump, Key::COMMA),
+                    (Action::Thrust, Key::PERIOD),
+                    (Action::Parry, Key::SLASH),
+                ],
+            ),
+        ];
+        for (player, bindings) in layouts.iter() {
+            for &(action, key) in bindings.iter() {
+                map.bind(*player, action, Input::Key(key), KeyModifier::NONE);
+            }
+        }
+        map
+    }
+}
+
+/// The state of the actions of a player during one tick.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct ActionState {
+    held: u8,
+    pressed: u8,
+    released: u8,
+}
+
+impl ActionState {
+    /// Returns `true` if the action is currently held.
+    pub fn held(self, action: Action) -> bool {
+        self.held & action.bit() != 0
+    }
+
+    /// Returns `true` if the action was pressed during this tick.
+    /// It is possible that it was also released again in the same tick.
+    pub fn pressed(self, action: Action) -> bool {
+        self.pressed & action.bit() != 0
+    }
+
+    /// Returns `true` if the action was released during this tick.
+    pub fn released(self, action: Action) -> bool {
+        self.released & action.bit() != 0
+    }
+
+    /// Press an action, pressing a held action has no effect.
+    pub fn press(&mut self, action: Action) {
+        if !self.held(action) {
+            self.held |= action.bit();
+            self.pressed |= action.bit();
+        }
+    }
+
+    /// Release an action, releasing an action that is not held has no effect.
+    pub fn release(&mut self, action: Action) {
+        if self.held(action) {
+            self.held &= !action.bit();
+            self.released |= action.bit();
+        }
+    }
+
+    /// Start a new tick, the held actions stay held.
+    pub fn next_tick(&mut self) {
+        self.pressed = 0;
+        self.released = 0;
+    }
+
+    /// Encode the action state into the lower 24 bits of an integer, e.g. to send it over the
+    /// network.
+    pub fn to_bits(self) -> u32 {
+        self.held as u32 | (self.pressed as u32) << 8 | (self.released as u32) << 16
+    }
+
+    /// Decode an action state encoded by `to_bits`.
+    pub fn from_bits(bits: u32) -> Self {
+        Self {
+            held: bits as u8,
+            pressed: (bits >> 8) as u8,
+            released: (bits >> 16) as u8,
+        }
+    }
+
+    /// Returns the horizontal direction the player wants to move in, `-1`, `0` or `1`.
+    pub fn direction(self) -> f32 {
+        match (self.held(Action::Left), self.held(Action::Right)) {
+            (true, false) => -1.0,
+            (false, true) => 1.0,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Applies an `InputMap` to events and keeps track of the action states of all players.
+#[derive(Debug, Clone, Default)]
+pub struct InputHandler {
+    map: InputMap,
+    players: Vec<ActionState>,
+    /// The inputs currently held and the bindings they triggered,
+    /// so the same action is released even if the modifiers changed in between.
+    active: Vec<(Input, u32, Action)>,
+    mouse_buttons: u8,
+}
+
+impl InputHandler {
+    /// Creates a new input handler using the given bindings.
+    pub fn new(map: InputMap) -> Self {
+        Self {
+            map,
+            ..Self::default()
+        }
+    }
+
+    /// Returns the bindings.
+    pub fn map(&self) -> &InputMap {
+        &self.map
+    }
+
+    /// Returns the bindings, e.g. to rebind an action.
+    pub fn map_mut(&mut self) -> &mut InputMap {
+        &mut self.map
+    }
+
+    /// Returns the action state of a player.
+    pub fn player(&self, player: u32) -> ActionState {
+        self.players
+            .get(player as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Override the action state of a player, e.g. with an input received over the network.
+    pub fn set_player(&mut self, player: u32, state: ActionState) {
+        *self.player_mut(player) = state;
+    }
+
+    fn player_mut(&mut self, player: u32) -> &mut ActionState {
+        let index = player as usize;
+        if self.players.len() <= index {
+            self.players.resize(index + 1, ActionState::default());
+        }
+        &mut self.players[index]
+    }
+
+    /// Start a new tick, this clears the pressed and released states.
+    pub fn next_tick(&mut self) {
+        for state in self.players.iter_mut() {
+            state.next_tick();
+        }
+    }
+
+    fn press(&mut self, input: Input, modifier: KeyModifier) {
+        if self.active.iter().any(|(active, _, _)| *active == input) {
+            // key repeat
+            return;
+        }
+        if let Some(binding) = self.map.lookup(input, modifier).copied() {
+            self.active.push((input, binding.player, binding.action));
+            self.player_mut(binding.player).press(binding.action);
+        }
+    }
+
+    fn release(&mut self, input: Input) {
+        if let Some(index) = self
+            .active
+            .iter()
+            .position(|(active, _, _)| *active == input)
+        {
+            let (_, player, action) = self.active.swap_remove(index);
+            // the action stays held, if another input is still bound to it
+            if !self
+                .active
+                .iter()
+                .any(|&(_, p, a)| p == player && a == action)
+            {
+                self.player_mut(player).release(action);
+            }
+        }
+    }
+
+    fn handle_mouse(&mut self, event: &MouseEvent) {
+        let (old, new) = (self.mouse_buttons, event.buttons());
+        self.mouse_buttons = new;
+        for &button in MouseButton::ALL.iter() {
+            match (old & button.bit() != 0, new & button.bit() != 0) {
+                (false, true) => self.press(Input::Mouse(button), event.modifier),
+                (true, false) => self.release(Input::Mouse(button)),
+                _ => (),
+            }
+        }
+    }
+
+    /// Update the action states according to an event.
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            Event::KeyDown(modifier, key) => self.press(Input::Key(*key), *modifier),
+            Event::KeyUp(_, key) => self.release(Input::Key(*key)),
+            Event::MouseDown(event) | Event::MouseUp(event) => self.handle_mouse(event),
+            Event::KeyPress(_, _) => (),
+        }
+    }
+}
@@ -1,10 +1,14 @@
 use core::iter::{once, Chain, Once};
 use core::ops;
 
+use serde::{Deserialize, Serialize};
+
 use crate::math::EPSILON;
 
 /// A 2-dimensional euclidean vector with `f32` elements.
-#[derive(Clone, Copy, Debug, Default)]
+/// It is serialized as an array `[x, y]`.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(from = "[f32; 2]", into = "[f32; 2]")]
 pub struct Vec2 {
     // The x coordinate.
     x: f32,
@@ -181,6 +185,17 @@ impl Vec2 {
         self.x * other.x + self.y * other.y
     }
 
+    /// Returns the z coordinate of the cross product, it is positive if `other` is
+    /// counterclockwise of the vector.
+    pub fn cross(self, other: Self) -> f32 {
+        self.x * other.y - self.y * other.x
+    }
+
+    /// Returns the vector rotated by 90 degrees counterclockwise.
+    pub fn perp(self) -> Self {
+        Self::new(-self.y, self.x)
+    }
+
     /// Returns the square of the euclidean norm of the vector.
     pub fn norm2(self) -> f32 {
         self.dot(self)
@@ -0,0 +1,116 @@
+//! The desync module detects when two simulations of the same game diverge.
+//!
+//! After every simulation step, both sides compute the checksum of their replicated state (see
+//! `WorldSnapshot::checksum`) and one of them sends it to the other with a `PushChecksum` packet.
+//! The `DesyncDetector` keeps the recent local checksums and compares the received ones with them.
+//! Checksums of steps that were not simulated locally yet are kept until they are.
+//!
+//! The engines must be deterministic for the checksums to match, see
+//! `RaskEngine::new_deterministic`.
+
+use std::collections::VecDeque;
+
+/// Compares the checksums of the local and a remote simulation.
+#[derive(Debug)]
+pub struct DesyncDetector {
+    /// The maximum number of checksums kept of each side.
+    history: usize,
+    /// The recent local checksums by tick, ordered by tick.
+    local: VecDeque<(u64, u64)>,
+    /// The remote checksums of ticks not simulated locally yet.
+    remote: VecDeque<(u64, u64)>,
+    /// The first tick the checksums differed at.
+    first_desync: Option<u64>,
+    desyncs: u32,
+}
+
+impl DesyncDetector {
+    /// Creates a detector keeping `history` checksums of each side.
+    pub fn new(history: usize) -> Self {
+        Self {
+            history,
+            local: VecDeque::new(),
+            remote: VecDeque::new(),
+            first_desync: None,
+            desyncs: 0,
+        }
+    }
+
+    /// Returns the first tick the checksums differed at.
+    pub fn first_desync(&self) -> Option<u64> {
+        self.first_desync
+    }
+
+    /// Returns the number of ticks the checksums differed at.
+    pub fn desyncs(&self) -> u32 {
+        self.desyncs
+    }
+
+    /// Record the local checksum after the simulation step `tick`.
+    /// Returns `Some(true)` if a remote checksum of the step was received before and matches.
+    pub fn record(&mut self, tick: u64, checksum: u64) -> Option<bool> {
+        // the step may have been simulated again, e.g. after a rollback
+        while matches!(self.local.back(), Some(&(last, _)) if last >= tick) {
+            self.local.pop_back();
+        }
+        if self.local.len() == self.history {
+            self.local.pop_front();
+        }
+        self.local.push_back((tick, checksum));
+
+        while matches!(self.remote.front(), Some(&(remote, _)) if remote < tick) {
+            self.remote.pop_front();
+        }
+        match self.remote.front() {
+            Some(&(remote, received)) if remote == tick => {
+                self.remote.pop_front();
+                Some(self.compare(tick, checksum, received))
+            }
+            _ => None,
+        }
+    }
+
+    /// Compare a remote checksum of the simulation step `tick` with the local one.
+    /// Returns `Some(false)` if they differ, the desync is logged as an error. Returns `None` if
+    /// the local checksum is not known, it is compared when the step is recorded unless the step
+    /// is too old.
+    pub fn check(&mut self, tick: u64, checksum: u64) -> Option<bool> {
+        if let Some(&(_, local)) = self.local.iter().find(|(t, _)| *t == tick) {
+            return Some(self.compare(tick, local, checksum));
+        }
+        if matches!(self.local.back(), Some(&(last, _)) if last >= tick) {
+            log::debug!("the checksum of tick {} is too old to be compared", tick);
+            return None;
+        }
+        let index = self
+            .remote
+            .iter()
+            .position(|(t, _)| *t >= tick)
+            .unwrap_or(self.remote.len());
+        match self.remote.get(index) {
+            Some(&(t, _)) if t == tick => self.remote[index].1 = checksum,
+            _ => self.remote.insert(index, (tick, checksum)),
+        }
+        if self.remote.len() > self.history {
+            self.remote.pop_back();
+        }
+        None
+    }
+
+    fn compare(&mut self, tick: u64, local: u64, remote: u64) -> bool {
+        if local == remote {
+            return true;
+        }
+        log::error!(
+            "desync at tick {}: local checksum {:016x}, remote checksum {:016x}",
+            tick,
+            local,
+            remote
+        );
+        self.desyncs += 1;
+        if !matches!(self.first_desync, Some(first) if first <= tick) {
+            self.first_desync = Some(tick);
+        }
+        false
+    }
+}
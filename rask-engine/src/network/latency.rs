@@ -0,0 +1,147 @@
+//! The latency module measures the connection to a remote peer with ping and pong packets.
+//!
+//! The server sends a `Ping` packet with an id in a fixed interval and the client answers with a
+//! `Pong` packet containing the same id and the time of its clock when the ping arrived.
+//! The `Heartbeat` estimates the round trip time like TCP does (RFC 6298) and the offset of the
+//! client clock to the clock of the server, assuming the ping and the pong take equally long.
+//! A peer that sent nothing for longer than the timeout is considered dead.
+//!
+//! All times are passed by the caller as the time since an arbitrary fixed instant of the server,
+//! so the estimation can be tested without waiting.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// The maximum number of pings waiting for a pong, older ones are considered lost.
+const MAX_PENDING_PINGS: usize = 8;
+/// The weight of a new sample in the smoothed values, as in RFC 6298.
+const ALPHA: f64 = 1.0 / 8.0;
+/// The weight of a new sample in the round trip time variation, as in RFC 6298.
+const BETA: f64 = 1.0 / 4.0;
+
+/// Measures the round trip time and the clock offset of a remote peer and detects timeouts.
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    /// The time between two pings.
+    interval: Duration,
+    /// The time without any packet of the peer after which it is considered dead.
+    timeout: Duration,
+    /// The id of the next ping.
+    next_id: u32,
+    /// The ids and send times of the pings without a pong, ordered by id.
+    pending: VecDeque<(u32, Duration)>,
+    /// The time the last ping was sent.
+    last_ping: Option<Duration>,
+    /// The time the last packet of the peer was received.
+    last_received: Duration,
+    /// The smoothed round trip time in seconds.
+    rtt: Option<f64>,
+    /// The round trip time variation in seconds.
+    rtt_var: f64,
+    /// The smoothed offset of the client clock to the server clock in microseconds.
+    offset: Option<f64>,
+}
+
+impl Heartbeat {
+    /// Creates a heartbeat for a peer connected at `now`.
+    pub fn new(interval: Duration, timeout: Duration, now: Duration) -> Self {
+        Self {
+            interval,
+            timeout,
+            next_id: 1,
+            pending: VecDeque::new(),
+            last_ping: None,
+            last_received: now,
+            rtt: None,
+            rtt_var: 0.0,
+            offset: None,
+        }
+    }
+
+    /// Returns the id of the ping to send if it is time for the next one.
+    pub fn ping(&mut self, now: Duration) -> Option<u32> {
+        if matches!(self.last_ping, Some(last) if now < last + self.interval) {
+            return None;
+        }
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        if self.pending.len() == MAX_PENDING_PINGS {
+            self.pending.pop_front();
+        }
+        self.pending.push_back((id, now));
+        self.last_ping = Some(now);
+        Some(id)
+    }
+
+    /// Handle the pong of the ping `id`, `client_time` is the time of the client clock in
+    /// microseconds when the ping arrived.
+    /// Returns the round trip time of the ping or `None` if it is unknown or was lost.
+    pub fn pong(&mut self, now: Duration, id: u32, client_time: u64) -> Option<Duration> {
+        self.received(now);
+        let index = self.pending.iter().position(|(i, _)| *i == id)?;
+        let (_, sent) = self.pending[index];
+        // earlier pings are answered in order, so they are lost
+        self.pending.drain(..=index);
+        let sample = now.checked_sub(sent)?;
+
+        let seconds = sample.as_secs_f64();
+        match self.rtt {
+            Some(rtt) => {
+                self.rtt_var = (1.0 - BETA) * self.rtt_var + BETA * (rtt - seconds).abs();
+                self.rtt = Some((1.0 - ALPHA) * rtt + ALPHA * seconds);
+            }
+            None => {
+                self.rtt_var = seconds / 2.0;
+                self.rtt = Some(seconds);
+            }
+        }
+
+        // the ping arrived half a round trip after it was sent
+        let arrival = (sent + sample / 2).as_micros() as f64;
+        let offset = client_time as f64 - arrival;
+        self.offset = Some(match self.offset {
+            Some(old) => (1.0 - ALPHA) * old + ALPHA * offset,
+            None => offset,
+        });
+        Some(sample)
+    }
+
+    /// Record that a packet of the peer was received at `now`.
+    pub fn received(&mut self, now: Duration) {
+        if now > self.last_received {
+            self.last_received = now;
+        }
+    }
+
+    /// Returns true if no packet of the peer was received for longer than the timeout.
+    pub fn is_timed_out(&self, now: Duration) -> bool {
+        now > self.last_received + self.timeout
+    }
+
+    /// Returns the smoothed round trip time.
+    pub fn rtt(&self) -> Option<Duration> {
+        self.rtt.map(Duration::from_secs_f64)
+    }
+
+    /// Returns the variation of the round trip time.
+    pub fn jitter(&self) -> Option<Duration> {
+        self.rtt.map(|_| Duration::from_secs_f64(self.rtt_var))
+    }
+
+    /// Returns the estimated time a packet takes to the peer, half of the round trip time.
+    pub fn latency(&self) -> Option<Duration> {
+        self.rtt().map(|rtt| rtt / 2)
+    }
+
+    /// Returns the offset of the client clock to the server clock in microseconds.
+    pub fn clock_offset(&self) -> Option<i64> {
+        self.offset.map(|offset| offset.round() as i64)
+    }
+
+    /// Converts a time of the server to the time of the client clock in microseconds,
+    /// e.g. the start of a simulation step.
+    pub fn client_time(&self, server_time: Duration) -> Option<i64> {
+        self.clock_offset()
+            .map(|offset| server_time.as_micros() as i64 + offset)
+    }
+}
@@ -1,2 +1,7 @@
+pub mod desync;
+pub mod latency;
 pub mod packet;
+pub mod prediction;
 pub mod protocol;
+pub mod rollback;
+pub mod snapshot;
@@ -1,7 +1,11 @@
+use std::convert::TryInto;
 use std::io::Read;
 
 use super::protocol::{op_codes, resource_types, Opcode};
 use crate::error::EngineError;
+use crate::events::{Event, Key, KeyModifier, MouseEvent};
+use crate::input::ActionState;
+use crate::math::Vec2;
 use crate::resources::registry::{CharacterInfo, ResourceInfo, ResourceVariant};
 
 pub trait Serialize {
@@ -44,6 +48,62 @@ impl GameState {
 pub enum PacketVariant<'a> {
     PushResource(NetworkResource<'a>),
     PushGameState(GameState),
+    /// An encoded `WorldSnapshot`, see the `snapshot` module.
+    /// `input` is the sequence number of the last input of the client applied by the server,
+    /// `0` if none was applied yet.
+    PushSnapshot {
+        input: u32,
+        data: &'a [u8],
+    },
+    /// The tick of a snapshot received by the client.
+    AckSnapshot(u64),
+    /// The actions of the player of the client during one simulation step.
+    PushInput {
+        sequence: u32,
+        actions: ActionState,
+    },
+    /// The inputs of a peer of a rollback session starting at the frame `start`.
+    /// `ack` is the number of inputs of the other peer received so far.
+    PushRollbackInput {
+        ack: u32,
+        start: u32,
+        inputs: Vec<ActionState>,
+    },
+    /// The checksum of the replicated state after the simulation step `tick`.
+    PushChecksum {
+        tick: u64,
+        checksum: u64,
+    },
+    /// An input event of the client, the op code depends on the kind of the event, see
+    /// `event_op_code`. `sequence` counts the events of a client, so lost or reordered events
+    /// can be detected.
+    Input {
+        sequence: u32,
+        event: Event,
+    },
+    /// An event relayed by the client, encoded as the op code of its kind followed by the
+    /// payload of an input event without the sequence number.
+    PushEngineEvent(Event),
+    /// A chat message of the client.
+    Chat(&'a str),
+    /// The client is ready to start the match, or no longer ready.
+    Ready(bool),
+    /// The client leaves the group.
+    Leave,
+    /// A ping of the server, sent while the simulation step `tick` is the current one.
+    Ping {
+        id: u32,
+        tick: u64,
+    },
+    /// The answer of the client to the ping `id`, `client_time` is the time of the client clock
+    /// in microseconds when the ping arrived. See the `latency` module.
+    Pong {
+        id: u32,
+        client_time: u64,
+    },
+    /// The session token of the client, a client reconnecting with it within a grace period
+    /// resumes its slot in the game.
+    PushSession(u64),
 }
 
 #[repr(C)]
@@ -117,6 +177,14 @@ impl<'a> Serialize for WebSocketPacket<'a> {
 }
 
 impl<'a> WebSocketPacket<'a> {
+    /// Creates a packet with the op code of the payload.
+    pub fn new(payload: PacketVariant<'a>) -> Self {
+        Self {
+            op_code: payload.op_code(),
+            payload,
+        }
+    }
+
     pub fn deserialize(buf: &'a [u8]) -> Result<Self, EngineError> {
         let op_code = u32_from_le(buf)?;
         Ok(Self {
@@ -150,11 +218,73 @@ impl<'a> Serialize for PacketVariant<'a> {
         match self {
             Self::PushResource(data) => data.serialize(buf),
             Self::PushGameState(data) => data.serialize(buf),
+            Self::PushSnapshot { input, data } => {
+                add_u32_to_vec(buf, *input);
+                buf.extend(*data);
+            }
+            Self::AckSnapshot(tick) => buf.extend_from_slice(&tick.to_le_bytes()),
+            Self::PushInput { sequence, actions } => {
+                add_u32_to_vec(buf, *sequence);
+                add_u32_to_vec(buf, actions.to_bits());
+            }
+            Self::PushRollbackInput { ack, start, inputs } => {
+                add_u32_to_vec(buf, *ack);
+                add_u32_to_vec(buf, *start);
+                add_u32_to_vec(buf, inputs.len() as u32);
+                for input in inputs {
+                    add_u32_to_vec(buf, input.to_bits());
+                }
+            }
+            Self::PushChecksum { tick, checksum } => {
+                buf.extend_from_slice(&tick.to_le_bytes());
+                buf.extend_from_slice(&checksum.to_le_bytes());
+            }
+            Self::Input { sequence, event } => {
+                add_u32_to_vec(buf, *sequence);
+                serialize_event(buf, event);
+            }
+            Self::PushEngineEvent(event) => {
+                add_u32_to_vec(buf, event_op_code(event));
+                serialize_event(buf, event);
+            }
+            Self::Chat(message) => buf.extend_from_slice(message.as_bytes()),
+            Self::Ready(ready) => add_u32_to_vec(buf, *ready as u32),
+            Self::Leave => (),
+            Self::Ping { id, tick } => {
+                add_u32_to_vec(buf, *id);
+                buf.extend_from_slice(&tick.to_le_bytes());
+            }
+            Self::Pong { id, client_time } => {
+                add_u32_to_vec(buf, *id);
+                buf.extend_from_slice(&client_time.to_le_bytes());
+            }
+            Self::PushSession(token) => buf.extend_from_slice(&token.to_le_bytes()),
         }
     }
 }
 
 impl<'a> PacketVariant<'a> {
+    /// Returns the op code of a packet with this payload.
+    pub fn op_code(&self) -> Opcode {
+        match self {
+            Self::PushResource(_) => op_codes::PUSH_RESOURCE,
+            Self::PushGameState(_) => op_codes::PUSH_GAME_STATE,
+            Self::PushSnapshot { .. } => op_codes::PUSH_SNAPSHOT,
+            Self::AckSnapshot(_) => op_codes::ACK_SNAPSHOT,
+            Self::PushInput { .. } => op_codes::PUSH_INPUT,
+            Self::PushRollbackInput { .. } => op_codes::PUSH_ROLLBACK_INPUT,
+            Self::PushChecksum { .. } => op_codes::PUSH_CHECKSUM,
+            Self::Input { event, .. } => event_op_code(event),
+            Self::PushEngineEvent(_) => op_codes::PUSH_ENGINE_EVENT,
+            Self::Chat(_) => op_codes::CHAT,
+            Self::Ready(_) => op_codes::READY,
+            Self::Leave => op_codes::LEAVE,
+            Self::Ping { .. } => op_codes::PING,
+            Self::Pong { .. } => op_codes::PONG,
+            Self::PushSession(_) => op_codes::PUSH_SESSION,
+        }
+    }
+
     fn deserialize(buf: &'a [u8], packet_variant: u32) -> Result<Self, EngineError> {
         match packet_variant {
             op_codes::PUSH_RESOURCE => {
@@ -163,6 +293,64 @@ impl<'a> PacketVariant<'a> {
             op_codes::PUSH_GAME_STATE => {
                 GameState::deserialize(buf).map(PacketVariant::PushGameState)
             }
+            op_codes::PUSH_SNAPSHOT if buf.len() >= 4 => Ok(PacketVariant::PushSnapshot {
+                input: u32_from_le(buf)?,
+                data: &buf[4..],
+            }),
+            op_codes::ACK_SNAPSHOT => u64_from_le(buf).map(PacketVariant::AckSnapshot),
+            op_codes::PUSH_INPUT if buf.len() >= 8 => Ok(PacketVariant::PushInput {
+                sequence: u32_from_le(buf)?,
+                actions: ActionState::from_bits(u32_from_le(&buf[4..])?),
+            }),
+            op_codes::PUSH_ROLLBACK_INPUT if buf.len() >= 12 => {
+                let count = u32_from_le(&buf[8..])? as usize;
+                let inputs = buf[12..].chunks_exact(4).take(count);
+                if inputs.len() < count {
+                    return Err(EngineError::Network(
+                        "the rollback input is truncated".into(),
+                    ));
+                }
+                Ok(PacketVariant::PushRollbackInput {
+                    ack: u32_from_le(buf)?,
+                    start: u32_from_le(&buf[4..])?,
+                    inputs: inputs
+                        .map(|input| u32_from_le(input).map(ActionState::from_bits))
+                        .collect::<Result<_, _>>()?,
+                })
+            }
+            op_codes::PUSH_CHECKSUM if buf.len() >= 16 => Ok(PacketVariant::PushChecksum {
+                tick: u64_from_le(buf)?,
+                checksum: u64_from_le(&buf[8..])?,
+            }),
+            op_codes::KEY_DOWN
+            | op_codes::KEY_UP
+            | op_codes::KEY_PRESS
+            | op_codes::MOUSE_DOWN
+            | op_codes::MOUSE_UP
+                if buf.len() >= 4 =>
+            {
+                Ok(PacketVariant::Input {
+                    sequence: u32_from_le(buf)?,
+                    event: deserialize_event(&buf[4..], packet_variant)?,
+                })
+            }
+            op_codes::PUSH_ENGINE_EVENT if buf.len() >= 4 => Ok(PacketVariant::PushEngineEvent(
+                deserialize_event(&buf[4..], u32_from_le(buf)?)?,
+            )),
+            op_codes::CHAT => std::str::from_utf8(buf)
+                .map(PacketVariant::Chat)
+                .map_err(|_| EngineError::Network("the chat message is no valid utf-8".into())),
+            op_codes::READY => Ok(PacketVariant::Ready(u32_from_le(buf)? != 0)),
+            op_codes::LEAVE => Ok(PacketVariant::Leave),
+            op_codes::PING if buf.len() >= 12 => Ok(PacketVariant::Ping {
+                id: u32_from_le(buf)?,
+                tick: u64_from_le(&buf[4..])?,
+            }),
+            op_codes::PONG if buf.len() >= 12 => Ok(PacketVariant::Pong {
+                id: u32_from_le(buf)?,
+                client_time: u64_from_le(&buf[4..])?,
+            }),
+            op_codes::PUSH_SESSION => u64_from_le(buf).map(PacketVariant::PushSession),
             _ => Err(EngineError::Network(format!(
                 "failed to parse websocket optcode {}",
                 packet_variant
@@ -195,18 +383,150 @@ impl GameState {
     }
 }
 
+/// Returns the op code of the input event of the same kind as the event.
+pub fn event_op_code(event: &Event) -> Opcode {
+    match event {
+        Event::KeyDown(..) => op_codes::KEY_DOWN,
+        Event::KeyUp(..) => op_codes::KEY_UP,
+        Event::KeyPress(..) => op_codes::KEY_PRESS,
+        Event::MouseDown(_) => op_codes::MOUSE_DOWN,
+        Event::MouseUp(_) => op_codes::MOUSE_UP,
+    }
+}
+
+/// The payload of an event, the layout of the mouse events is the one used by `main.js`.
+fn serialize_event(buf: &mut Vec<u8>, event: &Event) {
+    match event {
+        Event::KeyDown(modifier, key) | Event::KeyUp(modifier, key) => {
+            add_u32_to_vec(buf, modifier.bits() as u32);
+            add_u32_to_vec(buf, (*key).into());
+        }
+        Event::KeyPress(a, b) => {
+            buf.extend_from_slice(&a.to_le_bytes());
+            buf.extend_from_slice(&b.to_le_bytes());
+        }
+        Event::MouseDown(mouse) | Event::MouseUp(mouse) => {
+            add_u32_to_vec(
+                buf,
+                (mouse.modifier.bits() as u32) << 8 | mouse.buttons() as u32,
+            );
+            add_u32_to_vec(buf, mouse.x as u32);
+            add_u32_to_vec(buf, mouse.y as u32);
+        }
+    }
+}
+
+fn deserialize_event(buf: &[u8], op_code: Opcode) -> Result<Event, EngineError> {
+    let key = |buf: &[u8]| -> Result<(KeyModifier, Key), EngineError> {
+        Ok((
+            KeyModifier::from_bits(u32_from_le(buf)? as u8),
+            Key::from(u32_from_le(buf.get(4..).unwrap_or_default())?),
+        ))
+    };
+    let mouse = |buf: &[u8]| -> Result<MouseEvent, EngineError> {
+        let flags = u32_from_le(buf)?;
+        let pos = buf.get(4..).unwrap_or_default();
+        Ok(MouseEvent::new(
+            flags as u8,
+            KeyModifier::from_bits((flags >> 8) as u8),
+            u32_from_le(pos)? as i32,
+            u32_from_le(pos.get(4..).unwrap_or_default())? as i32,
+        ))
+    };
+    Ok(match op_code {
+        op_codes::KEY_DOWN => key(buf).map(|(modifier, key)| Event::KeyDown(modifier, key))?,
+        op_codes::KEY_UP => key(buf).map(|(modifier, key)| Event::KeyUp(modifier, key))?,
+        op_codes::KEY_PRESS if buf.len() >= 4 => Event::KeyPress(
+            u16::from_le_bytes([buf[0], buf[1]]),
+            u16::from_le_bytes([buf[2], buf[3]]),
+        ),
+        op_codes::MOUSE_DOWN => Event::MouseDown(mouse(buf)?),
+        op_codes::MOUSE_UP => Event::MouseUp(mouse(buf)?),
+        _ => {
+            return Err(EngineError::Network(format!(
+                "failed to parse the event {}",
+                op_code
+            )))
+        }
+    })
+}
+
 pub fn add_u32_to_vec(buf: &mut Vec<u8>, n: u32) {
     buf.extend_from_slice(&n.to_le_bytes())
 }
 
 pub fn u32_from_le(barry: &[u8]) -> Result<u32, EngineError> {
-    use std::convert::TryInto;
-    let arr: [u8; 4] = barry[..4]
-        .try_into()
-        .map_err(|_| EngineError::ResourceFormat("failed to parse u32 form le bytes".into()))?;
+    let arr: [u8; 4] = barry
+        .get(..4)
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| EngineError::ResourceFormat("failed to parse u32 form le bytes".into()))?;
     Ok(u32::from_le_bytes(arr))
 }
 
+pub fn u64_from_le(barry: &[u8]) -> Result<u64, EngineError> {
+    let arr: [u8; 8] = barry
+        .get(..8)
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| EngineError::ResourceFormat("failed to parse u64 form le bytes".into()))?;
+    Ok(u64::from_le_bytes(arr))
+}
+
+/// Reads the little endian fields of an encoded buffer, e.g. a snapshot or a replay.
+pub(crate) struct Reader<'a> {
+    buf: &'a [u8],
+    /// Creates the error returned if the buffer ends before a field.
+    truncated: fn() -> EngineError,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(buf: &'a [u8], truncated: fn() -> EngineError) -> Self {
+        Self { buf, truncated }
+    }
+
+    pub(crate) fn bytes(&mut self, n: usize) -> Result<&'a [u8], EngineError> {
+        if self.buf.len() < n {
+            return Err((self.truncated)());
+        }
+        let (bytes, rest) = self.buf.split_at(n);
+        self.buf = rest;
+        Ok(bytes)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, EngineError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, EngineError> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn i32(&mut self) -> Result<i32, EngineError> {
+        Ok(i32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, EngineError> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn f32(&mut self) -> Result<f32, EngineError> {
+        Ok(f32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn vec2(&mut self) -> Result<Vec2, EngineError> {
+        Ok(Vec2::new(self.f32()?, self.f32()?))
+    }
+
+    /// Read the length of a list, it is checked against the remaining bytes to not allocate
+    /// huge amounts of memory for a corrupted buffer.
+    pub(crate) fn len(&mut self, min_item_size: usize) -> Result<usize, EngineError> {
+        let len = self.u32()? as usize;
+        if !matches!(len.checked_mul(min_item_size), Some(n) if n <= self.buf.len()) {
+            return Err((self.truncated)());
+        }
+        Ok(len)
+    }
+}
+
 fn read_to_vec(path: &str, buf: &mut Vec<u8>) -> Result<(), EngineError> {
     let mut file = std::fs::File::open(path)?;
     file.read_to_end(buf)?;
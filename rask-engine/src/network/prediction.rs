@@ -0,0 +1,243 @@
+//! The prediction module lets the client run ahead of the server.
+//!
+//! The client simulates the game locally with the inputs of its player instead of waiting for the
+//! server. Every simulation step is recorded as an `InputFrame` with a sequence number and sent to
+//! the server, which applies the frames in order and reports the sequence number of the last
+//! applied frame with each snapshot. The `Predictor` compares the snapshot with the state it
+//! predicted after that frame. If they disagree by more than the tolerance, the engine is reset to
+//! the snapshot and the frames the server has not applied yet are replayed.
+//!
+//! To avoid visible jumps, a correction is not applied to the rendered positions at once. The
+//! difference is kept as an offset, which shrinks with every step.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use specs::WorldExt;
+
+use crate::engine::broadcast::{EntitySnapshot, WorldSnapshot};
+use crate::engine::resources::EventQueue;
+use crate::engine::{GameEngine, RaskEngine};
+use crate::input::ActionState;
+use crate::math::Vec2;
+
+/// Offsets shorter than this are dropped.
+const MIN_OFFSET: f32 = 1e-3;
+
+/// Configures when and how predictions are corrected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PredictionConfig {
+    /// The maximum distance between a predicted and the authoritative position, that is
+    /// tolerated without correcting the prediction.
+    pub tolerance: f32,
+    /// The fraction of the offset of a correction removed in every simulation step, `1.0`
+    /// applies corrections immediately.
+    pub smoothing: f32,
+    /// Corrections farther than this are applied immediately, e.g. when a player respawned.
+    pub snap_distance: f32,
+    /// The maximum number of frames kept until the server applies them.
+    pub history: usize,
+}
+
+impl Default for PredictionConfig {
+    fn default() -> Self {
+        Self {
+            tolerance: 0.05,
+            smoothing: 0.1,
+            snap_distance: 2.0,
+            history: 256,
+        }
+    }
+}
+
+/// The actions of the player during one simulation step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputFrame {
+    /// The sequence number, the first frame has the number `1`.
+    pub sequence: u32,
+    pub actions: ActionState,
+}
+
+/// A frame and the state predicted after it.
+#[derive(Debug)]
+struct Prediction {
+    frame: InputFrame,
+    snapshot: WorldSnapshot,
+}
+
+/// Predicts the game for one player and reconciles the prediction with the server.
+#[derive(Debug)]
+pub struct Predictor {
+    player: u32,
+    config: PredictionConfig,
+    next_sequence: u32,
+    /// The frames not yet applied by the server.
+    history: VecDeque<Prediction>,
+    /// The sequence number of the last frame applied by the server.
+    acked: u32,
+    /// The rendering offsets of the entities, by entity id.
+    offsets: Vec<(u32, Vec2)>,
+}
+
+impl Predictor {
+    /// Creates a predictor for the given player.
+    pub fn new(player: u32, config: PredictionConfig) -> Self {
+        Self {
+            player,
+            config,
+            next_sequence: 1,
+            history: VecDeque::new(),
+            acked: 0,
+            offsets: Vec::new(),
+        }
+    }
+
+    /// Returns the id of the predicted player.
+    pub fn player(&self) -> u32 {
+        self.player
+    }
+
+    /// Returns the configuration.
+    pub fn config(&self) -> &PredictionConfig {
+        &self.config
+    }
+
+    /// Returns the sequence number of the last frame applied by the server.
+    pub fn acked(&self) -> u32 {
+        self.acked
+    }
+
+    /// Returns the frames not yet applied by the server, ordered by sequence number.
+    pub fn pending(&self) -> impl Iterator<Item = &InputFrame> {
+        self.history.iter().map(|prediction| &prediction.frame)
+    }
+
+    /// Advance the engine by the given time and return the recorded frames, which have to be
+    /// sent to the server.
+    pub fn tick(&mut self, engine: &mut RaskEngine, dt: Duration) -> Vec<InputFrame> {
+        (0..engine.advance(dt)).map(|_| self.step(engine)).collect()
+    }
+
+    /// Do a single simulation step and return the recorded frame.
+    pub fn step(&mut self, engine: &mut RaskEngine) -> InputFrame {
+        engine.step();
+        self.decay_offsets();
+        let frame = InputFrame {
+            sequence: self.next_sequence,
+            actions: engine.actions(self.player),
+        };
+        self.next_sequence += 1;
+        if self.history.len() == self.config.history {
+            self.history.pop_front();
+        }
+        self.history.push_back(Prediction {
+            frame,
+            snapshot: engine.snapshot(),
+        });
+        frame
+    }
+
+    /// Reconcile the prediction with a snapshot of the server, `input` is the sequence number of
+    /// the last frame the server applied before taking the snapshot. Returns `true` if the
+    /// prediction was corrected.
+    ///
+    /// A correction replays the frames not yet applied by the server, the engine events emitted
+    /// while replaying are discarded. The events emitted before should be polled first.
+    pub fn reconcile(
+        &mut self,
+        engine: &mut RaskEngine,
+        snapshot: &WorldSnapshot,
+        input: u32,
+    ) -> bool {
+        if input < self.acked {
+            // an outdated snapshot
+            return false;
+        }
+        self.acked = input;
+        while matches!(self.history.front(), Some(p) if p.frame.sequence < input) {
+            self.history.pop_front();
+        }
+        let predicted = match self.history.front() {
+            Some(p) if p.frame.sequence == input => self.history.pop_front().map(|p| p.snapshot),
+            _ => None,
+        };
+        if let Some(predicted) = predicted {
+            if self.agrees(&predicted, snapshot) {
+                return false;
+            }
+        }
+        self.rewind(engine, snapshot);
+        true
+    }
+
+    /// Returns the offset of an entity to be added to its position when rendering it.
+    pub fn offset(&self, entity: u32) -> Vec2 {
+        self.offsets
+            .iter()
+            .find(|(id, _)| *id == entity)
+            .map_or(Vec2::zero(), |(_, offset)| *offset)
+    }
+
+    /// Returns the interpolated position of an entity including the offset of the last
+    /// corrections.
+    pub fn display_pos(&self, engine: &RaskEngine, entity: specs::Entity) -> Option<Vec2> {
+        engine
+            .interpolated_pos(entity)
+            .map(|pos| pos + self.offset(entity.id()))
+    }
+
+    /// Returns `true` if the prediction is close enough to the authoritative snapshot.
+    fn agrees(&self, predicted: &WorldSnapshot, snapshot: &WorldSnapshot) -> bool {
+        snapshot.entities.iter().all(|entity| {
+            matches!(predicted.counterpart(entity),
+                Some(local) if close(local, entity, self.config.tolerance))
+        })
+    }
+
+    /// Reset the engine to the snapshot and replay the pending frames.
+    fn rewind(&mut self, engine: &mut RaskEngine, snapshot: &WorldSnapshot) {
+        let before = engine.snapshot();
+        // the events of the current step must not be applied to the replayed ones
+        let events = std::mem::take(&mut engine.world_mut().write_resource::<EventQueue>().0);
+        engine.restore(snapshot);
+        for prediction in self.history.iter_mut() {
+            engine.set_actions(self.player, prediction.frame.actions);
+            engine.step();
+            prediction.snapshot = engine.snapshot();
+        }
+        engine.world_mut().write_resource::<EventQueue>().0 = events;
+        engine.poll_events();
+
+        let after = engine.snapshot();
+        let mut offsets = Vec::new();
+        for old in before.entities.iter() {
+            if let Some(new) = after.entity(old.entity) {
+                let offset = old.pos - new.pos + self.offset(old.entity);
+                if offset.norm() < self.config.snap_distance && offset.norm() > MIN_OFFSET {
+                    offsets.push((old.entity, offset));
+                }
+            }
+        }
+        self.offsets = offsets;
+    }
+
+    fn decay_offsets(&mut self) {
+        let factor = 1.0 - self.config.smoothing.clamp(0.0, 1.0);
+        for (_, offset) in self.offsets.iter_mut() {
+            *offset *= factor;
+        }
+        self.offsets
+            .retain(|(_, offset)| offset.norm() > MIN_OFFSET);
+    }
+}
+
+/// Returns `true` if two snapshots of an entity are the same within the tolerance.
+fn close(a: &EntitySnapshot, b: &EntitySnapshot, tolerance: f32) -> bool {
+    let same_player = match (a.player, b.player) {
+        (Some(a), Some(b)) => {
+            a.state == b.state && a.facing == b.facing && a.stance == b.stance && a.armed == b.armed
+        }
+        (a, b) => a.is_none() && b.is_none(),
+    };
+    same_player && (a.pos - b.pos).norm() <= tolerance
+}
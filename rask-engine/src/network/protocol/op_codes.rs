@@ -20,4 +20,15 @@ pub const STOP_SOUND: u32 = 17;
 pub const PUSH_GAME_STATE: u32 = 18;
 pub const PUSH_ENGINE_EVENT: u32 = 19;
 pub const PUSH_SERVER_EVENT: u32 = 20;
+pub const PUSH_SNAPSHOT: u32 = 21;
+pub const ACK_SNAPSHOT: u32 = 22;
+pub const PUSH_INPUT: u32 = 23;
+pub const PUSH_ROLLBACK_INPUT: u32 = 24;
+pub const PUSH_CHECKSUM: u32 = 25;
+pub const CHAT: u32 = 26;
+pub const READY: u32 = 27;
+pub const LEAVE: u32 = 28;
+pub const PING: u32 = 29;
+pub const PONG: u32 = 30;
+pub const PUSH_SESSION: u32 = 31;
 // The following lines are inserted from `wasm/scripts/main.js`
@@ -0,0 +1,304 @@
+//! The rollback module synchronizes a duel between two peers without an authoritative server.
+//!
+//! Both peers run the whole simulation. Each frame, a peer sends its local input to the other one
+//! and simulates the frame right away. If the input of the remote player did not arrive yet, it is
+//! predicted by repeating the last one. When the actual input arrives and differs from the
+//! prediction, the session rolls back to the state saved before that frame and simulates the
+//! following frames again.
+//!
+//! The local input is applied `input_delay` frames later than it was made, which gives it time to
+//! reach the other peer and avoids most rollbacks. A peer never runs more than `max_rollback`
+//! frames ahead of the inputs it received, it stalls instead.
+//!
+//! Every packet contains all local inputs the other peer has not acknowledged yet, so lost
+//! packets don't need to be detected.
+
+use std::collections::VecDeque;
+
+use super::packet::{PacketVariant, Serialize, WebSocketPacket};
+use super::protocol::op_codes;
+use crate::engine::{GameEngine, RaskEngine, SavedState};
+use crate::input::ActionState;
+
+/// Sends packets to the other peer and receives its packets.
+pub trait Transport {
+    /// Send a packet to the other peer, it may be delayed or lost.
+    fn send(&mut self, packet: Vec<u8>);
+
+    /// Returns the next packet received from the other peer, if there is one.
+    fn receive(&mut self) -> Option<Vec<u8>>;
+}
+
+/// The settings of a rollback session, both peers should use the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RollbackConfig {
+    /// The number of frames the local input is delayed by.
+    /// A longer delay needs fewer rollbacks, but makes the game less responsive.
+    pub input_delay: u32,
+    /// The maximum number of frames simulated ahead of the received remote inputs.
+    pub max_rollback: u32,
+}
+
+impl Default for RollbackConfig {
+    fn default() -> Self {
+        Self {
+            input_delay: 2,
+            max_rollback: 8,
+        }
+    }
+}
+
+/// A simulated frame, which may have to be simulated again.
+#[derive(Debug)]
+struct Frame {
+    /// The state before the frame.
+    state: SavedState,
+    local: ActionState,
+    /// The remote input the frame was simulated with, it may be a prediction.
+    remote: ActionState,
+}
+
+/// A duel of a local and a remote player synchronized by rollbacks.
+#[derive(Debug)]
+pub struct RollbackSession {
+    config: RollbackConfig,
+    local: u32,
+    remote: u32,
+    /// The number of simulated frames.
+    frame: u32,
+    /// The local inputs from the frame `local_start` on.
+    local_inputs: VecDeque<ActionState>,
+    local_start: u32,
+    /// The number of local inputs the other peer received.
+    remote_ack: u32,
+    /// The received remote inputs from the frame `remote_start` on.
+    remote_inputs: VecDeque<ActionState>,
+    remote_start: u32,
+    /// The last received remote input, the following ones are predicted from it.
+    last_remote: ActionState,
+    /// The simulated frames from `frames_start` on, that were not confirmed yet.
+    frames: VecDeque<Frame>,
+    frames_start: u32,
+    rollbacks: u32,
+}
+
+impl RollbackSession {
+    /// Creates a session for the local and the remote player id.
+    /// The session starts at the current state of the engine.
+    pub fn new(local: u32, remote: u32, config: RollbackConfig) -> Self {
+        Self {
+            config,
+            local,
+            remote,
+            frame: 0,
+            // the first frames are simulated without input
+            local_inputs: (0..config.input_delay)
+                .map(|_| ActionState::default())
+                .collect(),
+            local_start: 0,
+            remote_ack: 0,
+            remote_inputs: VecDeque::new(),
+            remote_start: 0,
+            last_remote: ActionState::default(),
+            frames: VecDeque::new(),
+            frames_start: 0,
+            rollbacks: 0,
+        }
+    }
+
+    /// Returns the settings.
+    pub fn config(&self) -> &RollbackConfig {
+        &self.config
+    }
+
+    /// Returns the number of simulated frames.
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Returns the number of frames simulated with the actual inputs of both players.
+    pub fn confirmed_frame(&self) -> u32 {
+        self.received().min(self.frame)
+    }
+
+    /// Returns the number of rollbacks done so far.
+    pub fn rollbacks(&self) -> u32 {
+        self.rollbacks
+    }
+
+    /// Returns `true` if the session waits for the inputs of the remote player.
+    pub fn is_stalled(&self) -> bool {
+        self.frame >= self.received() + self.config.max_rollback
+    }
+
+    /// Handle the received packets and simulate the next frame with the given local input.
+    /// Returns `false` if the session is stalled, the input is dropped in that case.
+    ///
+    /// The engine events emitted while simulating frames again are discarded, so the events
+    /// should be polled after every update.
+    pub fn update(
+        &mut self,
+        engine: &mut RaskEngine,
+        transport: &mut impl Transport,
+        input: ActionState,
+    ) -> bool {
+        self.handle_packets(engine, transport);
+        let stalled = self.is_stalled();
+        if !stalled {
+            self.local_inputs.push_back(input);
+            self.simulate(engine);
+        }
+        self.send(transport);
+        !stalled
+    }
+
+    /// Handle the received packets and send the local inputs again, without simulating a frame.
+    /// This keeps the other peer up to date, e.g. while the game is paused.
+    pub fn poll(&mut self, engine: &mut RaskEngine, transport: &mut impl Transport) {
+        self.handle_packets(engine, transport);
+        self.send(transport);
+    }
+
+    fn handle_packets(&mut self, engine: &mut RaskEngine, transport: &mut impl Transport) {
+        let mut rollback = None;
+        while let Some(packet) = transport.receive() {
+            if let Some(frame) = self.receive(&packet) {
+                rollback = Some(rollback.map_or(frame, |f: u32| f.min(frame)));
+            }
+        }
+        if let Some(frame) = rollback {
+            self.rollback(engine, frame);
+        }
+        self.forget_confirmed();
+    }
+
+    /// The number of received remote inputs.
+    fn received(&self) -> u32 {
+        self.remote_start + self.remote_inputs.len() as u32
+    }
+
+    /// The input of the remote player used for the given frame.
+    fn remote_input(&self, frame: u32) -> ActionState {
+        match frame.checked_sub(self.remote_start) {
+            Some(index) if frame < self.received() => self.remote_inputs[index as usize],
+            _ => {
+                // repeat the held actions of the last input
+                let mut predicted = self.last_remote;
+                predicted.next_tick();
+                predicted
+            }
+        }
+    }
+
+    /// Handle a packet of the other peer and return the first frame that has to be simulated
+    /// again.
+    fn receive(&mut self, packet: &[u8]) -> Option<u32> {
+        let (ack, start, inputs) = match WebSocketPacket::deserialize(packet) {
+            Ok(WebSocketPacket {
+                payload: PacketVariant::PushRollbackInput { ack, start, inputs },
+                ..
+            }) => (ack, start, inputs),
+            Ok(packet) => {
+                log::warn!("unexpected packet {}", packet.op_code);
+                return None;
+            }
+            Err(e) => {
+                log::warn!("invalid rollback packet: {}", e);
+                return None;
+            }
+        };
+        self.remote_ack = self.remote_ack.max(ack);
+
+        if start > self.received() {
+            // the packets arrived out of order, the inputs will be sent again
+            return None;
+        }
+        // inputs that were received before are skipped
+        let skip = (self.received() - start) as usize;
+        let mut rollback = None;
+        for input in inputs.into_iter().skip(skip) {
+            let frame = self.received();
+            if frame < self.frame && rollback.is_none() {
+                let simulated = &self.frames[(frame - self.frames_start) as usize];
+                if simulated.remote != input {
+                    rollback = Some(frame);
+                }
+            }
+            self.remote_inputs.push_back(input);
+            self.last_remote = input;
+        }
+        rollback
+    }
+
+    /// Reset the engine to the state before the frame and simulate the frames since again.
+    fn rollback(&mut self, engine: &mut RaskEngine, frame: u32) {
+        log::debug!("rolling back {} frames", self.frame - frame);
+        self.rollbacks += 1;
+        let first = (frame - self.frames_start) as usize;
+        engine.load_state(&self.frames[first].state);
+        for index in first..self.frames.len() {
+            let remote = self.remote_input(self.frames_start + index as u32);
+            if index > first {
+                self.frames[index].state = engine.save_state();
+            }
+            let simulated = &mut self.frames[index];
+            simulated.remote = remote;
+            engine.set_actions(self.local, simulated.local);
+            engine.set_actions(self.remote, remote);
+            engine.step();
+        }
+        engine.poll_events();
+    }
+
+    /// Drop the frames and inputs that are no longer needed.
+    fn forget_confirmed(&mut self) {
+        while self.frames_start < self.confirmed_frame() {
+            self.frames.pop_front();
+            self.frames_start += 1;
+        }
+        while self.remote_start < self.frames_start.min(self.received()) {
+            self.remote_inputs.pop_front();
+            self.remote_start += 1;
+        }
+        while self.local_start < self.remote_ack.min(self.frame) {
+            self.local_inputs.pop_front();
+            self.local_start += 1;
+        }
+    }
+
+    /// Simulate the next frame.
+    fn simulate(&mut self, engine: &mut RaskEngine) {
+        let local = self.local_inputs[(self.frame - self.local_start) as usize];
+        let remote = self.remote_input(self.frame);
+        self.frames.push_back(Frame {
+            state: engine.save_state(),
+            local,
+            remote,
+        });
+        engine.set_actions(self.local, local);
+        engine.set_actions(self.remote, remote);
+        engine.step();
+        self.frame += 1;
+    }
+
+    /// Send the local inputs the other peer has not received yet.
+    fn send(&mut self, transport: &mut impl Transport) {
+        let start = self.remote_ack.max(self.local_start);
+        let mut buf = Vec::new();
+        WebSocketPacket {
+            op_code: op_codes::PUSH_ROLLBACK_INPUT,
+            payload: PacketVariant::PushRollbackInput {
+                ack: self.received(),
+                start,
+                inputs: self
+                    .local_inputs
+                    .iter()
+                    .skip((start - self.local_start) as usize)
+                    .copied()
+                    .collect(),
+            },
+        }
+        .serialize(&mut buf);
+        transport.send(buf);
+    }
+}
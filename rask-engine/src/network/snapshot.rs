@@ -0,0 +1,545 @@
+//! The snapshot module contains the binary encoding of `WorldSnapshot`s.
+//!
+//! A snapshot is either encoded completely or as a delta against a baseline, an older snapshot
+//! the receiver has acknowledged. A delta only contains the entities that changed, and of those
+//! only the changed fields. The `SnapshotSender` keeps track of the acknowledged baseline of one
+//! client, the `SnapshotReceiver` keeps the snapshots needed to decode the deltas.
+//!
+//! All numbers are little endian. The encoding starts with the version and the kind of the
+//! snapshot, so the format can be changed later on.
+
+use std::collections::VecDeque;
+
+use super::packet::{add_u32_to_vec, Reader, Serialize};
+use crate::engine::broadcast::{EntitySnapshot, WorldSnapshot};
+use crate::engine::components::{Facing, Player, PlayerState, Stance};
+use crate::engine::rules::{MatchPhase, MatchRules, MatchState, Respawn};
+use crate::error::EngineError;
+use crate::math::Vec2;
+
+/// The version of the encoding.
+pub const SNAPSHOT_VERSION: u8 = 1;
+
+const FULL: u8 = 0;
+const DELTA: u8 = 1;
+
+// the fields of an entity contained in the encoding
+const POS: u8 = 1;
+const VEL: u8 = 1 << 1;
+const PLAYER: u8 = 1 << 2;
+/// The entity is no longer a player.
+const NO_PLAYER: u8 = 1 << 3;
+
+// the state of the match contained in the encoding
+const MATCH_UNCHANGED: u8 = 0;
+const MATCH_SET: u8 = 1;
+const MATCH_NONE: u8 = 2;
+
+/// A snapshot encoded as a delta against a baseline.
+pub struct SnapshotDelta<'a> {
+    pub baseline: &'a WorldSnapshot,
+    pub snapshot: &'a WorldSnapshot,
+}
+
+fn add_u64_to_vec(buf: &mut Vec<u8>, n: u64) {
+    buf.extend_from_slice(&n.to_le_bytes())
+}
+
+fn add_f32_to_vec(buf: &mut Vec<u8>, n: f32) {
+    buf.extend_from_slice(&n.to_le_bytes())
+}
+
+fn add_vec2_to_vec(buf: &mut Vec<u8>, v: Vec2) {
+    add_f32_to_vec(buf, v.x());
+    add_f32_to_vec(buf, v.y());
+}
+
+/// Creates a reader for an encoded snapshot.
+fn reader(buf: &[u8]) -> Reader<'_> {
+    Reader::new(buf, || {
+        EngineError::Network("the snapshot is truncated".into())
+    })
+}
+
+fn invalid(what: &str, value: u8) -> EngineError {
+    EngineError::Network(format!("invalid {} {} in snapshot", what, value))
+}
+
+fn player_state_to_u8(state: PlayerState) -> u8 {
+    match state {
+        PlayerState::Idle => 0,
+        PlayerState::Run => 1,
+        PlayerState::Jump => 2,
+        PlayerState::Lunge => 3,
+        PlayerState::Parry => 4,
+        PlayerState::Dead => 5,
+    }
+}
+
+fn player_state_from_u8(state: u8) -> Result<PlayerState, EngineError> {
+    Ok(match state {
+        0 => PlayerState::Idle,
+        1 => PlayerState::Run,
+        2 => PlayerState::Jump,
+        3 => PlayerState::Lunge,
+        4 => PlayerState::Parry,
+        5 => PlayerState::Dead,
+        _ => return Err(invalid("player state", state)),
+    })
+}
+
+/// The state, stance and flags of a player are packed into a single byte.
+fn serialize_player(buf: &mut Vec<u8>, player: &Player) {
+    add_u32_to_vec(buf, player.id);
+    let stance = match player.stance {
+        Stance::Low => 0,
+        Stance::Mid => 1,
+        Stance::High => 2,
+    };
+    buf.push(
+        player_state_to_u8(player.state)
+            | stance << 3
+            | ((player.facing == Facing::Left) as u8) << 5
+            | (player.grounded as u8) << 6
+            | (player.armed as u8) << 7,
+    );
+    add_f32_to_vec(buf, player.state_time);
+}
+
+fn deserialize_player(reader: &mut Reader) -> Result<Player, EngineError> {
+    let id = reader.u32()?;
+    let packed = reader.u8()?;
+    let stance = match (packed >> 3) & 0b11 {
+        0 => Stance::Low,
+        1 => Stance::Mid,
+        2 => Stance::High,
+        stance => return Err(invalid("stance", stance)),
+    };
+    Ok(Player {
+        id,
+        facing: if packed & 1 << 5 != 0 {
+            Facing::Left
+        } else {
+            Facing::Right
+        },
+        grounded: packed & 1 << 6 != 0,
+        stance,
+        state: player_state_from_u8(packed & 0b111)?,
+        armed: packed & 1 << 7 != 0,
+        state_time: reader.f32()?,
+    })
+}
+
+fn serialize_counts(buf: &mut Vec<u8>, counts: &[u32]) {
+    add_u32_to_vec(buf, counts.len() as u32);
+    for &count in counts {
+        add_u32_to_vec(buf, count);
+    }
+}
+
+fn deserialize_counts(reader: &mut Reader) -> Result<Vec<u32>, EngineError> {
+    (0..reader.len(4)?).map(|_| reader.u32()).collect()
+}
+
+fn serialize_match(buf: &mut Vec<u8>, state: &MatchState) {
+    let rules = &state.rules;
+    for &value in [
+        rules.screen_width,
+        rules.countdown,
+        rules.respawn_time,
+        rules.respawn_distance,
+        rules.round_over_time,
+    ]
+    .iter()
+    {
+        add_f32_to_vec(buf, value);
+    }
+    add_u32_to_vec(buf, rules.rounds_to_win);
+    match state.phase {
+        MatchPhase::Countdown { remaining } => {
+            buf.push(0);
+            add_f32_to_vec(buf, remaining);
+        }
+        MatchPhase::Playing => buf.push(1),
+        MatchPhase::RoundOver { winner, remaining } => {
+            buf.push(2);
+            add_u32_to_vec(buf, winner);
+            add_f32_to_vec(buf, remaining);
+        }
+        MatchPhase::MatchOver { winner } => {
+            buf.push(3);
+            add_u32_to_vec(buf, winner);
+        }
+    }
+    match state.right_of_way {
+        Some(player) => {
+            buf.push(1);
+            add_u32_to_vec(buf, player);
+        }
+        None => buf.push(0),
+    }
+    for &screen in [state.screen, state.screens.0, state.screens.1].iter() {
+        buf.extend_from_slice(&screen.to_le_bytes());
+    }
+    add_u32_to_vec(buf, state.respawns.len() as u32);
+    for respawn in state.respawns.iter() {
+        add_u32_to_vec(buf, respawn.player);
+        add_f32_to_vec(buf, respawn.remaining);
+    }
+    serialize_counts(buf, &state.wins);
+    serialize_counts(buf, &state.kills);
+}
+
+fn deserialize_match(reader: &mut Reader) -> Result<MatchState, EngineError> {
+    let rules = MatchRules {
+        screen_width: reader.f32()?,
+        countdown: reader.f32()?,
+        respawn_time: reader.f32()?,
+        respawn_distance: reader.f32()?,
+        round_over_time: reader.f32()?,
+        rounds_to_win: reader.u32()?,
+    };
+    let phase = match reader.u8()? {
+        0 => MatchPhase::Countdown {
+            remaining: reader.f32()?,
+        },
+        1 => MatchPhase::Playing,
+        2 => MatchPhase::RoundOver {
+            winner: reader.u32()?,
+            remaining: reader.f32()?,
+        },
+        3 => MatchPhase::MatchOver {
+            winner: reader.u32()?,
+        },
+        phase => return Err(invalid("match phase", phase)),
+    };
+    let right_of_way = match reader.u8()? {
+        0 => None,
+        _ => Some(reader.u32()?),
+    };
+    let screen = reader.i32()?;
+    let screens = (reader.i32()?, reader.i32()?);
+    let respawns = (0..reader.len(8)?)
+        .map(|_| {
+            Ok(Respawn {
+                player: reader.u32()?,
+                remaining: reader.f32()?,
+            })
+        })
+        .collect::<Result<_, EngineError>>()?;
+    Ok(MatchState {
+        rules,
+        phase,
+        right_of_way,
+        screen,
+        screens,
+        respawns,
+        wins: deserialize_counts(reader)?,
+        kills: deserialize_counts(reader)?,
+    })
+}
+
+/// Compare two vectors bitwise, a delta has to reproduce the snapshot exactly.
+fn same(a: Vec2, b: Vec2) -> bool {
+    a.x().to_bits() == b.x().to_bits() && a.y().to_bits() == b.y().to_bits()
+}
+
+fn serialize_entity(buf: &mut Vec<u8>, entity: &EntitySnapshot, base: Option<&EntitySnapshot>) {
+    let mut fields = 0;
+    if !matches!(base, Some(base) if same(base.pos, entity.pos)) {
+        fields |= POS;
+    }
+    if !matches!(base, Some(base) if same(base.vel, entity.vel)) {
+        fields |= VEL;
+    }
+    match (base.and_then(|base| base.player), entity.player) {
+        (Some(_), None) => fields |= NO_PLAYER,
+        (base, Some(player)) if base != Some(player) => fields |= PLAYER,
+        _ => (),
+    }
+    if fields == 0 {
+        return;
+    }
+    add_u32_to_vec(buf, entity.entity);
+    buf.push(fields);
+    if fields & POS != 0 {
+        add_vec2_to_vec(buf, entity.pos);
+    }
+    if fields & VEL != 0 {
+        add_vec2_to_vec(buf, entity.vel);
+    }
+    if let (true, Some(player)) = (fields & PLAYER != 0, entity.player) {
+        serialize_player(buf, &player);
+    }
+}
+
+fn deserialize_entity(
+    reader: &mut Reader,
+    baseline: Option<&WorldSnapshot>,
+) -> Result<EntitySnapshot, EngineError> {
+    let id = reader.u32()?;
+    let fields = reader.u8()?;
+    let mut entity = baseline
+        .and_then(|baseline| baseline.entity(id))
+        .copied()
+        .unwrap_or(EntitySnapshot {
+            entity: id,
+            pos: Vec2::zero(),
+            vel: Vec2::zero(),
+            player: None,
+        });
+    if fields & POS != 0 {
+        entity.pos = reader.vec2()?;
+    }
+    if fields & VEL != 0 {
+        entity.vel = reader.vec2()?;
+    }
+    if fields & PLAYER != 0 {
+        entity.player = Some(deserialize_player(reader)?);
+    }
+    if fields & NO_PLAYER != 0 {
+        entity.player = None;
+    }
+    Ok(entity)
+}
+
+/// Write the entities and the match state, omitting everything that equals the baseline.
+fn serialize_body(buf: &mut Vec<u8>, snapshot: &WorldSnapshot, baseline: Option<&WorldSnapshot>) {
+    let count_pos = buf.len();
+    add_u32_to_vec(buf, 0);
+    let mut count = 0;
+    for entity in snapshot.entities.iter() {
+        let len = buf.len();
+        serialize_entity(buf, entity, baseline.and_then(|b| b.entity(entity.entity)));
+        if buf.len() != len {
+            count += 1;
+        }
+    }
+    buf[count_pos..count_pos + 4].copy_from_slice(&(count as u32).to_le_bytes());
+
+    if let Some(baseline) = baseline {
+        let removed: Vec<u32> = baseline
+            .entities
+            .iter()
+            .map(|entity| entity.entity)
+            .filter(|&id| snapshot.entity(id).is_none())
+            .collect();
+        serialize_counts(buf, &removed);
+    }
+
+    match (baseline.map(|b| &b.match_state), &snapshot.match_state) {
+        (Some(base), state) if base == state => buf.push(MATCH_UNCHANGED),
+        (_, Some(state)) => {
+            buf.push(MATCH_SET);
+            serialize_match(buf, state);
+        }
+        (_, None) => buf.push(MATCH_NONE),
+    }
+}
+
+impl Serialize for WorldSnapshot {
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        buf.push(SNAPSHOT_VERSION);
+        buf.push(FULL);
+        add_u64_to_vec(buf, self.tick);
+        serialize_body(buf, self, None);
+    }
+}
+
+impl<'a> Serialize for SnapshotDelta<'a> {
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        buf.push(SNAPSHOT_VERSION);
+        buf.push(DELTA);
+        add_u64_to_vec(buf, self.snapshot.tick);
+        add_u64_to_vec(buf, self.baseline.tick);
+        serialize_body(buf, self.snapshot, Some(self.baseline));
+    }
+}
+
+impl WorldSnapshot {
+    /// Returns a hash of the complete encoding of the snapshot.
+    /// The encoding doesn't depend on the platform and the entities are ordered, so two engines
+    /// in the same state have the same checksum. Comparing them detects a desync.
+    pub fn checksum(&self) -> u64 {
+        let mut buf = Vec::new();
+        self.serialize(&mut buf);
+        // FNV-1a
+        buf.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+    }
+
+    /// Returns the tick of the baseline an encoded snapshot is relative to,
+    /// `None` for a complete snapshot.
+    pub fn baseline_of(buf: &[u8]) -> Result<Option<u64>, EngineError> {
+        let mut reader = reader(buf);
+        let (version, kind) = (reader.u8()?, reader.u8()?);
+        if version != SNAPSHOT_VERSION {
+            return Err(invalid("version", version));
+        }
+        match kind {
+            FULL => Ok(None),
+            DELTA => {
+                reader.u64()?;
+                Ok(Some(reader.u64()?))
+            }
+            kind => Err(invalid("kind", kind)),
+        }
+    }
+
+    /// Decode a snapshot, a delta requires the baseline it was encoded against.
+    pub fn deserialize(buf: &[u8], baseline: Option<&WorldSnapshot>) -> Result<Self, EngineError> {
+        let expected = Self::baseline_of(buf)?;
+        let mut reader = reader(&buf[2..]);
+        let tick = reader.u64()?;
+        let baseline = match expected {
+            None => None,
+            Some(expected) => {
+                reader.u64()?;
+                match baseline {
+                    Some(baseline) if baseline.tick == expected => Some(baseline),
+                    _ => {
+                        return Err(EngineError::Network(format!(
+                            "the baseline {} of the snapshot {} is missing",
+                            expected, tick
+                        )))
+                    }
+                }
+            }
+        };
+
+        let count = reader.len(5)?;
+        let changed = (0..count)
+            .map(|_| deserialize_entity(&mut reader, baseline))
+            .collect::<Result<Vec<_>, EngineError>>()?;
+        let mut entities = Vec::new();
+        let mut match_state = None;
+        if let Some(baseline) = baseline {
+            let removed = deserialize_counts(&mut reader)?;
+            entities.extend(
+                baseline
+                    .entities
+                    .iter()
+                    .filter(|entity| !removed.contains(&entity.entity))
+                    .map(|entity| {
+                        changed
+                            .iter()
+                            .find(|e| e.entity == entity.entity)
+                            .copied()
+                            .unwrap_or(*entity)
+                    }),
+            );
+            entities.extend(
+                changed
+                    .into_iter()
+                    .filter(|entity| baseline.entity(entity.entity).is_none()),
+            );
+            match_state = baseline.match_state.clone();
+        } else {
+            entities = changed;
+        }
+
+        // the entities are ordered by their id like in the world
+        entities.sort_by_key(|entity| entity.entity);
+
+        match reader.u8()? {
+            MATCH_UNCHANGED => (),
+            MATCH_SET => match_state = Some(deserialize_match(&mut reader)?),
+            MATCH_NONE => match_state = None,
+            state => return Err(invalid("match state", state)),
+        }
+        Ok(Self {
+            tick,
+            entities,
+            match_state,
+        })
+    }
+}
+
+/// Encodes the snapshots sent to a single client.
+/// Snapshots are encoded as a delta against the latest snapshot acknowledged by the client,
+/// or completely if there is none.
+pub struct SnapshotSender {
+    history: VecDeque<WorldSnapshot>,
+    acked: Option<u64>,
+    capacity: usize,
+}
+
+impl SnapshotSender {
+    /// Creates a new sender remembering up to `capacity` snapshots as possible baselines.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            history: VecDeque::with_capacity(capacity),
+            acked: None,
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Returns the tick of the latest acknowledged snapshot.
+    pub fn acked(&self) -> Option<u64> {
+        self.acked
+    }
+
+    /// Mark a snapshot as received by the client. Older acknowledgements are ignored.
+    pub fn ack(&mut self, tick: u64) {
+        if !matches!(self.acked, Some(acked) if acked >= tick)
+            && self.history.iter().any(|snapshot| snapshot.tick == tick)
+        {
+            self.acked = Some(tick);
+        }
+    }
+
+    /// Encode the next snapshot.
+    pub fn encode(&mut self, snapshot: WorldSnapshot) -> Vec<u8> {
+        let mut buf = Vec::new();
+        let baseline = self
+            .acked
+            .and_then(|acked| self.history.iter().find(|s| s.tick == acked));
+        match baseline {
+            Some(baseline) => SnapshotDelta {
+                baseline,
+                snapshot: &snapshot,
+            }
+            .serialize(&mut buf),
+            None => snapshot.serialize(&mut buf),
+        }
+        if self.history.len() == self.capacity {
+            self.history.pop_front();
+        }
+        self.history.push_back(snapshot);
+        buf
+    }
+}
+
+/// Decodes the snapshots received from the server.
+pub struct SnapshotReceiver {
+    history: VecDeque<WorldSnapshot>,
+    capacity: usize,
+}
+
+impl SnapshotReceiver {
+    /// Creates a new receiver remembering up to `capacity` snapshots as possible baselines.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            history: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Returns the latest snapshot.
+    pub fn latest(&self) -> Option<&WorldSnapshot> {
+        self.history.back()
+    }
+
+    /// Decode a snapshot, its tick should be acknowledged to the sender afterwards.
+    pub fn decode(&mut self, buf: &[u8]) -> Result<&WorldSnapshot, EngineError> {
+        let baseline = WorldSnapshot::baseline_of(buf)?
+            .and_then(|tick| self.history.iter().find(|s| s.tick == tick));
+        let snapshot = WorldSnapshot::deserialize(buf, baseline)?;
+        if self.history.len() == self.capacity {
+            self.history.pop_front();
+        }
+        self.history.push_back(snapshot);
+        Ok(self.history.back().unwrap())
+    }
+}
@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::io::Read;
 
 use super::Texture;
+use crate::boxes::RBox;
 use crate::network::packet::ResourceData;
 use crate::{math::Mat3, math::Vec3, EngineError};
 use image::DynamicImage;
@@ -154,6 +155,40 @@ impl Character {
             &self.atlas,
         ))
     }
+
+    /// Returns the area covered by each attachment at the given time of an animation,
+    /// in skeleton coordinates. The boxes are used to derive the hitboxes of a character.
+    pub fn attachment_boxes(
+        &self,
+        time: f32,
+        anim_name: &str,
+    ) -> Result<Vec<(String, RBox)>, EngineError> {
+        let animated_skin = self
+            .skeleton
+            .get_animated_skin("default", Some(anim_name))?;
+        let duration = animated_skin.get_duration();
+        // a single pose has no duration, its only frame is used
+        let time = if duration > 0.0 {
+            time.rem_euclid(duration)
+        } else {
+            0.0
+        };
+        Ok(animated_skin
+            .interpolate(time)
+            .ok_or_else(|| {
+                EngineError::Animation(format!("Could not interpolate animation at time {}", time,))
+            })?
+            .map(|s| (s.attachment.to_owned(), RBox::from(&s.srt)))
+            .collect())
+    }
+
+    /// Returns the duration of an animation in seconds.
+    pub fn duration(&self, anim_name: &str) -> Result<f32, EngineError> {
+        Ok(self
+            .skeleton
+            .get_animated_skin("default", Some(anim_name))?
+            .get_duration())
+    }
 }
 
 impl<'a> TryFrom<ResourceData<'a>> for Character {
@@ -0,0 +1,146 @@
+use rask_engine::boxes::{AABox, BoundingBox, RBox};
+use rask_engine::collide::*;
+use rask_engine::math::Vec2;
+
+fn unit_box(x: f32, y: f32) -> AABox {
+    AABox {
+        pos: Vec2::new(x, y),
+        size: Vec2::new(1.0, 1.0),
+    }
+}
+
+fn sorted(mut pairs: Vec<(u32, u32)>) -> Vec<(u32, u32)> {
+    for pair in pairs.iter_mut() {
+        if pair.0 > pair.1 {
+            *pair = (pair.1, pair.0);
+        }
+    }
+    pairs.sort();
+    pairs
+}
+
+#[test]
+fn test_grid_insert_remove() {
+    let mut grid = SpatialGrid::new(2.0);
+    grid.insert(1u32, unit_box(0.0, 0.0));
+    grid.insert(2, unit_box(5.0, 5.0));
+
+    assert_eq!(grid.len(), 2);
+    assert!(grid.contains(1));
+    assert_eq!(grid.remove(1), Some(unit_box(0.0, 0.0)));
+    assert_eq!(grid.remove(1), None);
+    assert_eq!(grid.len(), 1);
+    assert!(grid.query_point(Vec2::new(0.5, 0.5)).is_empty());
+}
+
+#[test]
+fn test_grid_pairs() {
+    let mut grid = SpatialGrid::new(2.0);
+    grid.insert(1u32, unit_box(0.0, 0.0));
+    grid.insert(2, unit_box(0.5, 0.5));
+    grid.insert(3, unit_box(10.0, 10.0));
+    grid.insert(4, unit_box(10.5, 9.5));
+
+    assert_eq!(sorted(grid.pairs()), vec![(1, 2), (3, 4)]);
+}
+
+#[test]
+fn test_grid_pairs_reported_once() {
+    // both boxes span four cells
+    let mut grid = SpatialGrid::new(1.0);
+    grid.insert(1u32, unit_box(0.5, 0.5));
+    grid.insert(2, unit_box(0.7, 0.7));
+
+    assert_eq!(grid.pairs(), vec![(1, 2)]);
+}
+
+#[test]
+fn test_grid_pairs_touching() {
+    let mut grid = SpatialGrid::new(1.0);
+    grid.insert(1u32, unit_box(0.0, 0.0));
+    grid.insert(2, unit_box(1.0, 0.0));
+
+    assert_eq!(sorted(grid.pairs()), vec![(1, 2)]);
+}
+
+#[test]
+fn test_grid_update() {
+    let mut grid = SpatialGrid::new(2.0);
+    grid.insert(1u32, unit_box(0.0, 0.0));
+    grid.insert(2, unit_box(10.0, 0.0));
+    assert!(grid.pairs().is_empty());
+
+    grid.update(2, unit_box(0.5, 0.0));
+    assert_eq!(sorted(grid.pairs()), vec![(1, 2)]);
+    assert_eq!(grid.bounds(2), Some(unit_box(0.5, 0.0)));
+
+    grid.update(2, unit_box(0.2, 0.0));
+    assert_eq!(sorted(grid.pairs()), vec![(1, 2)]);
+    assert!(grid.query_point(Vec2::new(10.5, 0.5)).is_empty());
+}
+
+#[test]
+fn test_grid_query_region() {
+    let mut grid = SpatialGrid::new(1.0);
+    for i in 0..10u32 {
+        grid.insert(i, unit_box(i as f32 * 2.0, 0.0));
+    }
+    let region = AABox {
+        pos: Vec2::new(3.5, -1.0),
+        size: Vec2::new(4.0, 4.0),
+    };
+    let mut result = grid.query_region(&region);
+    result.sort();
+
+    assert_eq!(result, vec![2, 3]);
+}
+
+#[test]
+fn test_grid_query_point() {
+    let mut grid = SpatialGrid::new(1.0);
+    grid.insert(1u32, unit_box(0.0, 0.0));
+    grid.insert(2, unit_box(-0.5, -0.5));
+    let mut result = grid.query_point(Vec2::new(0.25, 0.25));
+    result.sort();
+
+    assert_eq!(result, vec![1, 2]);
+    assert_eq!(grid.query_point(Vec2::new(-0.25, -0.25)), vec![2]);
+    assert!(grid.query_point(Vec2::new(3.0, 3.0)).is_empty());
+}
+
+#[test]
+fn test_grid_narrow_phase() {
+    let boxes = [
+        RBox::new(Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0), 0.1),
+        RBox::new(Vec2::new(0.55, 0.0), Vec2::new(0.0, 1.0), 0.1),
+        RBox::new(Vec2::new(0.8, 0.0), Vec2::new(0.2, 0.0), 0.2),
+    ];
+    let mut grid = SpatialGrid::new(1.0);
+    for (i, rbox) in boxes.iter().enumerate() {
+        grid.insert(i as u32, rbox.bounding_box());
+    }
+    let candidates = sorted(grid.pairs());
+    let collisions: Vec<(u32, u32)> = candidates
+        .iter()
+        .copied()
+        .filter(|&(a, b)| boxes[a as usize].collides(&boxes[b as usize]))
+        .collect();
+
+    assert_eq!(candidates, vec![(0, 1), (0, 2)]);
+    assert_eq!(collisions, vec![(0, 1)]);
+}
+
+#[test]
+fn test_bounding_box_rbox() {
+    let rbox = RBox {
+        pos: Vec2::new(-1.0, 0.0),
+        v1: Vec2::new(1.0, -1.0),
+        v2: Vec2::new(1.0, 1.0),
+    };
+    let aabox = AABox {
+        pos: Vec2::new(-1.0, -1.0),
+        size: Vec2::new(2.0, 2.0),
+    };
+
+    assert_eq!(rbox.bounding_box(), aabox);
+}
@@ -0,0 +1,161 @@
+mod common;
+
+use rask_engine::engine::broadcast::*;
+use rask_engine::engine::components::*;
+use rask_engine::engine::rules::MatchPhase;
+use rask_engine::engine::{GameEngine, MatchRules};
+use rask_engine::events::{Event, Key, KeyModifier};
+use rask_engine::math::Vec2;
+use specs::prelude::*;
+
+use common::{arena, run};
+
+fn spawns(events: &[EngineEvent]) -> Vec<Option<u32>> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            EngineEvent::Spawn { player, .. } => Some(*player),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_spawn_and_despawn() {
+    let mut engine = arena();
+    engine.poll_events();
+    let player = engine.spawn_player(0);
+    run(&mut engine, 1);
+
+    let mut spawned = spawns(&engine.poll_events());
+    spawned.sort();
+    // the ball of the level and the player
+    assert_eq!(spawned, vec![None, Some(0)]);
+
+    engine.world_mut().delete_entity(player).unwrap();
+    run(&mut engine, 1);
+    assert_eq!(
+        engine.poll_events(),
+        vec![EngineEvent::Despawn {
+            entity: player.id()
+        }]
+    );
+}
+
+#[test]
+fn test_poll_only_new_events() {
+    let mut engine = arena();
+    run(&mut engine, 1);
+    assert!(!engine.poll_events().is_empty());
+    run(&mut engine, 1);
+    assert!(engine.poll_events().is_empty());
+}
+
+#[test]
+fn test_independent_readers() {
+    let mut engine = arena();
+    let mut reader = engine.register_event_reader();
+    engine.poll_events();
+    engine.spawn_player(0);
+    run(&mut engine, 1);
+
+    assert_eq!(engine.poll_events().len(), 3);
+    assert_eq!(engine.events(&mut reader).len(), 3);
+    assert!(engine.events(&mut reader).is_empty());
+}
+
+#[test]
+fn test_kill_events() {
+    let mut engine = arena();
+    let (a, b) = (engine.spawn_player(0), engine.spawn_player(1));
+    run(&mut engine, 100);
+    for &(entity, x, stance) in [(a, -1.0, Stance::Low), (b, 1.3, Stance::High)].iter() {
+        engine
+            .world()
+            .write_storage::<Pos>()
+            .get_mut(entity)
+            .unwrap()
+            .0 = Vec2::new(x, -3.5);
+        engine
+            .world()
+            .write_storage::<Player>()
+            .get_mut(entity)
+            .unwrap()
+            .stance = stance;
+    }
+    engine.poll_events();
+    engine
+        .handle_event(Event::KeyDown(KeyModifier::NONE, Key::KEY_G))
+        .unwrap();
+    run(&mut engine, 20);
+
+    let events = engine.poll_events();
+    assert!(events.contains(&EngineEvent::PlayerState {
+        player: 0,
+        state: PlayerState::Lunge
+    }));
+    assert!(events.contains(&EngineEvent::Kill {
+        killer: 0,
+        victim: 1
+    }));
+    assert!(events.contains(&EngineEvent::PlayerState {
+        player: 1,
+        state: PlayerState::Dead
+    }));
+    assert!(events.iter().any(|event| matches!(
+        event,
+        EngineEvent::Sound {
+            cue: SoundCue::Kill,
+            ..
+        }
+    )));
+}
+
+#[test]
+fn test_match_phase_events() {
+    let mut engine = arena();
+    engine.start_match(MatchRules {
+        countdown: 0.1,
+        ..MatchRules::default()
+    });
+    run(&mut engine, 1);
+    let events = engine.poll_events();
+    assert!(events.iter().any(|event| matches!(
+        event,
+        EngineEvent::MatchPhase {
+            phase: MatchPhase::Countdown { .. }
+        }
+    )));
+
+    run(&mut engine, 5);
+    assert!(engine.poll_events().is_empty());
+    run(&mut engine, 10);
+    assert_eq!(
+        engine.poll_events(),
+        vec![EngineEvent::MatchPhase {
+            phase: MatchPhase::Playing
+        }]
+    );
+}
+
+#[test]
+fn test_snapshot() {
+    let mut engine = arena();
+    engine.spawn_player(1);
+    engine.start_match(MatchRules::default());
+    run(&mut engine, 3);
+    let snapshot = engine.snapshot();
+
+    assert_eq!(snapshot.tick, 3);
+    // only moving entities are part of the snapshot
+    assert_eq!(snapshot.entities.len(), 2);
+    let player = snapshot.player(1).unwrap();
+    assert_eq!(player.player.unwrap().facing, Facing::Left);
+    assert!(player.pos.x() > 0.0);
+    assert_eq!(snapshot.entity(player.entity), Some(player));
+    assert!(snapshot.match_state.is_some());
+
+    let json = serde_json::to_string(&snapshot).unwrap();
+    let parsed: WorldSnapshot = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, snapshot);
+}
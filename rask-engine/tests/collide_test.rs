@@ -182,6 +182,22 @@ fn test_not_collide_rbox_aabox() {
     assert!(!(box1.collides(&box2)));
 }
 
+#[test]
+fn test_not_collide_rbox_aabox_axis_aligned() {
+    // the edges of box1 are parallel to the ones of box2 and overlap them on the x axis
+    let box1 = RBox {
+        pos: Vec2::new(0.0, 5.0),
+        v1: Vec2::new(1.0, 0.0),
+        v2: Vec2::new(0.0, 1.0),
+    };
+    let box2 = AABox {
+        pos: Vec2::new(0.0, 0.0),
+        size: Vec2::new(1.0, 1.0),
+    };
+
+    assert!(!box1.collides(&box2));
+}
+
 #[test]
 fn test_collide_rbox_rbox_intersecting() {
     let box1 = RBox {
@@ -0,0 +1,187 @@
+mod common;
+
+use rask_engine::boxes::{AABox, RBox};
+use rask_engine::engine::combat::*;
+use rask_engine::engine::components::*;
+use rask_engine::engine::{GameEngine, RaskEngine};
+use rask_engine::events::{Event, Key, KeyModifier};
+use rask_engine::math::Vec2;
+use specs::prelude::*;
+
+use common::{arena, run};
+
+/// Spawn both players standing on the floor at the given positions, facing each other.
+fn duel(engine: &mut RaskEngine, x0: f32, x1: f32) -> (Entity, Entity) {
+    let (a, b) = (engine.spawn_player(0), engine.spawn_player(1));
+    run(engine, 100);
+    for &(entity, x) in [(a, x0), (b, x1)].iter() {
+        let mut pos = engine.world().write_storage::<Pos>();
+        let pos = pos.get_mut(entity).unwrap();
+        pos.0 = Vec2::new(x, pos.0.y());
+    }
+    (a, b)
+}
+
+fn press(engine: &mut RaskEngine, key: Key) {
+    engine
+        .handle_event(Event::KeyDown(KeyModifier::NONE, key))
+        .unwrap();
+}
+
+fn player(engine: &RaskEngine, entity: Entity) -> Player {
+    *engine.world().read_storage::<Player>().get(entity).unwrap()
+}
+
+fn set_stance(engine: &mut RaskEngine, entity: Entity, stance: Stance) {
+    engine
+        .world()
+        .write_storage::<Player>()
+        .get_mut(entity)
+        .unwrap()
+        .stance = stance;
+}
+
+fn hitboxes(engine: &RaskEngine, entity: Entity) -> Hitboxes {
+    engine
+        .world()
+        .read_storage::<Hitboxes>()
+        .get(entity)
+        .unwrap()
+        .clone()
+}
+
+#[test]
+fn test_default_hitboxes_follow_facing() {
+    let mut engine = arena();
+    let (a, b) = duel(&mut engine, -2.0, 2.0);
+    run(&mut engine, 1);
+    let (boxes_a, boxes_b) = (hitboxes(&engine, a), hitboxes(&engine, b));
+
+    assert_eq!(boxes_a.hurt.len(), 1);
+    assert_eq!(boxes_a.weapon.len(), 1);
+    // the sword of the left player points to the right and vice versa
+    assert!(boxes_a.weapon[0].pos.x() > -2.0);
+    let corners_b = [
+        boxes_b.weapon[0].pos,
+        boxes_b.weapon[0].pos + boxes_b.weapon[0].v1,
+        boxes_b.weapon[0].pos + boxes_b.weapon[0].v2,
+    ];
+    assert!(corners_b.iter().all(|corner| corner.x() < 2.0));
+}
+
+#[test]
+fn test_lunge_kills() {
+    let mut engine = arena();
+    let mut reader = engine.register_combat_reader();
+    let (a, b) = duel(&mut engine, -1.0, 1.3);
+    set_stance(&mut engine, a, Stance::Low);
+    set_stance(&mut engine, b, Stance::High);
+    press(&mut engine, Key::KEY_G);
+    run(&mut engine, 20);
+
+    assert_eq!(player(&engine, b).state, PlayerState::Dead);
+    assert_ne!(player(&engine, a).state, PlayerState::Dead);
+    assert_eq!(
+        engine.combat_events(&mut reader),
+        vec![CombatEvent::Kill {
+            killer: 0,
+            victim: 1
+        }]
+    );
+    // the dead can't be hit or hit anyone
+    assert_eq!(hitboxes(&engine, b), Hitboxes::default());
+    run(&mut engine, 20);
+    assert!(engine.combat_events(&mut reader).is_empty());
+}
+
+#[test]
+fn test_swords_clash() {
+    let mut engine = arena();
+    let mut reader = engine.register_combat_reader();
+    let (a, b) = duel(&mut engine, -1.0, 1.3);
+    press(&mut engine, Key::KEY_G);
+    run(&mut engine, 10);
+
+    let events = engine.combat_events(&mut reader);
+    assert!(events.contains(&CombatEvent::Clash { a: 0, b: 1 }));
+    assert!(!events
+        .iter()
+        .any(|event| matches!(event, CombatEvent::Kill { .. })));
+    assert!(player(&engine, a).armed && player(&engine, b).armed);
+    assert_eq!(player(&engine, a).state, PlayerState::Idle);
+}
+
+#[test]
+fn test_parry_disarms() {
+    let mut engine = arena();
+    let mut reader = engine.register_combat_reader();
+    let (a, b) = duel(&mut engine, -1.0, 1.3);
+    press(&mut engine, Key::SLASH);
+    run(&mut engine, 1);
+    press(&mut engine, Key::KEY_G);
+    run(&mut engine, 10);
+
+    let events = engine.combat_events(&mut reader);
+    assert_eq!(events, vec![CombatEvent::Disarm { by: 1, victim: 0 }]);
+    assert!(!player(&engine, a).armed);
+    assert!(player(&engine, b).armed);
+    assert!(hitboxes(&engine, a).weapon.is_empty());
+
+    // without a sword there is no lunge
+    press(&mut engine, Key::KEY_G);
+    run(&mut engine, 1);
+    assert_ne!(player(&engine, a).state, PlayerState::Lunge);
+}
+
+#[test]
+fn test_custom_hitboxes() {
+    let mut engine = arena();
+    let mut reader = engine.register_combat_reader();
+    // a very long sword while standing
+    let spear = Hitbox {
+        kind: HitboxKind::Weapon,
+        area: AABox {
+            pos: Vec2::new(0.0, 0.0),
+            size: Vec2::new(5.0, 1.5),
+        }
+        .into(),
+    };
+    let mut set = HitboxSet::default();
+    set.0.insert(
+        animation_name(PlayerState::Idle).to_owned(),
+        HitboxAnimation::new(1.0, vec![vec![spear]]).unwrap(),
+    );
+    engine.set_hitboxes(set);
+    let (a, b) = duel(&mut engine, -2.0, 2.0);
+    run(&mut engine, 1);
+
+    // the standing players have no body, but they both try to attack with the spear
+    assert!(hitboxes(&engine, a).hurt.is_empty());
+    assert_eq!(hitboxes(&engine, b).weapon.len(), 1);
+    assert!(engine
+        .combat_events(&mut reader)
+        .contains(&CombatEvent::Clash { a: 0, b: 1 }));
+}
+
+#[test]
+fn test_hitbox_animation_frames() {
+    let frame = |x: f32| {
+        vec![Hitbox {
+            kind: HitboxKind::Hurt,
+            area: RBox {
+                pos: Vec2::new(x, 0.0),
+                v1: Vec2::new(1.0, 0.0),
+                v2: Vec2::new(0.0, 1.0),
+            },
+        }]
+    };
+    let animation = HitboxAnimation::new(2.0, vec![frame(0.0), frame(1.0)]).unwrap();
+
+    assert_eq!(animation.at(0.0), &frame(0.0)[..]);
+    assert_eq!(animation.at(0.9), &frame(0.0)[..]);
+    assert_eq!(animation.at(1.5), &frame(1.0)[..]);
+    // the animation is looped
+    assert_eq!(animation.at(2.5), &frame(0.0)[..]);
+    assert_eq!(animation.at(-0.5), &frame(1.0)[..]);
+    assert!(HitboxAnimation::new(1.0, Vec::new()).is_err());
+}
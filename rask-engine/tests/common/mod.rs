@@ -0,0 +1,71 @@
+//! The fixtures shared by the integration tests and the benchmarks.
+//! Every test uses only some of them, so unused ones are allowed.
+#![allow(dead_code)]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use rask_engine::engine::{GameEngine, Level, MatchRules, RaskEngine};
+use specs::Entity;
+
+pub const ARENA: &str = include_str!("../../../res/levels/arena.json");
+pub const BRIDGE: &str = include_str!("../../../res/levels/bridge.json");
+
+/// An engine without a level, running its systems on a single thread.
+pub fn engine() -> RaskEngine {
+    engine_with_threads(1)
+}
+
+/// An engine without a level, running its systems on `threads` threads.
+pub fn engine_with_threads(threads: usize) -> RaskEngine {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .unwrap();
+    RaskEngine::new(Arc::new(pool))
+}
+
+/// An engine with the level `json` loaded.
+pub fn engine_with_level(json: &str) -> RaskEngine {
+    let mut engine = engine();
+    engine.load_level(Level::from_json(json).unwrap()).unwrap();
+    engine
+}
+
+/// An engine with the arena loaded.
+pub fn arena() -> RaskEngine {
+    engine_with_level(ARENA)
+}
+
+/// Spawn both players and start a match without a countdown.
+pub fn start_match(engine: &mut RaskEngine) -> (Entity, Entity) {
+    let players = (engine.spawn_player(0), engine.spawn_player(1));
+    engine.start_match(MatchRules {
+        countdown: 0.0,
+        ..MatchRules::default()
+    });
+    players
+}
+
+/// Advance the engine by `steps` ticks of 10ms.
+pub fn run(engine: &mut RaskEngine, steps: u32) {
+    for _ in 0..steps {
+        engine.tick(Duration::from_millis(10)).unwrap();
+    }
+}
+
+/// A linear congruential generator, the random numbers are reproducible.
+#[derive(Debug, Default, Clone)]
+pub struct Lcg(u32);
+
+impl Lcg {
+    pub fn new(seed: u32) -> Self {
+        Self(seed)
+    }
+
+    /// Returns a number in `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        self.0 = self.0.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        (self.0 >> 8) as f32 / (1 << 24) as f32
+    }
+}
@@ -0,0 +1,172 @@
+use rask_engine::boxes::{AABox, RBox};
+use rask_engine::collide::*;
+use rask_engine::math::Vec2;
+
+fn assert_close(a: f32, b: f32) {
+    assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+}
+
+fn assert_close_vec(a: Vec2, b: Vec2) {
+    assert!((a - b).norm() < 1e-5, "{:?} != {:?}", a, b);
+}
+
+#[test]
+fn test_contact_dot_dot() {
+    let a = Vec2::new(1.0, 7.5);
+    let manifold = a.contact(&a).unwrap();
+
+    assert_close(manifold.depth, 0.0);
+    assert_eq!(manifold.points(), &[a]);
+}
+
+#[test]
+fn test_no_contact_dot_dot() {
+    let a = Vec2::new(1.0, 7.5);
+    let b = Vec2::new(5.0, 7.5);
+
+    assert_eq!(a.contact(&b), None);
+}
+
+#[test]
+fn test_contact_dot_aabox() {
+    let aa_box = AABox {
+        pos: Vec2::new(0.0, 0.0),
+        size: Vec2::new(4.0, 4.0),
+    };
+    let a = Vec2::new(3.5, 2.0);
+    let manifold = a.contact(&aa_box).unwrap();
+
+    assert_close_vec(manifold.normal, Vec2::new(1.0, 0.0));
+    assert_close(manifold.depth, 0.5);
+    assert_eq!(manifold.points(), &[a]);
+    assert_close_vec(aa_box.contact(&a).unwrap().normal, Vec2::new(-1.0, 0.0));
+}
+
+#[test]
+fn test_no_contact_dot_rbox() {
+    let rbox = RBox {
+        pos: Vec2::new(1.0, 1.0),
+        v1: Vec2::new(1.0, 1.0),
+        v2: Vec2::new(1.0, -1.0),
+    };
+
+    assert_eq!(rbox.contact(&Vec2::new(1.4, 0.4)), None);
+}
+
+#[test]
+fn test_contact_aabox_aabox_resting() {
+    let aa_box = AABox {
+        pos: Vec2::new(0.0, 0.75),
+        size: Vec2::new(1.0, 1.0),
+    };
+    let floor = AABox {
+        pos: Vec2::new(-5.0, -1.0),
+        size: Vec2::new(10.0, 2.0),
+    };
+    let manifold = aa_box.contact(&floor).unwrap();
+
+    assert_close_vec(manifold.normal, Vec2::new(0.0, 1.0));
+    assert_close(manifold.depth, 0.25);
+    assert_close_vec(manifold.mtv(), Vec2::new(0.0, 0.25));
+    assert_eq!(manifold.points().len(), 2);
+    for &point in manifold.points() {
+        assert!(point.y() >= 0.75 && point.y() <= 1.0);
+        assert!(point.x() >= 0.0 && point.x() <= 1.0);
+    }
+}
+
+#[test]
+fn test_contact_aabox_aabox_flipped() {
+    let aa_box = AABox {
+        pos: Vec2::new(0.0, 0.75),
+        size: Vec2::new(1.0, 1.0),
+    };
+    let floor = AABox {
+        pos: Vec2::new(-5.0, -1.0),
+        size: Vec2::new(10.0, 2.0),
+    };
+    let manifold = floor.contact(&aa_box).unwrap();
+
+    assert_close_vec(manifold.normal, Vec2::new(0.0, -1.0));
+    assert_close_vec(
+        manifold.normal,
+        aa_box.contact(&floor).unwrap().flipped().normal,
+    );
+}
+
+#[test]
+fn test_contact_aabox_aabox_mtv_separates() {
+    let aa_box = AABox {
+        pos: Vec2::new(1.0, 2.5),
+        size: Vec2::new(3.0, 2.5),
+    };
+    let bb_box = AABox {
+        pos: Vec2::new(2.0, 3.5),
+        size: Vec2::new(3.0, 7.5),
+    };
+    let manifold = aa_box.contact(&bb_box).unwrap();
+
+    assert_close_vec(manifold.normal, Vec2::new(0.0, -1.0));
+    assert_close(manifold.depth, 1.5);
+    let moved = aa_box + manifold.mtv() * 1.001;
+    assert!(!moved.collides(&bb_box));
+}
+
+#[test]
+fn test_no_contact_aabox_aabox() {
+    let aa_box = AABox {
+        pos: Vec2::new(1.0, 1.0),
+        size: Vec2::new(1.0, 1.0),
+    };
+    let bb_box = AABox {
+        pos: Vec2::new(3.0, 3.5),
+        size: Vec2::new(3.0, 7.5),
+    };
+
+    assert_eq!(aa_box.contact(&bb_box), None);
+}
+
+#[test]
+fn test_contact_rbox_aabox_corner() {
+    // a diamond with its bottom corner at (0, -0.1)
+    let rbox = RBox {
+        pos: Vec2::new(-1.0, 0.9),
+        v1: Vec2::new(1.0, -1.0),
+        v2: Vec2::new(1.0, 1.0),
+    };
+    let floor = AABox {
+        pos: Vec2::new(-5.0, -1.0),
+        size: Vec2::new(10.0, 1.0),
+    };
+    let manifold = rbox.contact(&floor).unwrap();
+
+    assert_close_vec(manifold.normal, Vec2::new(0.0, 1.0));
+    assert_close(manifold.depth, 0.1);
+    assert_eq!(manifold.points().len(), 1);
+    assert_close_vec(manifold.points()[0], Vec2::new(0.0, -0.1));
+}
+
+#[test]
+fn test_contact_rbox_rbox() {
+    let box1 = RBox::new(Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0), 1.0);
+    let box2 = RBox::new(Vec2::new(0.5, 0.5), Vec2::new(1.0, 1.0), 1.0);
+    let manifold = box1.contact(&box2).unwrap();
+
+    assert_close(manifold.normal.norm(), 1.0);
+    assert!(manifold.depth > 0.0);
+    assert!(!manifold.points().is_empty());
+    let moved = box1 + manifold.mtv() * 1.001;
+    assert!(!moved.collides(&box2));
+}
+
+#[test]
+fn test_no_contact_rbox_rbox() {
+    let box1 = RBox {
+        pos: Vec2::new(1.0, 1.0),
+        v1: Vec2::new(0.0, 1.0),
+        v2: Vec2::new(1.0, 0.0),
+    };
+    let box2 = RBox::new(Vec2::new(3.0, 3.5), Vec2::new(1.0, 1.0), 1.0);
+
+    assert_eq!(box1.contact(&box2), None);
+}
@@ -0,0 +1,188 @@
+mod common;
+
+use std::time::Duration;
+
+use rask_engine::engine::components::*;
+use rask_engine::engine::{GameEngine, Level, RaskEngine};
+use rask_engine::input::{Action, ActionState};
+use rask_engine::math::Vec2;
+use rask_engine::network::desync::DesyncDetector;
+use rask_engine::network::packet::{PacketVariant, Serialize, WebSocketPacket};
+use rask_engine::network::protocol::op_codes;
+use specs::prelude::*;
+
+use common::{engine_with_threads, start_match, ARENA};
+
+fn setup(mut engine: RaskEngine) -> RaskEngine {
+    engine.load_level(Level::from_json(ARENA).unwrap()).unwrap();
+    start_match(&mut engine);
+    engine
+}
+
+fn engine() -> RaskEngine {
+    setup(RaskEngine::new_deterministic())
+}
+
+fn parallel_engine() -> RaskEngine {
+    setup(engine_with_threads(4))
+}
+
+/// The actions of a player in a step, the players run towards each other and fight.
+fn input(player: u32, step: u32) -> ActionState {
+    let mut state = ActionState::default();
+    match (step + player * 7) % 40 {
+        0..=14 if player == 0 => state.press(Action::Right),
+        0..=14 => state.press(Action::Left),
+        20 => state.press(Action::Jump),
+        30 => state.press(Action::Thrust),
+        _ => (),
+    }
+    state
+}
+
+fn step(engine: &mut RaskEngine, step: u32) {
+    engine.set_actions(0, input(0, step));
+    engine.set_actions(1, input(1, step));
+    engine.step();
+}
+
+/// Simulate the steps and return the checksum after each of them.
+fn run(engine: &mut RaskEngine, steps: u32) -> Vec<u64> {
+    (0..steps)
+        .map(|n| {
+            step(engine, n);
+            engine.checksum()
+        })
+        .collect()
+}
+
+#[test]
+fn test_same_inputs_same_checksums() {
+    let (mut a, mut b) = (engine(), engine());
+    assert!(a.is_deterministic());
+    assert_eq!(a.checksum(), b.checksum());
+
+    let checksums = run(&mut a, 300);
+    assert_eq!(run(&mut b, 300), checksums);
+    // the state changed during the game
+    assert!(checksums.windows(2).any(|w| w[0] != w[1]));
+}
+
+#[test]
+fn test_parallel_dispatch_matches() {
+    let mut deterministic = engine();
+    let mut parallel = parallel_engine();
+    assert!(!parallel.is_deterministic());
+
+    assert_eq!(run(&mut parallel, 200), run(&mut deterministic, 200));
+}
+
+#[test]
+fn test_checksum_detects_changes() {
+    let mut engine = engine();
+    run(&mut engine, 10);
+    let snapshot = engine.snapshot();
+    let checksum = engine.checksum();
+    assert_eq!(snapshot.checksum(), checksum);
+
+    let player = (
+        &engine.world().entities(),
+        &engine.world().read_storage::<Player>(),
+    )
+        .join()
+        .map(|(entity, _)| entity)
+        .next()
+        .unwrap();
+    engine
+        .world()
+        .write_storage::<Pos>()
+        .get_mut(player)
+        .unwrap()
+        .0 += Vec2::new(1e-5, 0.0);
+    assert_ne!(engine.checksum(), checksum);
+
+    let mut later = snapshot.clone();
+    later.tick += 1;
+    assert_ne!(later.checksum(), checksum);
+}
+
+#[test]
+fn test_tick_does_not_skip_steps() {
+    let mut deterministic = engine();
+    let mut parallel = parallel_engine();
+    deterministic.tick(Duration::from_millis(1005)).unwrap();
+    parallel.tick(Duration::from_millis(1005)).unwrap();
+
+    assert_eq!(deterministic.tick_nr(), 100);
+    assert!(parallel.tick_nr() < 100);
+    // the remainder is kept
+    deterministic.tick(Duration::from_millis(5)).unwrap();
+    assert_eq!(deterministic.tick_nr(), 101);
+}
+
+#[test]
+fn test_detect_desync() {
+    let (mut local, mut remote) = (engine(), engine());
+    let mut detector = DesyncDetector::new(16);
+    for n in 0..20 {
+        step(&mut local, n);
+        step(&mut remote, n);
+        if n == 12 {
+            // the remote simulation diverges
+            remote.set_actions(1, {
+                let mut jump = ActionState::default();
+                jump.press(Action::Jump);
+                jump
+            });
+            remote.step();
+        }
+        let tick = local.tick_nr();
+        assert_eq!(detector.record(tick, local.checksum()), None);
+        let in_sync = detector.check(tick, remote.checksum());
+        assert_eq!(in_sync, Some(n < 12));
+    }
+    assert_eq!(detector.first_desync(), Some(13));
+    assert_eq!(detector.desyncs(), 8);
+}
+
+#[test]
+fn test_remote_checksum_ahead() {
+    let mut detector = DesyncDetector::new(4);
+    assert_eq!(detector.check(2, 20), None);
+    assert_eq!(detector.check(3, 31), None);
+    assert_eq!(detector.record(1, 10), None);
+    assert_eq!(detector.record(2, 20), Some(true));
+    assert_eq!(detector.record(3, 30), Some(false));
+    assert_eq!(detector.first_desync(), Some(3));
+    // a step simulated again replaces the old checksum
+    assert_eq!(detector.record(3, 31), None);
+    assert_eq!(detector.check(3, 31), Some(true));
+    for tick in 4..10 {
+        detector.record(tick, tick * 10);
+    }
+    // too old to be compared
+    assert_eq!(detector.check(2, 0), None);
+    assert_eq!(detector.desyncs(), 1);
+}
+
+#[test]
+fn test_checksum_packet() {
+    let mut buf = Vec::new();
+    WebSocketPacket {
+        op_code: op_codes::PUSH_CHECKSUM,
+        payload: PacketVariant::PushChecksum {
+            tick: 42,
+            checksum: 0xdead_beef_0123_4567,
+        },
+    }
+    .serialize(&mut buf);
+    assert_eq!(buf.len(), 20);
+    match WebSocketPacket::deserialize(&buf).unwrap().payload {
+        PacketVariant::PushChecksum { tick, checksum } => {
+            assert_eq!(tick, 42);
+            assert_eq!(checksum, 0xdead_beef_0123_4567);
+        }
+        other => panic!("unexpected packet {:?}", other),
+    }
+    assert!(WebSocketPacket::deserialize(&buf[..12]).is_err());
+}
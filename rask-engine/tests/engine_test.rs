@@ -0,0 +1,117 @@
+mod common;
+
+use std::time::Duration;
+
+use rask_engine::engine::components::*;
+use rask_engine::engine::{GameEngine, RaskEngine};
+use rask_engine::events::Event;
+use rask_engine::math::Vec2;
+use specs::prelude::*;
+
+use common::engine;
+
+fn falling_body(engine: &mut RaskEngine) -> Entity {
+    engine
+        .world_mut()
+        .create_entity()
+        .with(Pos(Vec2::new(0.0, 10.0)))
+        .with(Vel(Vec2::zero()))
+        .build()
+}
+
+fn pos(engine: &RaskEngine, entity: Entity) -> Vec2 {
+    engine.world().read_storage::<Pos>().get(entity).unwrap().0
+}
+
+#[test]
+fn test_tick_runs_fixed_steps() {
+    let mut engine = engine();
+    engine.tick(Duration::from_millis(25)).unwrap();
+
+    assert_eq!(engine.tick_nr(), 2);
+    assert!((engine.alpha() - 0.5).abs() < 1e-5);
+
+    engine.tick(Duration::from_millis(5)).unwrap();
+    assert_eq!(engine.tick_nr(), 3);
+    assert!(engine.alpha().abs() < 1e-5);
+}
+
+#[test]
+fn test_tick_too_short() {
+    let mut engine = engine();
+    let body = falling_body(&mut engine);
+    engine.tick(Duration::from_millis(9)).unwrap();
+
+    assert_eq!(engine.tick_nr(), 0);
+    assert_eq!(pos(&engine, body), Vec2::new(0.0, 10.0));
+}
+
+#[test]
+fn test_steps_independent_of_frame_rate() {
+    let mut engine1 = engine();
+    let mut engine2 = engine();
+    let body1 = falling_body(&mut engine1);
+    let body2 = falling_body(&mut engine2);
+
+    engine1.tick(Duration::from_millis(90)).unwrap();
+    for _ in 0..30 {
+        engine2.tick(Duration::from_millis(3)).unwrap();
+    }
+
+    assert_eq!(engine1.tick_nr(), engine2.tick_nr());
+    assert_eq!(pos(&engine1, body1), pos(&engine2, body2));
+}
+
+#[test]
+fn test_interpolated_pos() {
+    let mut engine = engine();
+    let body = falling_body(&mut engine);
+    engine
+        .world_mut()
+        .write_storage::<Vel>()
+        .insert(body, Vel(Vec2::new(100.0, 0.0)))
+        .unwrap();
+    engine
+        .world_mut()
+        .insert(rask_engine::engine::resources::Gravitation(Vec2::zero()));
+    engine.tick(Duration::from_millis(15)).unwrap();
+
+    assert_eq!(pos(&engine, body), Vec2::new(1.0, 10.0));
+    let interpolated = engine.interpolated_pos(body).unwrap();
+    assert!((interpolated.x() - 0.5).abs() < 1e-4, "{:?}", interpolated);
+}
+
+#[test]
+fn test_set_timestep() {
+    let mut engine = engine();
+    engine.set_timestep(Duration::from_millis(50));
+    engine.tick(Duration::from_millis(120)).unwrap();
+
+    assert_eq!(engine.timestep(), Duration::from_millis(50));
+    assert_eq!(engine.tick_nr(), 2);
+}
+
+#[test]
+#[should_panic(expected = "the timestep has to be greater than zero")]
+fn test_zero_timestep() {
+    engine().set_timestep(Duration::from_secs(0));
+}
+
+#[test]
+fn test_tick_drops_time_when_behind() {
+    let mut engine = engine();
+    engine.tick(Duration::from_secs(10)).unwrap();
+
+    assert_eq!(engine.tick_nr(), 10);
+    assert!(engine.alpha() < 1.0);
+}
+
+#[test]
+fn test_events_are_queued() {
+    let mut engine = engine();
+    let body = falling_body(&mut engine);
+    engine.handle_event(Event::KeyPress(0, 0)).unwrap();
+
+    assert_eq!(engine.tick_nr(), 0);
+    assert_eq!(pos(&engine, body), Vec2::new(0.0, 10.0));
+}
@@ -0,0 +1,199 @@
+mod common;
+
+use std::time::Duration;
+
+use rask_engine::engine::GameEngine;
+use rask_engine::events::{Event, Key, KeyModifier, MouseEvent};
+use rask_engine::input::*;
+use specs::WorldExt;
+
+fn key_down(key: Key) -> Event {
+    Event::KeyDown(KeyModifier::NONE, key)
+}
+
+fn key_up(key: Key) -> Event {
+    Event::KeyUp(KeyModifier::NONE, key)
+}
+
+#[test]
+fn test_two_players_one_keyboard() {
+    let mut input = InputHandler::default();
+    input.handle_event(&key_down(Key::KEY_D));
+    input.handle_event(&key_down(Key::ARROW_LEFT));
+
+    assert!(input.player(0).held(Action::Right));
+    assert!(!input.player(0).held(Action::Left));
+    assert!(input.player(1).held(Action::Left));
+    assert!(!input.player(1).held(Action::Right));
+    assert_eq!(input.player(0).direction(), 1.0);
+    assert_eq!(input.player(1).direction(), -1.0);
+}
+
+#[test]
+fn test_pressed_held_released() {
+    let mut input = InputHandler::default();
+    input.handle_event(&key_down(Key::KEY_F));
+    let state = input.player(0);
+    assert!(state.pressed(Action::Jump) && state.held(Action::Jump));
+
+    input.next_tick();
+    let state = input.player(0);
+    assert!(!state.pressed(Action::Jump) && state.held(Action::Jump));
+
+    input.next_tick();
+    input.handle_event(&key_up(Key::KEY_F));
+    let state = input.player(0);
+    assert!(state.released(Action::Jump) && !state.held(Action::Jump));
+
+    input.next_tick();
+    assert_eq!(input.player(0), ActionState::default());
+}
+
+#[test]
+fn test_press_and_release_in_one_tick() {
+    let mut input = InputHandler::default();
+    input.handle_event(&key_down(Key::KEY_G));
+    input.handle_event(&key_up(Key::KEY_G));
+    let state = input.player(0);
+
+    assert!(state.pressed(Action::Thrust));
+    assert!(state.released(Action::Thrust));
+    assert!(!state.held(Action::Thrust));
+}
+
+#[test]
+fn test_key_repeat() {
+    let mut input = InputHandler::default();
+    input.handle_event(&key_down(Key::KEY_F));
+    input.next_tick();
+    input.handle_event(&key_down(Key::KEY_F));
+
+    assert!(!input.player(0).pressed(Action::Jump));
+    assert!(input.player(0).held(Action::Jump));
+}
+
+#[test]
+fn test_unbound_key() {
+    let mut input = InputHandler::default();
+    input.handle_event(&key_down(Key::KEY_Q));
+    input.handle_event(&Event::KeyPress(0, 0));
+
+    assert_eq!(input.player(0), ActionState::default());
+    assert_eq!(input.player(1), ActionState::default());
+}
+
+#[test]
+fn test_rebind() {
+    let mut input = InputHandler::default();
+    input
+        .map_mut()
+        .rebind(0, Action::Jump, Input::Key(Key::SPACE), KeyModifier::NONE);
+    input.handle_event(&key_down(Key::KEY_F));
+    assert!(!input.player(0).held(Action::Jump));
+
+    input.handle_event(&key_down(Key::SPACE));
+    assert!(input.player(0).held(Action::Jump));
+    assert_eq!(input.map().bindings_of(0, Action::Jump).count(), 1);
+}
+
+#[test]
+fn test_bind_replaces_input() {
+    let mut map = InputMap::default();
+    // steal the jump key of the first player
+    map.bind(1, Action::Jump, Input::Key(Key::KEY_F), KeyModifier::NONE);
+    let mut input = InputHandler::new(map);
+    input.handle_event(&key_down(Key::KEY_F));
+
+    assert!(!input.player(0).held(Action::Jump));
+    assert!(input.player(1).held(Action::Jump));
+}
+
+#[test]
+fn test_modifier_binding() {
+    let mut input = InputHandler::default();
+    input
+        .map_mut()
+        .bind(0, Action::Parry, Input::Key(Key::KEY_G), KeyModifier::SHIFT);
+    input.handle_event(&Event::KeyDown(KeyModifier::SHIFT, Key::KEY_G));
+    assert!(input.player(0).held(Action::Parry));
+    assert!(!input.player(0).held(Action::Thrust));
+
+    // shift is released first, but the same action has to be released
+    input.handle_event(&key_up(Key::KEY_G));
+    assert!(!input.player(0).held(Action::Parry));
+
+    input.handle_event(&key_down(Key::KEY_G));
+    assert!(input.player(0).held(Action::Thrust));
+}
+
+#[test]
+fn test_two_inputs_one_action() {
+    let mut input = InputHandler::default();
+    input
+        .map_mut()
+        .bind(0, Action::Jump, Input::Key(Key::SPACE), KeyModifier::NONE);
+    input.handle_event(&key_down(Key::KEY_F));
+    input.handle_event(&key_down(Key::SPACE));
+    input.handle_event(&key_up(Key::KEY_F));
+
+    assert!(input.player(0).held(Action::Jump));
+    input.handle_event(&key_up(Key::SPACE));
+    assert!(!input.player(0).held(Action::Jump));
+}
+
+#[test]
+fn test_mouse_buttons() {
+    let mut input = InputHandler::default();
+    input.map_mut().bind(
+        0,
+        Action::Thrust,
+        Input::Mouse(MouseButton::Left),
+        KeyModifier::NONE,
+    );
+    input.map_mut().bind(
+        0,
+        Action::Parry,
+        Input::Mouse(MouseButton::Right),
+        KeyModifier::NONE,
+    );
+    input.handle_event(&Event::MouseDown(MouseEvent::new(
+        0b01,
+        KeyModifier::NONE,
+        0,
+        0,
+    )));
+    input.handle_event(&Event::MouseDown(MouseEvent::new(
+        0b11,
+        KeyModifier::NONE,
+        0,
+        0,
+    )));
+    assert!(input.player(0).held(Action::Thrust));
+    assert!(input.player(0).held(Action::Parry));
+
+    input.handle_event(&Event::MouseUp(MouseEvent::new(
+        0b10,
+        KeyModifier::NONE,
+        0,
+        0,
+    )));
+    assert!(!input.player(0).held(Action::Thrust));
+    assert!(input.player(0).held(Action::Parry));
+}
+
+#[test]
+fn test_engine_input_per_step() {
+    let mut engine = common::engine();
+    engine.handle_event(key_down(Key::KEY_F)).unwrap();
+    engine.tick(Duration::from_millis(10)).unwrap();
+    assert!(engine
+        .world()
+        .read_resource::<InputHandler>()
+        .player(0)
+        .pressed(Action::Jump));
+
+    engine.tick(Duration::from_millis(10)).unwrap();
+    let input = engine.world().read_resource::<InputHandler>();
+    assert!(!input.player(0).pressed(Action::Jump));
+    assert!(input.player(0).held(Action::Jump));
+}
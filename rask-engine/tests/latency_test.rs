@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+use rask_engine::network::latency::Heartbeat;
+use rask_engine::network::packet::{PacketVariant, Serialize, WebSocketPacket};
+use rask_engine::network::protocol::op_codes;
+
+fn ms(n: u64) -> Duration {
+    Duration::from_millis(n)
+}
+
+fn heartbeat() -> Heartbeat {
+    Heartbeat::new(ms(1000), ms(5000), ms(0))
+}
+
+#[test]
+fn test_ping_interval() {
+    let mut heartbeat = heartbeat();
+    assert_eq!(heartbeat.ping(ms(0)), Some(1));
+    assert_eq!(heartbeat.ping(ms(999)), None);
+    assert_eq!(heartbeat.ping(ms(1000)), Some(2));
+    assert_eq!(heartbeat.ping(ms(1500)), None);
+    assert_eq!(heartbeat.ping(ms(3200)), Some(3));
+}
+
+#[test]
+fn test_round_trip_time() {
+    let mut heartbeat = heartbeat();
+    assert_eq!(heartbeat.rtt(), None);
+    let id = heartbeat.ping(ms(0)).unwrap();
+    assert_eq!(heartbeat.pong(ms(80), id, 0), Some(ms(80)));
+    assert_eq!(heartbeat.rtt(), Some(ms(80)));
+    assert_eq!(heartbeat.latency(), Some(ms(40)));
+    assert_eq!(heartbeat.jitter(), Some(ms(40)));
+
+    // a single slow pong only moves the estimation by an eighth
+    let id = heartbeat.ping(ms(1000)).unwrap();
+    assert_eq!(heartbeat.pong(ms(1160), id, 0), Some(ms(160)));
+    let rtt = heartbeat.rtt().unwrap();
+    assert!(rtt > ms(89) && rtt < ms(91), "{:?}", rtt);
+
+    // the estimation converges to a stable round trip time
+    for n in 2..100 {
+        let id = heartbeat.ping(ms(n * 1000)).unwrap();
+        heartbeat.pong(ms(n * 1000 + 50), id, 0);
+    }
+    let rtt = heartbeat.rtt().unwrap();
+    assert!(rtt > ms(49) && rtt < ms(51), "{:?}", rtt);
+    assert!(heartbeat.jitter().unwrap() < ms(1));
+}
+
+#[test]
+fn test_unknown_and_lost_pongs() {
+    let mut heartbeat = heartbeat();
+    let first = heartbeat.ping(ms(0)).unwrap();
+    let second = heartbeat.ping(ms(1000)).unwrap();
+    assert_eq!(heartbeat.pong(ms(1100), 42, 0), None);
+    assert_eq!(heartbeat.pong(ms(1100), second, 0), Some(ms(100)));
+    // the first ping was lost
+    assert_eq!(heartbeat.pong(ms(1200), first, 0), None);
+    // a pong is only accepted once
+    assert_eq!(heartbeat.pong(ms(1200), second, 0), None);
+    assert_eq!(heartbeat.rtt(), Some(ms(100)));
+}
+
+#[test]
+fn test_clock_offset() {
+    let mut heartbeat = heartbeat();
+    assert_eq!(heartbeat.clock_offset(), None);
+    // the client clock is 2.5 seconds ahead and the ping takes 30ms each way
+    for n in 0..20 {
+        let sent = ms(n * 1000);
+        let id = heartbeat.ping(sent).unwrap();
+        let arrival = sent + ms(30);
+        let client_time = (arrival + ms(2500)).as_micros() as u64;
+        heartbeat.pong(sent + ms(60), id, client_time);
+        assert_eq!(heartbeat.clock_offset(), Some(2_500_000));
+    }
+    assert_eq!(heartbeat.client_time(ms(100_000)), Some(102_500_000));
+}
+
+#[test]
+fn test_timeout() {
+    let mut heartbeat = heartbeat();
+    assert!(!heartbeat.is_timed_out(ms(5000)));
+    assert!(heartbeat.is_timed_out(ms(5001)));
+
+    heartbeat.received(ms(4000));
+    assert!(!heartbeat.is_timed_out(ms(9000)));
+    // a pong counts as activity even if its ping is unknown
+    heartbeat.pong(ms(8000), 7, 0);
+    assert!(!heartbeat.is_timed_out(ms(12000)));
+    assert!(heartbeat.is_timed_out(ms(13001)));
+}
+
+#[test]
+fn test_ping_packets() {
+    let mut buf = Vec::new();
+    WebSocketPacket::new(PacketVariant::Ping {
+        id: 3,
+        tick: 1 << 40,
+    })
+    .serialize(&mut buf);
+    assert_eq!(buf.len(), 16);
+    let packet = WebSocketPacket::deserialize(&buf).unwrap();
+    assert_eq!(packet.op_code, op_codes::PING);
+    match packet.payload {
+        PacketVariant::Ping { id, tick } => assert_eq!((id, tick), (3, 1 << 40)),
+        other => panic!("unexpected packet {:?}", other),
+    }
+
+    // the layout written by main.js
+    let words: [u32; 4] = [op_codes::PONG, 3, 0x89ab_cdef, 0x12];
+    let buf: Vec<u8> = words
+        .iter()
+        .flat_map(|w| w.to_le_bytes().to_vec())
+        .collect();
+    match WebSocketPacket::deserialize(&buf).unwrap().payload {
+        PacketVariant::Pong { id, client_time } => {
+            assert_eq!((id, client_time), (3, 0x12_89ab_cdef))
+        }
+        other => panic!("unexpected packet {:?}", other),
+    }
+    assert!(WebSocketPacket::deserialize(&buf[..15]).is_err());
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use std::time::Duration;
+
+use rask_engine::engine::components::*;
+use rask_engine::engine::{GameEngine, Level, RaskEngine};
+use rask_engine::math::Vec2;
+use rask_engine::EngineError;
+use specs::prelude::*;
+
+use common::{engine, ARENA};
+
+fn count<T: Component>(engine: &RaskEngine) -> usize {
+    engine.world().read_storage::<T>().join().count()
+}
+
+fn assert_format_error(result: Result<Level, EngineError>, message: &str) {
+    match result {
+        Err(EngineError::ResourceFormat(e)) => assert!(e.contains(message), "{}", e),
+        other => panic!("expected a format error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_arena() {
+    let level = Level::from_json(ARENA).unwrap();
+
+    assert_eq!(level.name, "arena");
+    assert_eq!(level.geometry.len(), 6);
+    assert_eq!(level.spawns.len(), 2);
+    assert_eq!(level.goals.len(), 2);
+    assert_eq!(level.bodies[0].material.restitution, 0.6);
+    assert_eq!(
+        level.bodies[0].material.friction,
+        Material::default().friction
+    );
+}
+
+#[test]
+fn test_json_round_trip() {
+    let level = Level::from_json(ARENA).unwrap();
+    let json = level.to_json().unwrap();
+
+    assert_eq!(Level::from_json(&json).unwrap(), level);
+}
+
+#[test]
+fn test_load_level() {
+    let mut engine = engine();
+    engine.load_level(Level::from_json(ARENA).unwrap()).unwrap();
+
+    assert_eq!(count::<Static>(&engine), 6);
+    assert_eq!(count::<SpawnPoint>(&engine), 2);
+    assert_eq!(count::<Goal>(&engine), 2);
+    assert_eq!(count::<Background>(&engine), 1);
+    assert_eq!(count::<Mass>(&engine), 1);
+}
+
+#[test]
+fn test_load_level_replaces_old_level() {
+    let mut engine = engine();
+    let player = engine
+        .world_mut()
+        .create_entity()
+        .with(Pos(Vec2::zero()))
+        .build();
+    engine.load_level(Level::from_json(ARENA).unwrap()).unwrap();
+    engine.load_level(Level::from_json(ARENA).unwrap()).unwrap();
+
+    assert_eq!(count::<LevelEntity>(&engine), 12);
+    assert!(engine.world().is_alive(player));
+}
+
+#[test]
+fn test_level_geometry_is_solid() {
+    let mut engine = engine();
+    engine.load_level(Level::from_json(ARENA).unwrap()).unwrap();
+    for _ in 0..500 {
+        engine.tick(Duration::from_millis(10)).unwrap();
+    }
+
+    // the ball bounced and came to rest on the platform in the middle
+    let world = engine.world();
+    let (pos, mass) = (world.read_storage::<Pos>(), world.read_storage::<Mass>());
+    let (pos, _) = (&pos, &mass).join().next().unwrap();
+    assert!((pos.0.y() + 0.3).abs() < 0.05, "{:?}", pos);
+}
+
+#[test]
+fn test_syntax_error() {
+    assert_format_error(Level::from_json("{\n  \"name\": \"broken\",\n}"), "line 3");
+}
+
+#[test]
+fn test_unknown_field() {
+    assert_format_error(
+        Level::from_json(r#"{ "name": "typo", "geometrie": [] }"#),
+        "unknown field `geometrie`",
+    );
+}
+
+#[test]
+fn test_unknown_texture() {
+    assert_format_error(
+        Level::from_json(
+            r#"{ "name": "test", "backgrounds": [
+                { "texture": 1000, "area": { "pos": [0, 0], "size": [1, 1] } }
+            ] }"#,
+        ),
+        "background 0: unknown texture id 1000",
+    );
+}
+
+#[test]
+fn test_invalid_geometry() {
+    assert_format_error(
+        Level::from_json(
+            r#"{ "name": "test", "geometry": [
+                { "aabox": { "pos": [0, 0], "size": [1, 1] } },
+                { "aabox": { "pos": [0, 0], "size": [1, -1] } }
+            ] }"#,
+        ),
+        "geometry 1",
+    );
+    assert_format_error(
+        Level::from_json(
+            r#"{ "name": "test", "geometry": [
+                { "rbox": { "pos": [0, 0], "v1": [1, 1], "v2": [1, 0] } }
+            ] }"#,
+        ),
+        "not orthogonal",
+    );
+}
+
+#[test]
+fn test_invalid_polygon() {
+    assert_format_error(
+        Level::from_json(
+            r#"{ "name": "test", "geometry": [
+                { "polygon": [[0, 0], [1, 0]] }
+            ] }"#,
+        ),
+        "three vertices",
+    );
+}
+
+#[test]
+fn test_invalid_mass() {
+    assert_format_error(
+        Level::from_json(
+            r#"{ "name": "test", "bodies": [{
+                "pos": [0, 0],
+                "collider": { "circle": { "pos": [0, 0], "radius": 1 } },
+                "mass": 0
+            }] }"#,
+        ),
+        "body 0: the mass 0 is not positive",
+    );
+}
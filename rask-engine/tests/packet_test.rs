@@ -0,0 +1,152 @@
+use rask_engine::events::{Event, Key, KeyModifier, MouseEvent};
+use rask_engine::input::{Action, ActionState};
+use rask_engine::network::packet::{event_op_code, PacketVariant, Serialize, WebSocketPacket};
+use rask_engine::network::protocol::op_codes;
+
+fn encode(payload: PacketVariant) -> Vec<u8> {
+    let mut buf = Vec::new();
+    WebSocketPacket::new(payload).serialize(&mut buf);
+    buf
+}
+
+fn events() -> Vec<Event> {
+    let modifier = KeyModifier::SHIFT | KeyModifier::ALT;
+    vec![
+        Event::KeyDown(modifier, Key::KEY_D),
+        Event::KeyUp(KeyModifier::NONE, Key::SPACE),
+        Event::KeyPress(0x41, 3),
+        Event::MouseDown(MouseEvent::new(0b101, modifier, 640, -20)),
+        Event::MouseUp(MouseEvent::new(1, KeyModifier::CONTROL, -1, i32::MAX)),
+    ]
+}
+
+#[test]
+fn test_input_events() {
+    for (sequence, event) in events().into_iter().enumerate() {
+        let buf = encode(PacketVariant::Input {
+            sequence: sequence as u32,
+            event: event.clone(),
+        });
+        let packet = WebSocketPacket::deserialize(&buf).unwrap();
+        assert_eq!(packet.op_code, event_op_code(&event));
+        match packet.payload {
+            PacketVariant::Input {
+                sequence: decoded_sequence,
+                event: decoded,
+            } => {
+                assert_eq!(decoded_sequence, sequence as u32);
+                assert_eq!(decoded, event);
+            }
+            other => panic!("unexpected packet {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn test_main_js_layout() {
+    // main.js writes the op code, the modifiers and buttons, and the position as 32 bit integers
+    let event = Event::MouseDown(MouseEvent::new(2, KeyModifier::META, 3, 4));
+    let buf = encode(PacketVariant::Input { sequence: 9, event });
+    let words: Vec<u32> = buf
+        .chunks_exact(4)
+        .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+        .collect();
+    assert_eq!(words, vec![op_codes::MOUSE_DOWN, 9, 8 << 8 | 2, 3, 4]);
+
+    let buf = encode(PacketVariant::Input {
+        sequence: 1,
+        event: Event::KeyDown(KeyModifier::CONTROL, Key::ENTER),
+    });
+    assert_eq!(&buf[8..12], &2u32.to_le_bytes());
+    assert_eq!(&buf[12..], &u32::from(Key::ENTER).to_le_bytes());
+}
+
+#[test]
+fn test_engine_events() {
+    for event in events() {
+        let buf = encode(PacketVariant::PushEngineEvent(event.clone()));
+        let packet = WebSocketPacket::deserialize(&buf).unwrap();
+        assert_eq!(packet.op_code, op_codes::PUSH_ENGINE_EVENT);
+        match packet.payload {
+            PacketVariant::PushEngineEvent(decoded) => assert_eq!(decoded, event),
+            other => panic!("unexpected packet {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn test_chat_ready_leave() {
+    let buf = encode(PacketVariant::Chat("gg wp ⚔"));
+    match WebSocketPacket::deserialize(&buf).unwrap().payload {
+        PacketVariant::Chat(message) => assert_eq!(message, "gg wp ⚔"),
+        other => panic!("unexpected packet {:?}", other),
+    }
+    // a cut off character
+    assert!(WebSocketPacket::deserialize(&buf[..buf.len() - 1]).is_err());
+
+    for ready in [true, false].iter() {
+        let buf = encode(PacketVariant::Ready(*ready));
+        match WebSocketPacket::deserialize(&buf).unwrap().payload {
+            PacketVariant::Ready(decoded) => assert_eq!(decoded, *ready),
+            other => panic!("unexpected packet {:?}", other),
+        }
+    }
+
+    let buf = encode(PacketVariant::Leave);
+    assert_eq!(buf, op_codes::LEAVE.to_le_bytes());
+    assert!(matches!(
+        WebSocketPacket::deserialize(&buf).unwrap().payload,
+        PacketVariant::Leave
+    ));
+}
+
+#[test]
+fn test_op_codes() {
+    let mut jump = ActionState::default();
+    jump.press(Action::Jump);
+    let payloads = vec![
+        (PacketVariant::AckSnapshot(3), op_codes::ACK_SNAPSHOT),
+        (
+            PacketVariant::PushInput {
+                sequence: 1,
+                actions: jump,
+            },
+            op_codes::PUSH_INPUT,
+        ),
+        (
+            PacketVariant::PushChecksum {
+                tick: 1,
+                checksum: 2,
+            },
+            op_codes::PUSH_CHECKSUM,
+        ),
+        (PacketVariant::Chat(""), op_codes::CHAT),
+        (PacketVariant::Ready(true), op_codes::READY),
+        (PacketVariant::Leave, op_codes::LEAVE),
+        (PacketVariant::PushSession(u64::MAX), op_codes::PUSH_SESSION),
+    ];
+    for (payload, op_code) in payloads {
+        assert_eq!(payload.op_code(), op_code);
+        let buf = encode(payload);
+        assert_eq!(WebSocketPacket::deserialize(&buf).unwrap().op_code, op_code);
+    }
+}
+
+#[test]
+fn test_truncated_packets() {
+    let mut packets = vec![
+        encode(PacketVariant::Ready(true)),
+        encode(PacketVariant::PushEngineEvent(events()[3].clone())),
+    ];
+    packets.extend(
+        events()
+            .into_iter()
+            .map(|event| encode(PacketVariant::Input { sequence: 1, event })),
+    );
+    for buf in packets {
+        for len in 0..buf.len() {
+            assert!(WebSocketPacket::deserialize(&buf[..len]).is_err());
+        }
+    }
+    assert!(WebSocketPacket::deserialize(&1000u32.to_le_bytes()).is_err());
+}
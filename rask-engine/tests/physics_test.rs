@@ -0,0 +1,178 @@
+use rask_engine::boxes::{AABox, Circle};
+use rask_engine::engine::components::*;
+use rask_engine::engine::resources::*;
+use rask_engine::engine::systems::*;
+use rask_engine::engine::PhysicsSystem;
+use rask_engine::math::Vec2;
+use specs::prelude::*;
+
+fn world() -> (World, Dispatcher<'static, 'static>) {
+    let mut world = World::new();
+    let mut dispatcher = DispatcherBuilder::new()
+        .with(GravitationSystem, "gravitation", &[])
+        .with(VelocitySystem, "velocity", &["gravitation"])
+        .with(PhysicsSystem::default(), "physics", &["velocity"])
+        .build();
+    dispatcher.setup(&mut world);
+    world.insert(Gravitation(Vec2::new(0.0, -9.807)));
+    world.insert(DeltaTime(std::time::Duration::from_millis(10)));
+    (world, dispatcher)
+}
+
+fn run(world: &mut World, dispatcher: &mut Dispatcher, steps: usize) {
+    for _ in 0..steps {
+        dispatcher.dispatch(world);
+        world.maintain();
+    }
+}
+
+fn floor(world: &mut World) -> Entity {
+    world
+        .create_entity()
+        .with(Pos(Vec2::new(-10.0, -1.0)))
+        .with(Collider::AABox(AABox {
+            pos: Vec2::zero(),
+            size: Vec2::new(20.0, 1.0),
+        }))
+        .with(Static)
+        .build()
+}
+
+fn body(world: &mut World, pos: Vec2, vel: Vec2) -> Entity {
+    world
+        .create_entity()
+        .with(Pos(pos))
+        .with(Vel(vel))
+        .with(Mass(1.0))
+        .with(Collider::AABox(AABox {
+            pos: Vec2::zero(),
+            size: Vec2::new(1.0, 1.0),
+        }))
+        .build()
+}
+
+fn pos(world: &World, entity: Entity) -> Vec2 {
+    world.read_storage::<Pos>().get(entity).unwrap().0
+}
+
+fn vel(world: &World, entity: Entity) -> Vec2 {
+    world.read_storage::<Vel>().get(entity).unwrap().0
+}
+
+#[test]
+fn test_body_rests_on_floor() {
+    let (mut world, mut dispatcher) = world();
+    let floor = floor(&mut world);
+    let body = body(&mut world, Vec2::new(0.0, 2.0), Vec2::zero());
+    run(&mut world, &mut dispatcher, 300);
+
+    let pos = pos(&world, body);
+    assert!(pos.y() > -0.05 && pos.y() < 0.05, "{:?}", pos);
+    assert!(vel(&world, body).y().abs() < 0.2);
+    assert!(world
+        .read_resource::<Collisions>()
+        .of(body)
+        .any(|(other, manifold)| other == floor && manifold.normal == Vec2::new(0.0, 1.0)));
+}
+
+#[test]
+fn test_floor_is_not_moved() {
+    let (mut world, mut dispatcher) = world();
+    let floor = floor(&mut world);
+    body(&mut world, Vec2::new(0.0, 0.5), Vec2::new(0.0, -5.0));
+    run(&mut world, &mut dispatcher, 10);
+
+    assert_eq!(pos(&world, floor), Vec2::new(-10.0, -1.0));
+}
+
+#[test]
+fn test_restitution_bounces() {
+    let (mut world, mut dispatcher) = world();
+    floor(&mut world);
+    let body = body(&mut world, Vec2::new(0.0, 0.05), Vec2::new(0.0, -5.0));
+    world
+        .write_storage::<Material>()
+        .insert(
+            body,
+            Material {
+                restitution: 1.0,
+                friction: 0.0,
+            },
+        )
+        .unwrap();
+    run(&mut world, &mut dispatcher, 2);
+
+    assert!(vel(&world, body).y() > 4.0);
+}
+
+#[test]
+fn test_friction_slows_down() {
+    let (mut world, mut dispatcher) = world();
+    floor(&mut world);
+    let body = body(&mut world, Vec2::new(0.0, 0.0), Vec2::new(3.0, 0.0));
+    run(&mut world, &mut dispatcher, 100);
+
+    assert!(vel(&world, body).x() < 0.5);
+}
+
+#[test]
+fn test_no_friction_keeps_sliding() {
+    let (mut world, mut dispatcher) = world();
+    let floor = floor(&mut world);
+    let body = body(&mut world, Vec2::new(0.0, 0.0), Vec2::new(3.0, 0.0));
+    let no_friction = Material {
+        restitution: 0.0,
+        friction: 0.0,
+    };
+    let mut materials = world.write_storage::<Material>();
+    materials.insert(body, no_friction).unwrap();
+    materials.insert(floor, no_friction).unwrap();
+    drop(materials);
+    run(&mut world, &mut dispatcher, 100);
+
+    assert!((vel(&world, body).x() - 3.0).abs() < 1e-3);
+}
+
+#[test]
+fn test_bodies_bump_into_each_other() {
+    let (mut world, mut dispatcher) = world();
+    world.insert(Gravitation(Vec2::zero()));
+    let a = body(&mut world, Vec2::new(0.0, 0.0), Vec2::new(2.0, 0.0));
+    let b = body(&mut world, Vec2::new(1.05, 0.0), Vec2::zero());
+    run(&mut world, &mut dispatcher, 10);
+
+    // the impulse is shared equally and the bodies don't overlap
+    let (va, vb) = (vel(&world, a), vel(&world, b));
+    assert!((va.x() - 1.0).abs() < 1e-3, "{:?}", va);
+    assert!((vb.x() - 1.0).abs() < 1e-3, "{:?}", vb);
+    assert!(pos(&world, b).x() - pos(&world, a).x() > 0.95);
+}
+
+#[test]
+fn test_heavy_body_pushes_light_body() {
+    let (mut world, mut dispatcher) = world();
+    world.insert(Gravitation(Vec2::zero()));
+    let a = body(&mut world, Vec2::new(0.0, 0.0), Vec2::new(2.0, 0.0));
+    let b = body(&mut world, Vec2::new(1.05, 0.0), Vec2::zero());
+    world.write_storage::<Mass>().insert(a, Mass(9.0)).unwrap();
+    run(&mut world, &mut dispatcher, 10);
+
+    assert!((vel(&world, a).x() - 1.8).abs() < 1e-3);
+    assert!((vel(&world, b).x() - 1.8).abs() < 1e-3);
+}
+
+#[test]
+fn test_circle_rests_on_floor() {
+    let (mut world, mut dispatcher) = world();
+    floor(&mut world);
+    let ball = world
+        .create_entity()
+        .with(Pos(Vec2::new(0.0, 3.0)))
+        .with(Vel(Vec2::zero()))
+        .with(Mass(1.0))
+        .with(Collider::Circle(Circle::new(Vec2::zero(), 0.5)))
+        .build();
+    run(&mut world, &mut dispatcher, 300);
+
+    assert!((pos(&world, ball).y() - 0.5).abs() < 0.05);
+}
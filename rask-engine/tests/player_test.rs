@@ -0,0 +1,181 @@
+mod common;
+
+use rask_engine::engine::components::*;
+use rask_engine::engine::player::{LUNGE_TIME, RUN_SPEED};
+use rask_engine::engine::{GameEngine, RaskEngine};
+use rask_engine::events::{Event, Key, KeyModifier};
+use rask_engine::math::Vec2;
+use specs::prelude::*;
+
+use common::{arena, run};
+
+/// Spawn a player and let it land on the floor.
+fn landed_player(engine: &mut RaskEngine, id: u32) -> Entity {
+    let entity = engine.spawn_player(id);
+    run(engine, 100);
+    assert!(player(engine, entity).grounded);
+    entity
+}
+
+fn press(engine: &mut RaskEngine, key: Key) {
+    engine
+        .handle_event(Event::KeyDown(KeyModifier::NONE, key))
+        .unwrap();
+}
+
+fn release(engine: &mut RaskEngine, key: Key) {
+    engine
+        .handle_event(Event::KeyUp(KeyModifier::NONE, key))
+        .unwrap();
+}
+
+fn player(engine: &RaskEngine, entity: Entity) -> Player {
+    *engine.world().read_storage::<Player>().get(entity).unwrap()
+}
+
+fn vel(engine: &RaskEngine, entity: Entity) -> Vec2 {
+    engine.world().read_storage::<Vel>().get(entity).unwrap().0
+}
+
+fn pos(engine: &RaskEngine, entity: Entity) -> Vec2 {
+    engine.world().read_storage::<Pos>().get(entity).unwrap().0
+}
+
+#[test]
+fn test_spawn_at_spawn_point() {
+    let mut engine = arena();
+    let left = engine.spawn_player(0);
+    let right = engine.spawn_player(1);
+
+    assert_eq!(pos(&engine, left), Vec2::new(-3.0, -3.0));
+    assert_eq!(pos(&engine, right), Vec2::new(3.0, -3.0));
+    assert_eq!(player(&engine, left).facing, Facing::Right);
+    assert_eq!(player(&engine, right).facing, Facing::Left);
+    assert_eq!(player(&engine, left).state, PlayerState::Idle);
+}
+
+#[test]
+fn test_land_on_floor() {
+    let mut engine = arena();
+    let entity = landed_player(&mut engine, 0);
+
+    assert!((pos(&engine, entity).y() + 3.5).abs() < 0.05);
+    assert_eq!(player(&engine, entity).state, PlayerState::Idle);
+}
+
+#[test]
+fn test_run() {
+    let mut engine = arena();
+    let entity = landed_player(&mut engine, 1);
+    let start = pos(&engine, entity).x();
+    press(&mut engine, Key::ARROW_RIGHT);
+    run(&mut engine, 10);
+
+    let player = player(&engine, entity);
+    assert_eq!(player.state, PlayerState::Run);
+    assert_eq!(player.facing, Facing::Right);
+    assert!((vel(&engine, entity).x() - RUN_SPEED).abs() < 1e-5);
+    assert!(pos(&engine, entity).x() > start);
+
+    release(&mut engine, Key::ARROW_RIGHT);
+    run(&mut engine, 1);
+    assert_eq!(self::player(&engine, entity).state, PlayerState::Idle);
+    assert_eq!(vel(&engine, entity).x(), 0.0);
+}
+
+#[test]
+fn test_jump_only_when_grounded() {
+    let mut engine = arena();
+    let entity = engine.spawn_player(0);
+    // still in the air
+    press(&mut engine, Key::KEY_F);
+    run(&mut engine, 1);
+    assert_ne!(player(&engine, entity).state, PlayerState::Jump);
+    release(&mut engine, Key::KEY_F);
+
+    run(&mut engine, 100);
+    let floor = pos(&engine, entity).y();
+    press(&mut engine, Key::KEY_F);
+    run(&mut engine, 10);
+    assert_eq!(player(&engine, entity).state, PlayerState::Jump);
+    assert!(!player(&engine, entity).grounded);
+    assert!(pos(&engine, entity).y() > floor);
+
+    run(&mut engine, 200);
+    assert_eq!(player(&engine, entity).state, PlayerState::Idle);
+    assert!(player(&engine, entity).grounded);
+}
+
+#[test]
+fn test_lunge() {
+    let mut engine = arena();
+    let entity = landed_player(&mut engine, 0);
+    press(&mut engine, Key::KEY_G);
+    run(&mut engine, 1);
+    assert_eq!(player(&engine, entity).state, PlayerState::Lunge);
+    assert!(vel(&engine, entity).x() > RUN_SPEED);
+
+    // the lunge can't be cancelled by other actions
+    press(&mut engine, Key::KEY_H);
+    run(&mut engine, 1);
+    assert_eq!(player(&engine, entity).state, PlayerState::Lunge);
+
+    run(&mut engine, (LUNGE_TIME * 100.0) as u32);
+    assert_eq!(player(&engine, entity).state, PlayerState::Idle);
+}
+
+#[test]
+fn test_parry_stops() {
+    let mut engine = arena();
+    let entity = landed_player(&mut engine, 0);
+    press(&mut engine, Key::KEY_D);
+    run(&mut engine, 5);
+    press(&mut engine, Key::KEY_H);
+    run(&mut engine, 1);
+
+    assert_eq!(player(&engine, entity).state, PlayerState::Parry);
+    assert_eq!(vel(&engine, entity).x(), 0.0);
+    run(&mut engine, 50);
+    assert_eq!(player(&engine, entity).state, PlayerState::Run);
+}
+
+#[test]
+fn test_stance() {
+    let mut engine = arena();
+    let entity = landed_player(&mut engine, 0);
+    assert_eq!(player(&engine, entity).stance, Stance::Mid);
+
+    for &(key, stance) in [
+        (Key::KEY_W, Stance::High),
+        (Key::KEY_W, Stance::High),
+        (Key::KEY_S, Stance::Mid),
+        (Key::KEY_S, Stance::Low),
+        (Key::KEY_S, Stance::Low),
+    ]
+    .iter()
+    {
+        press(&mut engine, key);
+        run(&mut engine, 1);
+        release(&mut engine, key);
+        run(&mut engine, 1);
+        assert_eq!(player(&engine, entity).stance, stance);
+    }
+}
+
+#[test]
+fn test_dead_ignores_input() {
+    let mut engine = arena();
+    let entity = landed_player(&mut engine, 0);
+    engine
+        .world_mut()
+        .write_storage::<Player>()
+        .get_mut(entity)
+        .unwrap()
+        .set_state(PlayerState::Dead);
+    press(&mut engine, Key::KEY_D);
+    press(&mut engine, Key::KEY_F);
+    run(&mut engine, 10);
+
+    assert_eq!(player(&engine, entity).state, PlayerState::Dead);
+    assert_eq!(vel(&engine, entity).x(), 0.0);
+}
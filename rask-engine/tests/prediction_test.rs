@@ -0,0 +1,223 @@
+mod common;
+
+use std::time::Duration;
+
+use rask_engine::engine::components::*;
+use rask_engine::engine::{GameEngine, RaskEngine};
+use rask_engine::events::{Event, Key, KeyModifier};
+use rask_engine::input::{Action, ActionState};
+use rask_engine::math::Vec2;
+use rask_engine::network::packet::{PacketVariant, Serialize, WebSocketPacket};
+use rask_engine::network::prediction::*;
+use rask_engine::network::protocol::op_codes;
+use specs::prelude::*;
+
+use common::{arena, start_match};
+
+fn engine() -> (RaskEngine, Entity) {
+    let mut engine = arena();
+    let (player, _) = start_match(&mut engine);
+    (engine, player)
+}
+
+/// Apply the frames of the client the way the server does.
+fn apply(server: &mut RaskEngine, frames: &[InputFrame]) {
+    for frame in frames {
+        server.set_actions(0, frame.actions);
+        server.step();
+    }
+}
+
+fn pos(engine: &RaskEngine, entity: Entity) -> Vec2 {
+    engine.world().read_storage::<Pos>().get(entity).unwrap().0
+}
+
+/// Let the client run to the right for some steps.
+fn run_right(client: &mut RaskEngine, predictor: &mut Predictor, steps: u32) -> Vec<InputFrame> {
+    client
+        .handle_event(Event::KeyDown(KeyModifier::NONE, Key::KEY_D))
+        .unwrap();
+    (0..steps).map(|_| predictor.step(client)).collect()
+}
+
+#[test]
+fn test_record_frames() {
+    let (mut client, player) = engine();
+    let mut predictor = Predictor::new(0, PredictionConfig::default());
+    let start = pos(&client, player);
+    client
+        .handle_event(Event::KeyDown(KeyModifier::NONE, Key::KEY_D))
+        .unwrap();
+    let frames = predictor.tick(&mut client, Duration::from_millis(35));
+
+    assert_eq!(frames.len(), 3);
+    assert_eq!(
+        frames.iter().map(|f| f.sequence).collect::<Vec<_>>(),
+        vec![1, 2, 3]
+    );
+    assert!(frames[0].actions.pressed(Action::Right));
+    assert!(frames[2].actions.held(Action::Right));
+    assert!(!frames[2].actions.pressed(Action::Right));
+    assert!(pos(&client, player).x() > start.x());
+    assert_eq!(predictor.pending().count(), 3);
+}
+
+#[test]
+fn test_matching_snapshot() {
+    let (mut client, _) = engine();
+    let (mut server, _) = engine();
+    let mut predictor = Predictor::new(0, PredictionConfig::default());
+    let frames = run_right(&mut client, &mut predictor, 20);
+    apply(&mut server, &frames[..15]);
+
+    assert!(!predictor.reconcile(&mut client, &server.snapshot(), 15));
+    assert_eq!(predictor.acked(), 15);
+    assert_eq!(
+        predictor.pending().map(|f| f.sequence).collect::<Vec<_>>(),
+        vec![16, 17, 18, 19, 20]
+    );
+}
+
+#[test]
+fn test_correct_prediction() {
+    let (mut client, player) = engine();
+    let (mut server, server_player) = engine();
+    let mut predictor = Predictor::new(0, PredictionConfig::default());
+    let frames = run_right(&mut client, &mut predictor, 20);
+    apply(&mut server, &frames[..10]);
+    // something the client didn't know about pushed the player
+    server
+        .world()
+        .write_storage::<Pos>()
+        .get_mut(server_player)
+        .unwrap()
+        .0 += Vec2::new(0.5, 0.0);
+    let predicted = pos(&client, player);
+
+    assert!(predictor.reconcile(&mut client, &server.snapshot(), 10));
+    apply(&mut server, &frames[10..]);
+    let corrected = pos(&client, player);
+    assert!((corrected - pos(&server, server_player)).norm() < 1e-4);
+    assert!(corrected.x() > predicted.x());
+    // the correction is smoothed
+    let offset = predictor.offset(player.id());
+    assert!((offset - (predicted - corrected)).norm() < 1e-4);
+    predictor.step(&mut client);
+    assert!(predictor.offset(player.id()).norm() < offset.norm());
+    for _ in 0..100 {
+        predictor.step(&mut client);
+    }
+    assert_eq!(predictor.offset(player.id()), Vec2::zero());
+}
+
+#[test]
+fn test_tolerance() {
+    let (mut client, player) = engine();
+    let (mut server, server_player) = engine();
+    let config = PredictionConfig {
+        tolerance: 0.1,
+        ..PredictionConfig::default()
+    };
+    let mut predictor = Predictor::new(0, config);
+    let frames = run_right(&mut client, &mut predictor, 10);
+    apply(&mut server, &frames);
+    server
+        .world()
+        .write_storage::<Pos>()
+        .get_mut(server_player)
+        .unwrap()
+        .0 += Vec2::new(0.05, 0.0);
+    let predicted = pos(&client, player);
+
+    assert!(!predictor.reconcile(&mut client, &server.snapshot(), 10));
+    assert_eq!(pos(&client, player), predicted);
+}
+
+#[test]
+fn test_snap_distance() {
+    let (mut client, player) = engine();
+    let (mut server, server_player) = engine();
+    let mut predictor = Predictor::new(0, PredictionConfig::default());
+    let frames = run_right(&mut client, &mut predictor, 10);
+    apply(&mut server, &frames);
+    server
+        .world()
+        .write_storage::<Pos>()
+        .get_mut(server_player)
+        .unwrap()
+        .0 += Vec2::new(-4.0, 0.0);
+
+    assert!(predictor.reconcile(&mut client, &server.snapshot(), 10));
+    assert_eq!(predictor.offset(player.id()), Vec2::zero());
+    assert_eq!(
+        predictor.display_pos(&client, player),
+        Some(pos(&client, player))
+    );
+}
+
+#[test]
+fn test_outdated_snapshot() {
+    let (mut client, player) = engine();
+    let (mut server, _) = engine();
+    let mut predictor = Predictor::new(0, PredictionConfig::default());
+    let frames = run_right(&mut client, &mut predictor, 10);
+    apply(&mut server, &frames[..5]);
+    let old = server.snapshot();
+    apply(&mut server, &frames[5..]);
+    assert!(!predictor.reconcile(&mut client, &server.snapshot(), 10));
+    let predicted = pos(&client, player);
+
+    assert!(!predictor.reconcile(&mut client, &old, 5));
+    assert_eq!(predictor.acked(), 10);
+    assert_eq!(pos(&client, player), predicted);
+}
+
+#[test]
+fn test_restore_and_actions() {
+    let (mut client, player) = engine();
+    let (mut server, _) = engine();
+    let mut actions = ActionState::default();
+    actions.press(Action::Right);
+    assert_eq!(ActionState::from_bits(actions.to_bits()), actions);
+    server.set_actions(0, actions);
+    for _ in 0..10 {
+        server.step();
+    }
+    assert_eq!(server.actions(0), {
+        let mut held = actions;
+        held.next_tick();
+        held
+    });
+
+    let snapshot = server.snapshot();
+    client.restore(&snapshot);
+    assert_eq!(client.snapshot(), snapshot);
+    assert_eq!(client.tick_nr(), 10);
+    assert_eq!(pos(&client, player), snapshot.player(0).unwrap().pos);
+}
+
+#[test]
+fn test_input_packet() {
+    let mut actions = ActionState::default();
+    actions.press(Action::Jump);
+    let mut buf = Vec::new();
+    WebSocketPacket {
+        op_code: op_codes::PUSH_INPUT,
+        payload: PacketVariant::PushInput {
+            sequence: 3,
+            actions,
+        },
+    }
+    .serialize(&mut buf);
+    assert_eq!(buf.len(), 12);
+    match WebSocketPacket::deserialize(&buf).unwrap().payload {
+        PacketVariant::PushInput {
+            sequence,
+            actions: decoded,
+        } => {
+            assert_eq!(sequence, 3);
+            assert_eq!(decoded, actions);
+        }
+        other => panic!("unexpected packet {:?}", other),
+    }
+}
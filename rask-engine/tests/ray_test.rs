@@ -0,0 +1,183 @@
+use rask_engine::boxes::{AABox, RBox};
+use rask_engine::collide::*;
+use rask_engine::math::Vec2;
+
+fn assert_close(a: f32, b: f32) {
+    assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+}
+
+#[test]
+fn test_cast_ray_aabox_hit() {
+    let ray = Ray::new(Vec2::new(0.0, 0.5), Vec2::new(2.0, 0.0));
+    let aa_box = AABox {
+        pos: Vec2::new(3.0, 0.0),
+        size: Vec2::new(1.0, 1.0),
+    };
+    let hit = ray.cast(&aa_box).unwrap();
+
+    assert_close(hit.distance, 3.0);
+    assert_eq!(hit.point, Vec2::new(3.0, 0.5));
+    assert_eq!(hit.normal, Vec2::new(-1.0, 0.0));
+}
+
+#[test]
+fn test_cast_ray_aabox_miss() {
+    let ray = Ray::new(Vec2::new(0.0, 2.0), Vec2::new(1.0, 0.0));
+    let aa_box = AABox {
+        pos: Vec2::new(3.0, 0.0),
+        size: Vec2::new(1.0, 1.0),
+    };
+
+    assert_eq!(ray.cast(&aa_box), None);
+}
+
+#[test]
+fn test_cast_ray_aabox_behind() {
+    let ray = Ray::new(Vec2::new(5.0, 0.5), Vec2::new(1.0, 0.0));
+    let aa_box = AABox {
+        pos: Vec2::new(3.0, 0.0),
+        size: Vec2::new(1.0, 1.0),
+    };
+
+    assert_eq!(ray.cast(&aa_box), None);
+}
+
+#[test]
+fn test_cast_ray_aabox_diagonal() {
+    let ray = Ray::new(Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0));
+    let aa_box = AABox {
+        pos: Vec2::new(2.0, 1.0),
+        size: Vec2::new(2.0, 4.0),
+    };
+    let hit = ray.cast(&aa_box).unwrap();
+
+    assert_close(hit.distance, 2.0 * 2.0f32.sqrt());
+    assert_close(hit.point.x(), 2.0);
+    assert_close(hit.point.y(), 2.0);
+    assert_eq!(hit.normal, Vec2::new(-1.0, 0.0));
+}
+
+#[test]
+fn test_cast_ray_aabox_from_above() {
+    let ray = Ray::new(Vec2::new(0.5, 10.0), Vec2::new(0.0, -1.0));
+    let floor = AABox {
+        pos: Vec2::new(-5.0, 0.0),
+        size: Vec2::new(10.0, 1.0),
+    };
+    let hit = ray.cast(&floor).unwrap();
+
+    assert_close(hit.distance, 9.0);
+    assert_eq!(hit.normal, Vec2::new(0.0, 1.0));
+}
+
+#[test]
+fn test_cast_ray_aabox_inside() {
+    let ray = Ray::new(Vec2::new(0.5, 0.5), Vec2::new(1.0, 0.0));
+    let aa_box = AABox {
+        pos: Vec2::new(0.0, 0.0),
+        size: Vec2::new(1.0, 1.0),
+    };
+    let hit = ray.cast(&aa_box).unwrap();
+
+    assert_close(hit.distance, 0.0);
+    assert_eq!(hit.point, ray.origin);
+    assert_eq!(hit.normal, Vec2::new(-1.0, 0.0));
+}
+
+#[test]
+fn test_cast_ray_aabox_parallel_outside() {
+    let ray = Ray::new(Vec2::new(0.0, 1.5), Vec2::new(0.0, 1.0));
+    let aa_box = AABox {
+        pos: Vec2::new(1.0, 0.0),
+        size: Vec2::new(1.0, 5.0),
+    };
+
+    assert_eq!(ray.cast(&aa_box), None);
+}
+
+#[test]
+fn test_cast_ray_rbox_hit() {
+    // a diamond with its left corner at (2, 0)
+    let rbox = RBox {
+        pos: Vec2::new(2.0, 0.0),
+        v1: Vec2::new(1.0, -1.0),
+        v2: Vec2::new(1.0, 1.0),
+    };
+    let ray = Ray::new(Vec2::new(0.0, 0.5), Vec2::new(1.0, 0.0));
+    let hit = ray.cast(&rbox).unwrap();
+
+    assert_close(hit.distance, 2.5);
+    assert_eq!(hit.point, Vec2::new(2.5, 0.5));
+    assert_eq!(hit.normal, Vec2::new(-1.0, 1.0).normalized());
+}
+
+#[test]
+fn test_cast_ray_rbox_miss() {
+    let rbox = RBox {
+        pos: Vec2::new(2.0, 0.0),
+        v1: Vec2::new(1.0, -1.0),
+        v2: Vec2::new(1.0, 1.0),
+    };
+    let ray = Ray::new(Vec2::new(0.0, 1.5), Vec2::new(1.0, 0.0));
+
+    assert_eq!(ray.cast(&rbox), None);
+}
+
+#[test]
+fn test_cast_segment_aabox_hit() {
+    let segment = Segment::new(Vec2::new(0.5, 5.0), Vec2::new(0.5, -5.0));
+    let aa_box = AABox {
+        pos: Vec2::new(0.0, 0.0),
+        size: Vec2::new(1.0, 1.0),
+    };
+    let hit = segment.cast(&aa_box).unwrap();
+
+    assert_close(hit.distance, 4.0);
+    assert_eq!(hit.point, Vec2::new(0.5, 1.0));
+    assert_eq!(hit.normal, Vec2::new(0.0, 1.0));
+}
+
+#[test]
+fn test_cast_segment_aabox_too_short() {
+    let segment = Segment::new(Vec2::new(0.5, 5.0), Vec2::new(0.5, 2.0));
+    let aa_box = AABox {
+        pos: Vec2::new(0.0, 0.0),
+        size: Vec2::new(1.0, 1.0),
+    };
+
+    assert_eq!(segment.cast(&aa_box), None);
+}
+
+#[test]
+fn test_cast_segment_rbox_hit() {
+    let rbox = RBox::new(Vec2::new(3.0, -1.0), Vec2::new(0.0, 2.0), 1.0);
+    let segment = Segment::new(Vec2::new(0.0, 0.0), Vec2::new(4.0, 0.0));
+    let hit = segment.cast(&rbox).unwrap();
+
+    assert_close(hit.distance, 2.0);
+    assert_eq!(hit.point, Vec2::new(2.0, 0.0));
+    assert_eq!(hit.normal, Vec2::new(-1.0, 0.0));
+}
+
+#[test]
+fn test_cast_ray_slice_nearest() {
+    let ray = Ray::new(Vec2::new(0.0, 0.5), Vec2::new(1.0, 0.0));
+    let walls = [
+        AABox {
+            pos: Vec2::new(7.0, 0.0),
+            size: Vec2::new(1.0, 1.0),
+        },
+        AABox {
+            pos: Vec2::new(3.0, 0.0),
+            size: Vec2::new(1.0, 1.0),
+        },
+        AABox {
+            pos: Vec2::new(1.0, 3.0),
+            size: Vec2::new(1.0, 1.0),
+        },
+    ];
+    let hit = ray.cast(&walls[..]).unwrap();
+
+    assert_close(hit.distance, 3.0);
+    assert_eq!(ray.cast(&walls[2..]), None);
+}
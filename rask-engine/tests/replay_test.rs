@@ -0,0 +1,182 @@
+mod common;
+
+use std::time::Duration;
+
+use rask_engine::engine::replay::CHECKSUM_INTERVAL;
+use rask_engine::engine::{
+    GameEngine, Level, MatchRules, RaskEngine, Replay, ReplayPlayer, ReplayRecorder,
+};
+use rask_engine::events::{Event, Key, KeyModifier};
+use rask_engine::input::{Action, ActionState};
+use rask_engine::math::Vec2;
+use rask_engine::network::packet::Serialize;
+
+use common::ARENA;
+
+fn replay() -> Replay {
+    let mut replay = Replay::new(
+        Level::from_json(ARENA).unwrap(),
+        vec![0, 1],
+        Some(MatchRules {
+            countdown: 0.0,
+            ..MatchRules::default()
+        }),
+    );
+    replay.seed = 7;
+    replay
+}
+
+/// The actions of a player in a step, the players run towards each other and fight.
+fn input(player: u32, step: u32) -> ActionState {
+    let mut state = ActionState::default();
+    let phase = (step + player * 11) % 50;
+    match phase {
+        0..=19 if player == 0 => state.press(Action::Right),
+        0..=19 => state.press(Action::Left),
+        25 => state.press(Action::Jump),
+        35 => state.press(Action::Thrust),
+        _ => (),
+    }
+    if phase > 0 {
+        // the run is held down
+        state.next_tick();
+    }
+    state
+}
+
+/// Record a match and return the replay and the engine after the last step.
+fn record(steps: u32) -> (Replay, RaskEngine) {
+    let mut engine = RaskEngine::new_deterministic();
+    let mut recorder = ReplayRecorder::new(replay(), &mut engine).unwrap();
+    for step in 0..steps {
+        engine.set_actions(0, input(0, step));
+        engine.set_actions(1, input(1, step));
+        recorder.step(&mut engine);
+    }
+    (recorder.finish(&engine), engine)
+}
+
+#[test]
+fn test_record_and_verify() {
+    let (replay, engine) = record(450);
+
+    assert_eq!(replay.steps(), 450);
+    assert_eq!(replay.checksum(), Some(engine.checksum()));
+    assert_eq!(replay.verify().unwrap(), engine.checksum());
+    assert_eq!(engine.seed(), 7);
+}
+
+#[test]
+fn test_encoding() {
+    let (replay, _) = record(300);
+    let mut buf = Vec::new();
+    replay.serialize(&mut buf);
+
+    assert_eq!(Replay::deserialize(&buf).unwrap(), replay);
+    // only the changes of the actions are stored
+    assert!(replay.inputs().len() < 300);
+    assert!(buf.len() < ARENA.len() + 300 * 12);
+}
+
+#[test]
+fn test_playback_reproduces_match() {
+    let mut recorded = RaskEngine::new_deterministic();
+    let mut recorder = ReplayRecorder::new(replay(), &mut recorded).unwrap();
+    let mut snapshots = Vec::new();
+    for step in 0..250 {
+        recorded.set_actions(0, input(0, step));
+        recorded.set_actions(1, input(1, step));
+        recorder.step(&mut recorded);
+        snapshots.push(recorded.snapshot());
+    }
+    let replay = recorder.finish(&recorded);
+
+    let mut engine = RaskEngine::new_deterministic();
+    let mut player = ReplayPlayer::new(replay, &mut engine).unwrap();
+    for snapshot in snapshots.iter() {
+        assert!(player.step(&mut engine));
+        assert_eq!(&engine.snapshot(), snapshot);
+    }
+    assert!(player.is_finished());
+    assert!(!player.step(&mut engine));
+    assert_eq!(player.first_mismatch(), None);
+}
+
+#[test]
+fn test_tick_stops_at_the_end() {
+    let (replay, _) = record(150);
+    let mut engine = RaskEngine::new_deterministic();
+    let mut player = ReplayPlayer::new(replay, &mut engine).unwrap();
+
+    assert_eq!(player.tick(&mut engine, Duration::from_millis(1000)), 100);
+    assert_eq!(player.position(), 100);
+    assert_eq!(player.tick(&mut engine, Duration::from_millis(1000)), 50);
+    assert!(player.is_finished());
+    assert_eq!(engine.tick_nr(), 150);
+}
+
+#[test]
+fn test_record_events() {
+    let mut engine = RaskEngine::new_deterministic();
+    let mut recorder = ReplayRecorder::new(replay(), &mut engine).unwrap();
+    engine
+        .handle_event(Event::KeyDown(KeyModifier::NONE, Key::KEY_D))
+        .unwrap();
+    recorder.tick(&mut engine, Duration::from_millis(400));
+    engine
+        .handle_event(Event::KeyUp(KeyModifier::NONE, Key::KEY_D))
+        .unwrap();
+    recorder.tick(&mut engine, Duration::from_millis(200));
+    let replay = recorder.finish(&engine);
+
+    // the key was pressed and released
+    assert_eq!(replay.inputs().len(), 2);
+    assert_eq!(replay.verify().unwrap(), engine.checksum());
+}
+
+#[test]
+fn test_divergence() {
+    let (mut replay, _) = record(2 * CHECKSUM_INTERVAL + 50);
+    replay.level.spawns[0].pos += Vec2::new(0.5, 0.0);
+
+    let mut engine = RaskEngine::new_deterministic();
+    let mut player = ReplayPlayer::new(replay.clone(), &mut engine).unwrap();
+    while player.step(&mut engine) {}
+    assert_eq!(player.first_mismatch(), Some(CHECKSUM_INTERVAL));
+    assert!(replay.verify().is_err());
+}
+
+#[test]
+fn test_invalid_replays() {
+    let (replay, _) = record(10);
+    let mut buf = Vec::new();
+    replay.serialize(&mut buf);
+
+    assert!(Replay::deserialize(&buf[..buf.len() - 1]).is_err());
+    let mut magic = buf.clone();
+    magic[0] = b'X';
+    assert!(Replay::deserialize(&magic).is_err());
+    let mut version = buf.clone();
+    version[4] += 1;
+    assert!(Replay::deserialize(&version).is_err());
+}
+
+#[test]
+fn test_playback_drops_old_events() {
+    let (replay, _) = record(2000);
+    let mut engine = RaskEngine::new_deterministic();
+    let mut player = ReplayPlayer::new(replay.clone(), &mut engine).unwrap();
+    let mut total = 0;
+    let mut last = 0;
+    while player.step(&mut engine) {
+        last = engine.poll_events().len();
+        total += last;
+    }
+
+    // without polling only the events of the last step are kept
+    let mut engine = RaskEngine::new_deterministic();
+    let mut player = ReplayPlayer::new(replay, &mut engine).unwrap();
+    while player.step(&mut engine) {}
+    assert!(total > 1000);
+    assert_eq!(engine.poll_events().len(), last);
+}
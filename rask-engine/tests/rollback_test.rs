@@ -0,0 +1,303 @@
+mod common;
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use rask_engine::engine::components::*;
+use rask_engine::engine::{GameEngine, RaskEngine};
+use rask_engine::input::{Action, ActionState};
+use rask_engine::network::packet::{PacketVariant, Serialize, WebSocketPacket};
+use rask_engine::network::protocol::op_codes;
+use rask_engine::network::rollback::*;
+use specs::prelude::*;
+
+use common::{arena, start_match, Lcg};
+
+fn engine() -> RaskEngine {
+    let mut engine = arena();
+    start_match(&mut engine);
+    engine
+}
+
+/// A one way connection delivering packets after a delay and dropping some of them.
+#[derive(Default)]
+struct Link {
+    /// The packets and the time they arrive at.
+    packets: VecDeque<(u32, Vec<u8>)>,
+    latency: u32,
+    /// Every packet is lost with this probability.
+    loss: f32,
+    time: u32,
+    /// Decides which packets are lost.
+    random: Lcg,
+}
+
+impl Link {
+    fn new(latency: u32, loss: f32, seed: u32) -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(Self {
+            latency,
+            loss,
+            random: Lcg::new(seed),
+            ..Self::default()
+        }))
+    }
+}
+
+/// The end of a loopback connection between two peers in the same process.
+struct Loopback {
+    outgoing: Rc<RefCell<Link>>,
+    incoming: Rc<RefCell<Link>>,
+}
+
+impl Transport for Loopback {
+    fn send(&mut self, packet: Vec<u8>) {
+        let mut link = self.outgoing.borrow_mut();
+        if link.random.next_f32() >= link.loss {
+            let arrival = link.time + link.latency;
+            link.packets.push_back((arrival, packet));
+        }
+    }
+
+    fn receive(&mut self) -> Option<Vec<u8>> {
+        let mut link = self.incoming.borrow_mut();
+        match link.packets.front() {
+            Some(&(arrival, _)) if arrival <= link.time => link.packets.pop_front().map(|p| p.1),
+            _ => None,
+        }
+    }
+}
+
+/// Creates the two ends of a connection with the same latency and loss in both directions.
+fn loopback(latency: u32, loss: f32) -> (Loopback, Loopback, [Rc<RefCell<Link>>; 2]) {
+    let (a, b) = (Link::new(latency, loss, 1), Link::new(latency, loss, 2));
+    (
+        Loopback {
+            outgoing: a.clone(),
+            incoming: b.clone(),
+        },
+        Loopback {
+            outgoing: b.clone(),
+            incoming: a.clone(),
+        },
+        [a, b],
+    )
+}
+
+/// The input of a player in a frame, a short sequence of runs, jumps and thrusts.
+fn input(player: u32, frame: u32) -> ActionState {
+    let mut state = ActionState::default();
+    let phase = (frame + player * 17) % 60;
+    if phase < 20 {
+        state.press(if player == 0 {
+            Action::Right
+        } else {
+            Action::Left
+        });
+    }
+    if phase == 25 {
+        state.press(Action::Jump);
+    }
+    if phase == 40 {
+        state.press(Action::Thrust);
+    }
+    state
+}
+
+struct Peer {
+    engine: RaskEngine,
+    session: RollbackSession,
+    transport: Loopback,
+    player: u32,
+    /// The number of inputs made so far.
+    inputs: u32,
+}
+
+impl Peer {
+    fn update(&mut self) -> bool {
+        let input = input(self.player, self.inputs);
+        let simulated = self
+            .session
+            .update(&mut self.engine, &mut self.transport, input);
+        if simulated {
+            self.inputs += 1;
+        }
+        simulated
+    }
+}
+
+/// Run two peers over a loopback connection until both simulated the given number of frames
+/// and confirmed them.
+fn duel(config: RollbackConfig, latency: u32, loss: f32, frames: u32) -> (Peer, Peer) {
+    let (a, b, links) = loopback(latency, loss);
+    let mut peers = (
+        Peer {
+            engine: engine(),
+            session: RollbackSession::new(0, 1, config),
+            transport: a,
+            player: 0,
+            inputs: 0,
+        },
+        Peer {
+            engine: engine(),
+            session: RollbackSession::new(1, 0, config),
+            transport: b,
+            player: 1,
+            inputs: 0,
+        },
+    );
+    for time in 0..10 * frames {
+        for link in links.iter() {
+            link.borrow_mut().time = time;
+        }
+        // the peers stop at the same frame to compare them
+        for peer in [&mut peers.0, &mut peers.1].iter_mut() {
+            if peer.session.frame() < frames {
+                peer.update();
+            } else {
+                peer.session.poll(&mut peer.engine, &mut peer.transport);
+            }
+        }
+        if peers.0.session.confirmed_frame() == frames
+            && peers.1.session.confirmed_frame() == frames
+        {
+            return peers;
+        }
+    }
+    panic!("the peers did not confirm {} frames", frames);
+}
+
+/// Simulate the duel directly with the delayed inputs of both players.
+fn reference(config: RollbackConfig, frames: u32) -> RaskEngine {
+    let mut engine = engine();
+    let delayed = |player, frame: u32| match frame.checked_sub(config.input_delay) {
+        Some(frame) => input(player, frame),
+        None => ActionState::default(),
+    };
+    for frame in 0..frames {
+        engine.set_actions(0, delayed(0, frame));
+        engine.set_actions(1, delayed(1, frame));
+        engine.step();
+    }
+    engine
+}
+
+#[test]
+fn test_save_and_load_state() {
+    let mut engine = engine();
+    let mut actions = ActionState::default();
+    actions.press(Action::Right);
+    engine.set_actions(0, actions);
+    for _ in 0..10 {
+        engine.step();
+    }
+    let saved = engine.save_state();
+    let snapshot = engine.snapshot();
+    for _ in 0..30 {
+        engine.step();
+    }
+    let later = engine.snapshot();
+
+    engine.load_state(&saved);
+    assert_eq!(saved.tick(), 10);
+    assert_eq!(engine.snapshot(), snapshot);
+    // the player keeps running, because the held actions are restored as well
+    for _ in 0..30 {
+        engine.step();
+    }
+    assert_eq!(engine.snapshot(), later);
+}
+
+#[test]
+fn test_without_latency() {
+    let config = RollbackConfig::default();
+    let (a, b) = duel(config, 0, 0.0, 200);
+
+    let expected = reference(config, 200).snapshot();
+    assert_eq!(a.engine.snapshot(), expected);
+    assert_eq!(b.engine.snapshot(), expected);
+}
+
+#[test]
+fn test_latency_and_loss() {
+    let config = RollbackConfig {
+        input_delay: 2,
+        max_rollback: 12,
+    };
+    let (a, b) = duel(config, 5, 0.2, 300);
+
+    let expected = reference(config, 300).snapshot();
+    assert_eq!(a.engine.snapshot(), expected);
+    assert_eq!(b.engine.snapshot(), expected);
+    assert!(a.session.rollbacks() > 0);
+    assert!(b.session.rollbacks() > 0);
+    // both players were moving
+    assert!(expected.player(0).unwrap().pos.x() > -3.0);
+    assert!(expected.player(1).unwrap().pos.x() < 3.0);
+}
+
+#[test]
+fn test_input_delay_avoids_rollbacks() {
+    let config = RollbackConfig {
+        input_delay: 4,
+        max_rollback: 8,
+    };
+    let (a, b) = duel(config, 3, 0.0, 200);
+
+    assert_eq!(a.session.rollbacks(), 0);
+    assert_eq!(b.session.rollbacks(), 0);
+    assert_eq!(a.engine.snapshot(), reference(config, 200).snapshot());
+}
+
+#[test]
+fn test_stall_without_remote_input() {
+    let config = RollbackConfig::default();
+    let (transport, _other, _) = loopback(0, 0.0);
+    let mut peer = Peer {
+        engine: engine(),
+        session: RollbackSession::new(0, 1, config),
+        transport,
+        player: 0,
+        inputs: 0,
+    };
+    for _ in 0..config.max_rollback {
+        assert!(peer.update());
+    }
+    assert!(peer.session.is_stalled());
+    assert!(!peer.update());
+    assert_eq!(peer.session.frame(), config.max_rollback);
+    assert_eq!(peer.session.confirmed_frame(), 0);
+    assert_eq!(
+        peer.engine.world().read_storage::<Player>().join().count(),
+        2
+    );
+}
+
+#[test]
+fn test_rollback_input_packet() {
+    let mut jump = ActionState::default();
+    jump.press(Action::Jump);
+    let inputs = vec![ActionState::default(), jump];
+    let mut buf = Vec::new();
+    WebSocketPacket {
+        op_code: op_codes::PUSH_ROLLBACK_INPUT,
+        payload: PacketVariant::PushRollbackInput {
+            ack: 4,
+            start: 9,
+            inputs: inputs.clone(),
+        },
+    }
+    .serialize(&mut buf);
+    match WebSocketPacket::deserialize(&buf).unwrap().payload {
+        PacketVariant::PushRollbackInput {
+            ack,
+            start,
+            inputs: decoded,
+        } => {
+            assert_eq!((ack, start), (4, 9));
+            assert_eq!(decoded, inputs);
+        }
+        other => panic!("unexpected packet {:?}", other),
+    }
+    assert!(WebSocketPacket::deserialize(&buf[..buf.len() - 1]).is_err());
+}
@@ -0,0 +1,198 @@
+mod common;
+
+use rask_engine::engine::components::*;
+use rask_engine::engine::rules::*;
+use rask_engine::engine::{GameEngine, RaskEngine};
+use rask_engine::events::{Event, Key, KeyModifier};
+use rask_engine::math::Vec2;
+use specs::prelude::*;
+
+use common::{engine_with_level, run, BRIDGE};
+
+fn rules() -> MatchRules {
+    MatchRules {
+        countdown: 0.5,
+        respawn_time: 1.0,
+        round_over_time: 0.5,
+        rounds_to_win: 2,
+        ..MatchRules::default()
+    }
+}
+
+/// Start a match on the bridge and wait until the countdown is over.
+fn engine() -> (RaskEngine, Entity, Entity) {
+    let mut engine = engine_with_level(BRIDGE);
+    let (a, b) = (engine.spawn_player(0), engine.spawn_player(1));
+    engine.start_match(rules());
+    run(&mut engine, 60);
+    assert_eq!(state(&engine).phase, MatchPhase::Playing);
+    (engine, a, b)
+}
+
+fn state(engine: &RaskEngine) -> MatchState {
+    engine.match_state().unwrap()
+}
+
+fn player(engine: &RaskEngine, entity: Entity) -> Player {
+    *engine.world().read_storage::<Player>().get(entity).unwrap()
+}
+
+fn pos(engine: &RaskEngine, entity: Entity) -> Vec2 {
+    engine.world().read_storage::<Pos>().get(entity).unwrap().0
+}
+
+fn set_x(engine: &mut RaskEngine, entity: Entity, x: f32) {
+    let mut pos = engine.world().write_storage::<Pos>();
+    let pos = pos.get_mut(entity).unwrap();
+    pos.0 = Vec2::new(x, pos.0.y());
+}
+
+/// Let player 0 kill player 1.
+fn kill(engine: &mut RaskEngine, a: Entity, b: Entity) {
+    set_x(engine, a, -1.0);
+    set_x(engine, b, 1.3);
+    {
+        let mut player = engine.world().write_storage::<Player>();
+        player.get_mut(a).unwrap().stance = Stance::Low;
+        player.get_mut(b).unwrap().stance = Stance::High;
+    }
+    engine
+        .handle_event(Event::KeyDown(KeyModifier::NONE, Key::KEY_G))
+        .unwrap();
+    run(engine, 20);
+    engine
+        .handle_event(Event::KeyUp(KeyModifier::NONE, Key::KEY_G))
+        .unwrap();
+    assert_eq!(player(engine, b).state, PlayerState::Dead);
+}
+
+#[test]
+fn test_screens_of_arena() {
+    let state = MatchState::new(MatchRules::default(), (-24.0, 24.0));
+    assert_eq!(state.screens, (-1, 1));
+    assert_eq!(state.screen, 0);
+    assert_eq!(state.screen_bounds(), (-8.0, 8.0));
+
+    let state = MatchState::new(MatchRules::default(), (-8.0, 8.0));
+    assert_eq!(state.screens, (0, 0));
+}
+
+#[test]
+fn test_countdown_freezes_players() {
+    let mut engine = engine_with_level(BRIDGE);
+    let a = engine.spawn_player(0);
+    assert!(engine.match_state().is_none());
+    engine.start_match(rules());
+    run(&mut engine, 10);
+    let start = pos(&engine, a).x();
+    engine
+        .handle_event(Event::KeyDown(KeyModifier::NONE, Key::KEY_D))
+        .unwrap();
+    run(&mut engine, 10);
+
+    assert!(matches!(state(&engine).phase, MatchPhase::Countdown { .. }));
+    assert_eq!(pos(&engine, a).x(), start);
+    run(&mut engine, 40);
+    assert_eq!(state(&engine).phase, MatchPhase::Playing);
+    assert!(pos(&engine, a).x() > start);
+}
+
+#[test]
+fn test_kill_gives_right_of_way() {
+    let (mut engine, a, b) = engine();
+    assert_eq!(state(&engine).right_of_way, None);
+    kill(&mut engine, a, b);
+
+    let state = state(&engine);
+    assert_eq!(state.right_of_way, Some(0));
+    assert_eq!(state.kills_of(0), 1);
+    assert_eq!(state.respawns.len(), 1);
+    assert_eq!(state.respawns[0].player, 1);
+}
+
+#[test]
+fn test_respawn_ahead_of_advancing_player() {
+    let (mut engine, a, b) = engine();
+    kill(&mut engine, a, b);
+    run(&mut engine, 110);
+
+    let revived = player(&engine, b);
+    assert_ne!(revived.state, PlayerState::Dead);
+    assert!(revived.armed);
+    assert_eq!(revived.facing, Facing::Left);
+    assert!(pos(&engine, b).x() > pos(&engine, a).x());
+    assert!(state(&engine).respawns.is_empty());
+}
+
+#[test]
+fn test_screen_follows_right_of_way() {
+    let (mut engine, a, b) = engine();
+    // without the right of way nobody leaves the screen
+    set_x(&mut engine, a, 9.0);
+    run(&mut engine, 1);
+    assert_eq!(state(&engine).screen, 0);
+    assert!(pos(&engine, a).x() <= 8.0);
+
+    kill(&mut engine, a, b);
+    set_x(&mut engine, a, 8.5);
+    run(&mut engine, 2);
+    assert_eq!(state(&engine).screen, 1);
+    // the advancing player can't go back
+    set_x(&mut engine, a, 7.0);
+    run(&mut engine, 1);
+    assert!(pos(&engine, a).x() >= 8.0);
+}
+
+#[test]
+fn test_left_behind_respawns_ahead() {
+    let (mut engine, a, b) = engine();
+    kill(&mut engine, a, b);
+    run(&mut engine, 110);
+    set_x(&mut engine, b, -5.0);
+    set_x(&mut engine, a, 8.5);
+    run(&mut engine, 2);
+
+    assert_eq!(state(&engine).screen, 1);
+    assert!(pos(&engine, b).x() > 8.5);
+}
+
+#[test]
+fn test_round_and_match_over() {
+    let (mut engine, a, b) = engine();
+    for round in 1..=2 {
+        kill(&mut engine, a, b);
+        {
+            let mut state = engine.world().write_resource::<MatchState>();
+            state.screen = 1;
+        }
+        set_x(&mut engine, a, 23.0);
+        run(&mut engine, 1);
+        assert_eq!(state(&engine).wins_of(0), round);
+        if round == 1 {
+            assert!(matches!(
+                state(&engine).phase,
+                MatchPhase::RoundOver { winner: 0, .. }
+            ));
+            // the next round starts at the spawn points
+            run(&mut engine, 110);
+            let state = state(&engine);
+            assert_eq!(state.phase, MatchPhase::Playing);
+            assert_eq!(state.screen, 0);
+            assert_eq!(state.right_of_way, None);
+            assert_eq!(pos(&engine, b).x(), 3.0);
+            assert_ne!(player(&engine, b).state, PlayerState::Dead);
+        }
+    }
+    assert_eq!(state(&engine).phase, MatchPhase::MatchOver { winner: 0 });
+}
+
+#[test]
+fn test_serialize_state() {
+    let (mut engine, a, b) = engine();
+    kill(&mut engine, a, b);
+    let state = state(&engine);
+    let json = state.to_json().unwrap();
+
+    assert!(json.contains("\"phase\":\"playing\""));
+    assert_eq!(MatchState::from_json(&json).unwrap(), state);
+}
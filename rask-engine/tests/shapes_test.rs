@@ -0,0 +1,355 @@
+use rask_engine::boxes::{AABox, BoundingBox, Capsule, Circle, Polygon, RBox};
+use rask_engine::collide::*;
+use rask_engine::math::Vec2;
+
+fn triangle() -> Polygon {
+    Polygon::new(vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(4.0, 0.0),
+        Vec2::new(0.0, 4.0),
+    ])
+    .unwrap()
+}
+
+#[test]
+fn test_collide_circle_dot() {
+    let circle = Circle::new(Vec2::new(1.0, 1.0), 2.0);
+    let a = Vec2::new(2.0, 2.5);
+
+    assert!(circle.collides(&a));
+    assert!(a.collides(&circle));
+}
+
+#[test]
+fn test_not_collide_circle_dot() {
+    let circle = Circle::new(Vec2::new(1.0, 1.0), 2.0);
+    let a = Vec2::new(2.5, 2.5);
+
+    assert!(!circle.collides(&a));
+    assert!(!a.collides(&circle));
+}
+
+#[test]
+fn test_collide_circle_circle_intersecting() {
+    let circle1 = Circle::new(Vec2::new(0.0, 0.0), 1.0);
+    let circle2 = Circle::new(Vec2::new(1.5, 0.5), 1.0);
+
+    assert!(circle1.collides(&circle2));
+}
+
+#[test]
+fn test_collide_circle_circle_touching() {
+    let circle1 = Circle::new(Vec2::new(0.0, 0.0), 1.0);
+    let circle2 = Circle::new(Vec2::new(0.0, 3.0), 2.0);
+
+    assert!(circle1.collides(&circle2));
+}
+
+#[test]
+fn test_not_collide_circle_circle() {
+    let circle1 = Circle::new(Vec2::new(0.0, 0.0), 1.0);
+    let circle2 = Circle::new(Vec2::new(2.0, 2.0), 1.0);
+
+    assert!(!circle1.collides(&circle2));
+}
+
+#[test]
+fn test_collide_circle_aabox_edge() {
+    let circle = Circle::new(Vec2::new(0.5, 2.0), 1.5);
+    let aa_box = AABox {
+        pos: Vec2::new(0.0, 0.0),
+        size: Vec2::new(1.0, 1.0),
+    };
+
+    assert!(circle.collides(&aa_box));
+    assert!(aa_box.collides(&circle));
+}
+
+#[test]
+fn test_collide_circle_aabox_inside() {
+    let circle = Circle::new(Vec2::new(2.0, 2.0), 0.5);
+    let aa_box = AABox {
+        pos: Vec2::new(0.0, 0.0),
+        size: Vec2::new(4.0, 4.0),
+    };
+
+    assert!(circle.collides(&aa_box));
+    assert!(aa_box.collides(&circle));
+}
+
+#[test]
+fn test_not_collide_circle_aabox_corner() {
+    // the circle overlaps the bounding box, but not the corner itself
+    let circle = Circle::new(Vec2::new(2.0, 2.0), 1.2);
+    let aa_box = AABox {
+        pos: Vec2::new(0.0, 0.0),
+        size: Vec2::new(1.0, 1.0),
+    };
+
+    assert!(!circle.collides(&aa_box));
+    assert!(!aa_box.collides(&circle));
+}
+
+#[test]
+fn test_collide_circle_rbox() {
+    // a diamond with its right corner at (2, 0)
+    let rbox = RBox {
+        pos: Vec2::new(0.0, 0.0),
+        v1: Vec2::new(1.0, -1.0),
+        v2: Vec2::new(1.0, 1.0),
+    };
+    let circle = Circle::new(Vec2::new(2.5, 0.0), 0.6);
+
+    assert!(circle.collides(&rbox));
+    assert!(rbox.collides(&circle));
+}
+
+#[test]
+fn test_not_collide_circle_rbox() {
+    let rbox = RBox {
+        pos: Vec2::new(0.0, 0.0),
+        v1: Vec2::new(1.0, -1.0),
+        v2: Vec2::new(1.0, 1.0),
+    };
+    // close to the corner of the bounding box, but far from the diamond
+    let circle = Circle::new(Vec2::new(2.0, 1.0), 0.6);
+
+    assert!(!circle.collides(&rbox));
+    assert!(!rbox.collides(&circle));
+}
+
+#[test]
+fn test_collide_capsule_dot() {
+    let capsule = Capsule::new(Vec2::new(0.0, 0.0), Vec2::new(0.0, 4.0), 1.0);
+    let a = Vec2::new(0.5, 3.0);
+
+    assert!(capsule.collides(&a));
+    assert!(a.collides(&capsule));
+}
+
+#[test]
+fn test_not_collide_capsule_dot() {
+    let capsule = Capsule::new(Vec2::new(0.0, 0.0), Vec2::new(0.0, 4.0), 1.0);
+    let a = Vec2::new(0.8, 4.8);
+
+    assert!(!capsule.collides(&a));
+    assert!(!a.collides(&capsule));
+}
+
+#[test]
+fn test_collide_capsule_circle() {
+    let capsule = Capsule::new(Vec2::new(0.0, 0.0), Vec2::new(4.0, 0.0), 0.5);
+    let circle = Circle::new(Vec2::new(2.0, 1.0), 0.6);
+
+    assert!(capsule.collides(&circle));
+    assert!(circle.collides(&capsule));
+}
+
+#[test]
+fn test_collide_capsule_capsule_crossed() {
+    let capsule1 = Capsule::new(Vec2::new(0.0, 0.0), Vec2::new(4.0, 4.0), 0.1);
+    let capsule2 = Capsule::new(Vec2::new(0.0, 4.0), Vec2::new(4.0, 0.0), 0.1);
+
+    assert!(capsule1.collides(&capsule2));
+}
+
+#[test]
+fn test_collide_capsule_capsule_collinear() {
+    let capsule1 = Capsule::new(Vec2::new(0.0, 0.0), Vec2::new(2.0, 0.0), 0.5);
+    let capsule2 = Capsule::new(Vec2::new(2.8, 0.0), Vec2::new(5.0, 0.0), 0.5);
+
+    assert!(capsule1.collides(&capsule2));
+}
+
+#[test]
+fn test_not_collide_capsule_capsule_parallel() {
+    let capsule1 = Capsule::new(Vec2::new(0.0, 0.0), Vec2::new(4.0, 0.0), 0.5);
+    let capsule2 = Capsule::new(Vec2::new(0.0, 1.5), Vec2::new(4.0, 1.5), 0.5);
+
+    assert!(!capsule1.collides(&capsule2));
+}
+
+#[test]
+fn test_collide_capsule_aabox_crossed() {
+    // the center line goes through the box without any end inside of it
+    let capsule = Capsule::new(Vec2::new(-2.0, 0.5), Vec2::new(3.0, 0.5), 0.1);
+    let aa_box = AABox {
+        pos: Vec2::new(0.0, 0.0),
+        size: Vec2::new(1.0, 1.0),
+    };
+
+    assert!(capsule.collides(&aa_box));
+    assert!(aa_box.collides(&capsule));
+}
+
+#[test]
+fn test_not_collide_capsule_rbox() {
+    let capsule = Capsule::new(Vec2::new(-2.0, 3.0), Vec2::new(3.0, 3.0), 0.5);
+    let rbox = RBox::new(Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0), 1.0);
+
+    assert!(!capsule.collides(&rbox));
+    assert!(!rbox.collides(&capsule));
+}
+
+#[test]
+fn test_collide_polygon_dot() {
+    let polygon = triangle();
+
+    assert!(polygon.collides(&Vec2::new(1.0, 1.0)));
+    assert!(Vec2::new(2.0, 2.0).collides(&polygon));
+}
+
+#[test]
+fn test_not_collide_polygon_dot() {
+    let polygon = triangle();
+
+    assert!(!polygon.collides(&Vec2::new(2.5, 2.5)));
+    assert!(!Vec2::new(-0.1, 1.0).collides(&polygon));
+}
+
+#[test]
+fn test_collide_polygon_aabox_intersecting() {
+    let aa_box = AABox {
+        pos: Vec2::new(1.5, 1.5),
+        size: Vec2::new(2.0, 2.0),
+    };
+
+    assert!(triangle().collides(&aa_box));
+    assert!(aa_box.collides(&triangle()));
+}
+
+#[test]
+fn test_not_collide_polygon_aabox_slope() {
+    // the box lies above the slope of the triangle
+    let aa_box = AABox {
+        pos: Vec2::new(2.5, 2.5),
+        size: Vec2::new(1.0, 1.0),
+    };
+
+    assert!(!triangle().collides(&aa_box));
+    assert!(!aa_box.collides(&triangle()));
+}
+
+#[test]
+fn test_collide_polygon_rbox_inside() {
+    let rbox = RBox::new(Vec2::new(0.5, 0.5), Vec2::new(0.5, 0.5), 0.2);
+
+    assert!(triangle().collides(&rbox));
+    assert!(rbox.collides(&triangle()));
+}
+
+#[test]
+fn test_collide_polygon_circle() {
+    let circle = Circle::new(Vec2::new(3.0, 3.0), 1.5);
+
+    assert!(triangle().collides(&circle));
+    assert!(circle.collides(&triangle()));
+}
+
+#[test]
+fn test_collide_polygon_capsule() {
+    let capsule = Capsule::new(Vec2::new(-1.0, -1.0), Vec2::new(-1.0, 5.0), 1.0);
+
+    assert!(triangle().collides(&capsule));
+    assert!(capsule.collides(&triangle()));
+}
+
+#[test]
+fn test_collide_polygon_polygon() {
+    let polygon = triangle() + Vec2::new(1.0, 1.0);
+
+    assert!(triangle().collides(&polygon));
+}
+
+#[test]
+fn test_not_collide_polygon_polygon() {
+    let polygon = Polygon::new(vec![
+        Vec2::new(4.0, 4.0),
+        Vec2::new(4.0, 2.5),
+        Vec2::new(2.5, 4.0),
+    ])
+    .unwrap();
+
+    assert!(!triangle().collides(&polygon));
+    assert!(!polygon.collides(&triangle()));
+}
+
+#[test]
+fn test_polygon_clockwise() {
+    let polygon = Polygon::new(vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(0.0, 4.0),
+        Vec2::new(4.0, 0.0),
+    ])
+    .unwrap();
+
+    assert_eq!(polygon, triangle());
+}
+
+#[test]
+fn test_polygon_invalid() {
+    assert!(Polygon::new(vec![Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0)]).is_err());
+    // concave
+    assert!(Polygon::new(vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(4.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 4.0),
+    ])
+    .is_err());
+    // a pentagram turns in one direction but goes around twice
+    let star = (0..5)
+        .map(|i| {
+            let angle = i as f32 * 4.0 * std::f32::consts::PI / 5.0;
+            Vec2::new(angle.cos(), angle.sin())
+        })
+        .collect();
+    assert!(Polygon::new(star).is_err());
+    // all points on one line
+    assert!(Polygon::new(vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(2.0, 0.0),
+    ])
+    .is_err());
+}
+
+#[test]
+fn test_bounding_boxes() {
+    let circle = Circle::new(Vec2::new(1.0, 2.0), 0.5);
+    let capsule = Capsule::new(Vec2::new(1.0, 2.0), Vec2::new(-1.0, 4.0), 0.5);
+
+    assert_eq!(
+        circle.bounding_box(),
+        AABox {
+            pos: Vec2::new(0.5, 1.5),
+            size: Vec2::new(1.0, 1.0),
+        }
+    );
+    assert_eq!(
+        capsule.bounding_box(),
+        AABox {
+            pos: Vec2::new(-1.5, 1.5),
+            size: Vec2::new(3.0, 3.0),
+        }
+    );
+    assert_eq!(
+        triangle().bounding_box(),
+        AABox {
+            pos: Vec2::new(0.0, 0.0),
+            size: Vec2::new(4.0, 4.0),
+        }
+    );
+}
+
+#[test]
+fn test_collide_circle_slice() {
+    let circle = Circle::new(Vec2::new(0.0, 0.0), 1.0);
+    let shapes = [
+        Circle::new(Vec2::new(5.0, 0.0), 1.0),
+        Circle::new(Vec2::new(1.0, 1.0), 1.0),
+    ];
+
+    assert!(circle.collides(&shapes[..]));
+    assert!(!circle.collides(&shapes[..1]));
+}
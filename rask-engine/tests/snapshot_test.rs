@@ -0,0 +1,209 @@
+mod common;
+
+use rask_engine::engine::broadcast::{EntitySnapshot, WorldSnapshot};
+use rask_engine::engine::{GameEngine, RaskEngine};
+use rask_engine::events::{Event, Key, KeyModifier};
+use rask_engine::math::Vec2;
+use rask_engine::network::packet::{PacketVariant, Serialize, WebSocketPacket};
+use rask_engine::network::protocol::op_codes;
+use rask_engine::network::snapshot::*;
+use rask_engine::EngineError;
+
+use common::{arena, run, start_match};
+
+/// An engine running a match with two players.
+fn engine() -> RaskEngine {
+    let mut engine = arena();
+    start_match(&mut engine);
+    run(&mut engine, 10);
+    engine
+}
+
+fn encode<T: Serialize>(snapshot: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    snapshot.serialize(&mut buf);
+    buf
+}
+
+fn assert_network_error<T: std::fmt::Debug>(result: Result<T, EngineError>) {
+    match result {
+        Err(EngineError::Network(_)) => (),
+        other => panic!("expected a network error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_full_round_trip() {
+    let snapshot = engine().snapshot();
+    let buf = encode(&snapshot);
+
+    assert_eq!(buf[0], SNAPSHOT_VERSION);
+    assert_eq!(WorldSnapshot::baseline_of(&buf).unwrap(), None);
+    assert_eq!(WorldSnapshot::deserialize(&buf, None).unwrap(), snapshot);
+}
+
+#[test]
+fn test_delta_round_trip() {
+    let mut engine = engine();
+    let baseline = engine.snapshot();
+    engine
+        .handle_event(Event::KeyDown(KeyModifier::NONE, Key::KEY_D))
+        .unwrap();
+    run(&mut engine, 5);
+    let snapshot = engine.snapshot();
+    let delta = encode(&SnapshotDelta {
+        baseline: &baseline,
+        snapshot: &snapshot,
+    });
+
+    assert_eq!(
+        WorldSnapshot::baseline_of(&delta).unwrap(),
+        Some(baseline.tick)
+    );
+    assert_eq!(
+        WorldSnapshot::deserialize(&delta, Some(&baseline)).unwrap(),
+        snapshot
+    );
+    assert!(delta.len() < encode(&snapshot).len());
+}
+
+#[test]
+fn test_unchanged_delta_is_small() {
+    let snapshot = engine().snapshot();
+    let mut next = snapshot.clone();
+    next.tick += 1;
+    let delta = encode(&SnapshotDelta {
+        baseline: &snapshot,
+        snapshot: &next,
+    });
+
+    // the header, no entities, no removed entities and an unchanged match
+    assert_eq!(delta.len(), 2 + 8 + 8 + 4 + 4 + 1);
+    assert_eq!(
+        WorldSnapshot::deserialize(&delta, Some(&snapshot)).unwrap(),
+        next
+    );
+}
+
+#[test]
+fn test_delta_added_and_removed_entities() {
+    let entity = |id: u32| EntitySnapshot {
+        entity: id,
+        pos: Vec2::new(id as f32, 0.0),
+        vel: Vec2::zero(),
+        player: None,
+    };
+    let baseline = WorldSnapshot {
+        tick: 1,
+        entities: vec![entity(1), entity(2), entity(4)],
+        match_state: None,
+    };
+    let mut moved = entity(4);
+    moved.vel = Vec2::new(1.0, 0.0);
+    let snapshot = WorldSnapshot {
+        tick: 2,
+        entities: vec![entity(0), entity(1), entity(3), moved],
+        match_state: None,
+    };
+    let delta = encode(&SnapshotDelta {
+        baseline: &baseline,
+        snapshot: &snapshot,
+    });
+
+    assert_eq!(
+        WorldSnapshot::deserialize(&delta, Some(&baseline)).unwrap(),
+        snapshot
+    );
+}
+
+#[test]
+fn test_missing_baseline() {
+    let mut engine = engine();
+    let baseline = engine.snapshot();
+    run(&mut engine, 1);
+    let other = engine.snapshot();
+    run(&mut engine, 1);
+    let delta = encode(&SnapshotDelta {
+        baseline: &baseline,
+        snapshot: &engine.snapshot(),
+    });
+
+    assert_network_error(WorldSnapshot::deserialize(&delta, None));
+    assert_network_error(WorldSnapshot::deserialize(&delta, Some(&other)));
+}
+
+#[test]
+fn test_invalid_encoding() {
+    let buf = encode(&engine().snapshot());
+
+    let mut wrong_version = buf.clone();
+    wrong_version[0] = SNAPSHOT_VERSION + 1;
+    assert_network_error(WorldSnapshot::deserialize(&wrong_version, None));
+    for len in [0, 1, 5, 20, buf.len() - 1].iter() {
+        assert_network_error(WorldSnapshot::deserialize(&buf[..*len], None));
+    }
+}
+
+#[test]
+fn test_sender_uses_acknowledged_baseline() {
+    let mut engine = engine();
+    let mut sender = SnapshotSender::new(4);
+    let mut receiver = SnapshotReceiver::new(4);
+
+    let first = sender.encode(engine.snapshot());
+    assert_eq!(WorldSnapshot::baseline_of(&first).unwrap(), None);
+    let tick = receiver.decode(&first).unwrap().tick;
+    sender.ack(tick);
+    assert_eq!(sender.acked(), Some(tick));
+
+    for _ in 0..3 {
+        run(&mut engine, 1);
+        let buf = sender.encode(engine.snapshot());
+        assert_eq!(WorldSnapshot::baseline_of(&buf).unwrap(), Some(tick));
+        assert_eq!(receiver.decode(&buf).unwrap(), &engine.snapshot());
+    }
+    // an outdated acknowledgement is ignored
+    sender.ack(tick - 1);
+    assert_eq!(sender.acked(), Some(tick));
+
+    // the baseline is forgotten, so the snapshot has to be sent completely
+    run(&mut engine, 1);
+    sender.encode(engine.snapshot());
+    run(&mut engine, 1);
+    let buf = sender.encode(engine.snapshot());
+    assert_eq!(WorldSnapshot::baseline_of(&buf).unwrap(), None);
+    assert_eq!(receiver.decode(&buf).unwrap(), &engine.snapshot());
+    assert_eq!(receiver.latest(), Some(&engine.snapshot()));
+}
+
+#[test]
+fn test_snapshot_packets() {
+    let buf = encode(&engine().snapshot());
+    let mut packet = Vec::new();
+    WebSocketPacket {
+        op_code: op_codes::PUSH_SNAPSHOT,
+        payload: PacketVariant::PushSnapshot {
+            input: 7,
+            data: &buf,
+        },
+    }
+    .serialize(&mut packet);
+    match WebSocketPacket::deserialize(&packet).unwrap().payload {
+        PacketVariant::PushSnapshot { input, data } => {
+            assert_eq!(input, 7);
+            assert_eq!(data, &buf[..]);
+        }
+        other => panic!("unexpected packet {:?}", other),
+    }
+
+    let mut packet = Vec::new();
+    WebSocketPacket {
+        op_code: op_codes::ACK_SNAPSHOT,
+        payload: PacketVariant::AckSnapshot(42),
+    }
+    .serialize(&mut packet);
+    match WebSocketPacket::deserialize(&packet).unwrap().payload {
+        PacketVariant::AckSnapshot(tick) => assert_eq!(tick, 42),
+        other => panic!("unexpected packet {:?}", other),
+    }
+}
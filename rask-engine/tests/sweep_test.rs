@@ -0,0 +1,192 @@
+use rask_engine::boxes::{AABox, RBox};
+use rask_engine::collide::*;
+use rask_engine::math::Vec2;
+
+fn assert_close(a: f32, b: f32) {
+    assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+}
+
+#[test]
+fn test_sweep_aabox_aabox_hit() {
+    let aa_box = AABox {
+        pos: Vec2::new(0.0, 0.0),
+        size: Vec2::new(1.0, 1.0),
+    };
+    let bb_box = AABox {
+        pos: Vec2::new(3.0, 0.5),
+        size: Vec2::new(1.0, 1.0),
+    };
+    let impact = aa_box.sweep(Vec2::new(4.0, 0.0), &bb_box).unwrap();
+
+    assert_close(impact.time, 0.5);
+    assert_eq!(impact.normal, Vec2::new(-1.0, 0.0));
+}
+
+#[test]
+fn test_sweep_aabox_aabox_miss() {
+    let aa_box = AABox {
+        pos: Vec2::new(0.0, 0.0),
+        size: Vec2::new(1.0, 1.0),
+    };
+    let bb_box = AABox {
+        pos: Vec2::new(3.0, 2.0),
+        size: Vec2::new(1.0, 1.0),
+    };
+
+    assert_eq!(aa_box.sweep(Vec2::new(4.0, 0.0), &bb_box), None);
+}
+
+#[test]
+fn test_sweep_aabox_aabox_too_short() {
+    let aa_box = AABox {
+        pos: Vec2::new(0.0, 0.0),
+        size: Vec2::new(1.0, 1.0),
+    };
+    let bb_box = AABox {
+        pos: Vec2::new(3.0, 0.0),
+        size: Vec2::new(1.0, 1.0),
+    };
+
+    assert_eq!(aa_box.sweep(Vec2::new(1.5, 0.0), &bb_box), None);
+}
+
+#[test]
+fn test_sweep_aabox_aabox_tunneling() {
+    let aa_box = AABox {
+        pos: Vec2::new(0.0, 0.0),
+        size: Vec2::new(1.0, 1.0),
+    };
+    let wall = AABox {
+        pos: Vec2::new(5.0, -10.0),
+        size: Vec2::new(0.1, 20.0),
+    };
+    let displacement = Vec2::new(10.0, 0.0);
+
+    assert!(!(aa_box + displacement).collides(&wall));
+    let impact = aa_box.sweep(displacement, &wall).unwrap();
+    assert_close(impact.time, 0.4);
+    assert_eq!(impact.normal, Vec2::new(-1.0, 0.0));
+}
+
+#[test]
+fn test_sweep_aabox_aabox_overlapping() {
+    let aa_box = AABox {
+        pos: Vec2::new(0.0, 0.0),
+        size: Vec2::new(2.0, 2.0),
+    };
+    let bb_box = AABox {
+        pos: Vec2::new(1.0, 1.0),
+        size: Vec2::new(2.0, 2.0),
+    };
+    let impact = aa_box.sweep(Vec2::new(0.0, -1.0), &bb_box).unwrap();
+
+    assert_close(impact.time, 0.0);
+}
+
+#[test]
+fn test_sweep_aabox_aabox_separating() {
+    let aa_box = AABox {
+        pos: Vec2::new(0.0, 1.0),
+        size: Vec2::new(1.0, 1.0),
+    };
+    let floor = AABox {
+        pos: Vec2::new(-5.0, 0.0),
+        size: Vec2::new(10.0, 1.0),
+    };
+
+    assert_eq!(aa_box.sweep(Vec2::new(0.0, 1.0), &floor), None);
+}
+
+#[test]
+fn test_sweep_aabox_aabox_falling() {
+    let aa_box = AABox {
+        pos: Vec2::new(0.0, 3.0),
+        size: Vec2::new(1.0, 1.0),
+    };
+    let floor = AABox {
+        pos: Vec2::new(-5.0, 0.0),
+        size: Vec2::new(10.0, 1.0),
+    };
+    let impact = aa_box.sweep(Vec2::new(0.0, -4.0), &floor).unwrap();
+
+    assert_close(impact.time, 0.5);
+    assert_eq!(impact.normal, Vec2::new(0.0, 1.0));
+}
+
+#[test]
+fn test_sweep_rbox_aabox_hit() {
+    // a diamond with its right corner at (1, 0)
+    let rbox = RBox {
+        pos: Vec2::new(-1.0, 0.0),
+        v1: Vec2::new(1.0, -1.0),
+        v2: Vec2::new(1.0, 1.0),
+    };
+    let wall = AABox {
+        pos: Vec2::new(3.0, -5.0),
+        size: Vec2::new(1.0, 10.0),
+    };
+    let impact = rbox.sweep(Vec2::new(4.0, 0.0), &wall).unwrap();
+
+    assert_close(impact.time, 0.5);
+    assert_eq!(impact.normal, Vec2::new(-1.0, 0.0));
+}
+
+#[test]
+fn test_sweep_aabox_rbox_hit() {
+    let aa_box = AABox {
+        pos: Vec2::new(-0.5, 3.0),
+        size: Vec2::new(1.0, 1.0),
+    };
+    // a diamond with its top corner at (0, 1)
+    let rbox = RBox {
+        pos: Vec2::new(-1.0, 0.0),
+        v1: Vec2::new(1.0, -1.0),
+        v2: Vec2::new(1.0, 1.0),
+    };
+    let impact = aa_box.sweep(Vec2::new(0.0, -4.0), &rbox).unwrap();
+
+    assert_close(impact.time, 0.5);
+    assert_eq!(impact.normal, Vec2::new(0.0, 1.0));
+}
+
+#[test]
+fn test_sweep_rbox_rbox_hit() {
+    let box1 = RBox::new(Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0), 1.0);
+    let box2 = RBox::new(Vec2::new(6.0, 0.0), Vec2::new(1.0, 1.0), 1.0);
+    let impact = box1.sweep(Vec2::new(6.0, 0.0), &box2).unwrap();
+
+    assert!(impact.time > 0.0 && impact.time < 1.0);
+    assert!((box1 + Vec2::new(6.0, 0.0) * impact.time).collides(&box2));
+    assert!(!(box1 + Vec2::new(6.0, 0.0) * (impact.time - 0.01)).collides(&box2));
+    assert_close(impact.normal.norm(), 1.0);
+    assert!(impact.normal.x() < 0.0);
+}
+
+#[test]
+fn test_sweep_rbox_rbox_miss() {
+    let box1 = RBox::new(Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0), 1.0);
+    let box2 = RBox::new(Vec2::new(6.0, 5.0), Vec2::new(1.0, 1.0), 1.0);
+
+    assert_eq!(box1.sweep(Vec2::new(6.0, 0.0), &box2), None);
+}
+
+#[test]
+fn test_sweep_aabox_slice_first_impact() {
+    let aa_box = AABox {
+        pos: Vec2::new(0.0, 0.0),
+        size: Vec2::new(1.0, 1.0),
+    };
+    let walls = [
+        AABox {
+            pos: Vec2::new(7.0, 0.0),
+            size: Vec2::new(1.0, 1.0),
+        },
+        AABox {
+            pos: Vec2::new(3.0, 0.0),
+            size: Vec2::new(1.0, 1.0),
+        },
+    ];
+    let impact = aa_box.sweep(Vec2::new(10.0, 0.0), &walls[..]).unwrap();
+
+    assert_close(impact.time, 0.2);
+}
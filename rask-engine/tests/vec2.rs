@@ -101,6 +101,23 @@ fn test_dot() {
     assert!(f32::abs(a.dot(b) - -39.0) < EPSILON);
 }
 
+#[test]
+fn test_cross() {
+    let a = Vec2::new(3.0, -6.0);
+    let b = Vec2::new(1.0, 7.0);
+
+    assert!(f32::abs(a.cross(b) - 27.0) < EPSILON);
+    assert!(f32::abs(b.cross(a) - -27.0) < EPSILON);
+}
+
+#[test]
+fn test_perp() {
+    let a = Vec2::new(3.0, -6.0);
+
+    assert_eq!(a.perp(), Vec2::new(6.0, 3.0));
+    assert!(f32::abs(a.dot(a.perp())) < EPSILON);
+}
+
 #[test]
 fn test_normalized_vec2() {
     let a = Vec2::new(2.0, -2.0);
@@ -26,24 +26,27 @@ pub struct TokenResponse {
 
 #[allow(dead_code)]
 /// Make a plaintext get request to API_ENDPOINT/{location}.
-pub fn request(location: &str) -> Option<String> {
+pub async fn request(location: &str) -> Option<String> {
     let uri = &format!("{}{}", API_ENDPOINT, location);
-    reqwest::get(uri)
-        .and_then(|mut res| res.text())
-        .map_err(|err| log::warn!("request on \"{}\" failed: {}", uri, err))
+    let res = match reqwest::get(uri).await {
+        Ok(res) => res.text().await,
+        Err(err) => Err(err),
+    };
+    res.map_err(|err| log::warn!("request on \"{}\" failed: {}", uri, err))
         .ok()
 }
 
 /// Verify the token validity.
-pub fn verify_token(token: i32) -> Result<TokenResponse, ServerError> {
-    let mut res = reqwest::get(&format!("{}api/lobby/tokens/{}", API_ENDPOINT, token))
+pub async fn verify_token(token: i32) -> Result<TokenResponse, ServerError> {
+    let res = reqwest::get(&format!("{}api/lobby/tokens/{}", API_ENDPOINT, token))
+        .await
         .map_err(ServerError::BackendRequest)?;
-    let token_res: Result<TokenResponse, _> = res.json();
-    token_res.map_err(|e| {
+    let text = res.text().await.map_err(ServerError::BackendRequest)?;
+    serde_json::from_str(&text).map_err(|e| {
         warn!("{}", e);
         ServerError::InvalidToken(format!(
             "The Backend Response did not contain valid group information: {:?}",
-            res.text()
+            text
         ))
     })
 }
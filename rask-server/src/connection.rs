@@ -0,0 +1,51 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::error::ServerError;
+use tokio::sync::mpsc;
+
+/// The id of the next connection.
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A message to be sent by the task of a connection.
+#[derive(Debug)]
+pub enum Outgoing {
+    Binary(Vec<u8>),
+    /// Close the connection with a reason.
+    Close(String),
+}
+
+/// Sends messages to a client, the messages are written by the task of its connection.
+#[derive(Debug, Clone)]
+pub struct Sender {
+    id: u64,
+    outgoing: mpsc::UnboundedSender<Outgoing>,
+}
+
+impl PartialEq for Sender {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Sender {}
+
+impl Sender {
+    /// Creates a sender for a new connection and the receiver of its messages.
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<Outgoing>) {
+        let (outgoing, receiver) = mpsc::unbounded_channel();
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        (Self { id, outgoing }, receiver)
+    }
+
+    pub fn send(&self, data: Vec<u8>) -> Result<(), ServerError> {
+        self.outgoing
+            .send(Outgoing::Binary(data))
+            .map_err(|_| ServerError::ConnectionClosed(self.id))
+    }
+
+    pub fn close_with_reason(&self, reason: &str) -> Result<(), ServerError> {
+        self.outgoing
+            .send(Outgoing::Close(reason.to_owned()))
+            .map_err(|_| ServerError::ConnectionClosed(self.id))
+    }
+}
@@ -1,8 +1,8 @@
-use std::sync::mpsc::SendError;
-
 use crate::group;
 use rask_engine::error;
 use reqwest::Error as ReqError;
+use tokio::sync::mpsc::error::SendError;
+use tokio_tungstenite::tungstenite;
 
 #[allow(dead_code)]
 #[derive(Debug)]
@@ -11,7 +11,9 @@ pub enum ServerError {
     GroupCreation(String),
     GameCreation(std::io::Error),
     WebSocketCreation(std::io::Error),
-    WebSocketError(ws::Error),
+    WebSocketError(tungstenite::Error),
+    /// The connection with the id was closed.
+    ConnectionClosed(u64),
     BackendRequest(ReqError),
     InvalidProtocol,
     InvalidTokenFormat,
@@ -31,6 +33,7 @@ impl std::fmt::Display for ServerError {
             ServerError::GameCreation(e) => write!(f, "GameCreationError: {}", e),
             ServerError::WebSocketCreation(e) => write!(f, "WebSocketCreationError: {}", e),
             ServerError::WebSocketError(e) => write!(f, "WebSocketError: {}", e),
+            ServerError::ConnectionClosed(id) => write!(f, "Connection {} is closed", id),
             ServerError::BackendRequest(e) => write!(f, "BackendRequestError: {}", e),
             ServerError::InvalidProtocol => write!(f, "InvalidProtocolError"),
             ServerError::InvalidTokenFormat => write!(f, "InvalidTokenFormat"),
@@ -56,7 +59,7 @@ macro_rules! derive_from {
     };
 }
 
-derive_from!(ws::Error, WebSocketError);
+derive_from!(tungstenite::Error, WebSocketError);
 derive_from!(std::io::Error, FileError);
 derive_from!(Box<dyn std::error::Error>, StdErr);
 derive_from!(ReqError, BackendRequest);
@@ -24,7 +24,8 @@ pub fn init_logger() {
         })
         .level(LevelFilter::Debug)
         .level_for("hyper", LevelFilter::Off)
-        .level_for("tokio_reactor", LevelFilter::Off)
+        .level_for("tungstenite", LevelFilter::Off)
+        .level_for("tokio_tungstenite", LevelFilter::Off)
         .level_for("reqwest", LevelFilter::Off)
         .chain(std::io::stdout())
         .apply()
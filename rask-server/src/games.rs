@@ -1,78 +1,230 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::thread;
-use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
+use crate::connection::Sender;
 use crate::error::ServerError;
-use crate::group::{Message, SendGroup};
-use log::{error, info};
+use crate::group::{Message, SendGroup, RECONNECT_GRACE};
+use crate::registry::GameType;
+use log::{debug, error, info, warn};
+use rask_engine::engine::rules::MatchPhase;
+use rask_engine::engine::{EngineEvent, GameEngine, Level, MatchRules, RaskEngine};
 use rask_engine::error::EngineError;
-use rask_engine::network::packet::ReadResource;
+use rask_engine::input::ActionState;
+use rask_engine::network::latency::Heartbeat;
+use rask_engine::network::packet::{PacketVariant, ReadResource, Serialize, WebSocketPacket};
+use rask_engine::network::protocol::op_codes;
+use rask_engine::network::snapshot::SnapshotSender;
 use rask_engine::resources::registry;
+use tokio::sync::mpsc;
 
+/// A game played by the users of a group.
+/// The game is created by the thread of the group, see `registry::GameType`, and the thread calls
+/// the handlers for the messages of the group and updates the game `tick_rate` times per second.
 pub trait Game {
-    fn run(self) -> Result<JoinHandle<()>, ServerError>;
+    /// A user joined the group with a new session token.
+    fn add_user(&mut self, user: User, session: u64);
+    /// A user reconnected with the token of a session the group knows, its previous connection
+    /// may still be open.
+    fn resume_user(&mut self, user: User, session: u64);
+    /// The connection of a user was closed.
+    fn remove_user(&mut self, sender: &Sender);
+    /// A packet sent by a user.
+    fn handle_data(&mut self, sender: &Sender, data: &[u8]);
+    /// Advance the game by `dt`.
+    fn update(&mut self, dt: Duration);
+}
+
+/// Runs the game until the group is killed.
+pub fn run(
+    mut game: Box<dyn Game>,
+    mut receiver: mpsc::UnboundedReceiver<Message>,
+    tick_rate: u32,
+) {
+    let period = Duration::from_secs(1) / tick_rate.max(1);
+    let mut last_update = Instant::now();
+    loop {
+        let start = Instant::now();
+        while let Ok(message) = receiver.try_recv() {
+            match message {
+                Message::Data(sender, data) => game.handle_data(&sender, &data),
+                Message::Add(user, session) => game.add_user(user, session),
+                Message::Resume(user, session) => game.resume_user(user, session),
+                Message::Remove(sender) => game.remove_user(&sender),
+                Message::Park => thread::park(),
+                Message::Kill => return info!("thread killed itself"),
+            }
+        }
+        let mut dt = start - last_update;
+        if dt > MAX_UPDATE_TIME {
+            warn!("skipping {:?} of the game", dt - MAX_UPDATE_TIME);
+            dt = MAX_UPDATE_TIME;
+        }
+        last_update = start;
+        game.update(dt);
+        if let Some(rest) = period.checked_sub(start.elapsed()) {
+            thread::sleep(rest);
+        }
+    }
 }
 
-#[allow(dead_code)]
 pub struct RaskGame {
-    game: (),
+    /// The engine can't be sent to another thread, the game is created by the thread of the group.
+    engine: RaskEngine,
     group: SendGroup,
-    users: Vec<User>,
-    will_to_live: bool,
+    users: Vec<Client>,
     res_cache: HashMap<u32, Vec<u8>>,
+    /// The instant the times of the heartbeats are measured from.
+    epoch: Instant,
 }
 
 #[derive(Debug, Clone)]
 pub struct User {
     name: String,
-    sender: ws::Sender,
+    sender: Sender,
 }
 
 impl User {
-    pub fn new(name: String, sender: ws::Sender) -> Self {
+    pub fn new(name: String, sender: Sender) -> Self {
         User { name, sender }
     }
 }
 
-impl Game for RaskGame {
-    fn run(self) -> Result<JoinHandle<()>, ServerError> {
-        thread::Builder::new()
-            .name(format!("group{}", self.group.id))
-            .spawn(move || self.game_loop())
-            .map_err(ServerError::GameCreation)
+/// A user playing the game.
+struct Client {
+    user: User,
+    /// The id of the player controlled by the user.
+    player: u32,
+    /// The received inputs, one is applied in every simulation step.
+    inputs: VecDeque<(u32, ActionState)>,
+    /// The sequence number of the last applied input.
+    applied: u32,
+    snapshots: SnapshotSender,
+    heartbeat: Heartbeat,
+    /// The token the user resumes the session with after the connection was lost.
+    session: u64,
+    /// The time the connection was lost, the slot is kept for `RECONNECT_GRACE`.
+    disconnected: Option<Duration>,
+    /// The ids of the resources already sent to the user.
+    resources: HashSet<u32>,
+    /// The number of players killed by the player of the user.
+    kills: u32,
+}
+
+impl Client {
+    fn new(user: User, player: u32, session: u64, now: Duration) -> Self {
+        Self {
+            user,
+            player,
+            inputs: VecDeque::new(),
+            applied: 0,
+            snapshots: SnapshotSender::new(SNAPSHOT_HISTORY),
+            heartbeat: Heartbeat::new(PING_INTERVAL, CLIENT_TIMEOUT, now),
+            session,
+            disconnected: None,
+            resources: HashSet::new(),
+            kills: 0,
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        self.disconnected.is_none()
+    }
+
+    /// Keep the slot of the user after the connection was lost, the player stops moving.
+    fn disconnect(&mut self, engine: &mut RaskEngine, now: Duration) {
+        self.disconnected = Some(now);
+        self.inputs.clear();
+        engine.set_actions(self.player, ActionState::default());
+    }
+
+    /// Continue the session with a new connection of the user.
+    /// The next snapshot is sent completely, as the client may have lost its baseline.
+    fn reconnect(&mut self, user: User, now: Duration) {
+        self.user = user;
+        self.disconnected = None;
+        self.applied = 0;
+        self.snapshots = SnapshotSender::new(SNAPSHOT_HISTORY);
+        self.heartbeat = Heartbeat::new(PING_INTERVAL, CLIENT_TIMEOUT, now);
     }
 }
 
 const RES_PATH: &str = "res";
+const LEVEL: &str = "levels/arena.json";
+/// The number of snapshots kept as possible baselines for the delta encoding.
+const SNAPSHOT_HISTORY: usize = 64;
+/// The maximum number of inputs of a user waiting to be applied, older ones are dropped.
+const MAX_PENDING_INPUTS: usize = 32;
+/// The maximum time simulated in one update, e.g. after the game was parked.
+const MAX_UPDATE_TIME: Duration = Duration::from_millis(250);
+/// The time between two pings of a user.
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+/// The time without any packet of a user after which the connection is closed.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
 
 impl RaskGame {
-    pub fn new(group: SendGroup) -> Self {
-        Self {
-            game: (),
+    /// The game type of the rask duels.
+    pub fn game_type() -> GameType {
+        GameType {
+            name: "rask",
+            max_users: 2,
+            create: |group| Ok(Box::new(RaskGame::new(group)?)),
+        }
+    }
+
+    pub fn new(group: SendGroup) -> Result<Self, ServerError> {
+        let level = std::fs::read_to_string(format!("{}/{}", RES_PATH, LEVEL))?;
+        let mut engine = RaskEngine::new_deterministic();
+        engine.load_level(Level::from_json(&level)?)?;
+        Ok(Self {
+            engine,
             group,
             users: Vec::new(),
-            will_to_live: true,
             res_cache: HashMap::new(),
-        }
+            epoch: Instant::now(),
+        })
+    }
+
+    /// The current time of the heartbeats.
+    fn now(&self) -> Duration {
+        self.epoch.elapsed()
+    }
+
+    /// Returns the estimated time a packet takes to the user controlling `player`.
+    #[allow(dead_code)]
+    pub fn latency(&self, player: u32) -> Option<Duration> {
+        self.client(player)?.heartbeat.latency()
+    }
+
+    /// Returns the offset of the clock of the user controlling `player` to the server clock in
+    /// microseconds, a simulation step started at `now()` on the server started at
+    /// `now() + offset` on the client.
+    #[allow(dead_code)]
+    pub fn clock_offset(&self, player: u32) -> Option<i64> {
+        self.client(player)?.heartbeat.clock_offset()
+    }
+
+    fn client(&self, player: u32) -> Option<&Client> {
+        self.users.iter().find(|c| c.player == player)
     }
 
     fn push_buffer(&mut self, buf_id: u32, user_id: usize) -> Result<(), ServerError> {
-        self.users
+        let client = self
+            .users
             .get_mut(user_id)
-            .ok_or(ServerError::InvalidUser(user_id))?
-            .sender
-            .send(ws::Message::from(
-                self.res_cache
-                    .get(&buf_id)
-                    .ok_or_else(|| {
-                        EngineError::ResourceMissing(format!(
-                            "Resource {} is not loaded yet",
-                            buf_id
-                        ))
-                    })?
-                    .as_slice(),
-            ))?;
-        Ok(())
+            .ok_or(ServerError::InvalidUser(user_id))?;
+        // the resource was delivered before the user reconnected
+        if !client.resources.insert(buf_id) {
+            return Ok(());
+        }
+        client.user.sender.send(
+            self.res_cache
+                .get(&buf_id)
+                .ok_or_else(|| {
+                    EngineError::ResourceMissing(format!("Resource {} is not loaded yet", buf_id))
+                })?
+                .clone(),
+        )
     }
 
     fn load_char(&mut self, chr: registry::CharacterInfo) -> Result<(), ServerError> {
@@ -110,44 +262,239 @@ impl RaskGame {
         self.push_buffer(registry::CHAR.id, uid)
     }
 
-    fn game_loop(mut self) {
-        let _messages = self.get_messages();
-        while self.will_to_live {
-            //game.handle_events(messages);
-            //game.tick();
-            //let b = game.get_broadcast()
-            //self.users.iter().foreach(|u| u.sender.send(b));
-            let _messages = self.get_messages();
-            thread::sleep(std::time::Duration::from_secs(5));
+    /// Ping every user and close the connections of the users that timed out.
+    /// The slots of users that did not reconnect within `RECONNECT_GRACE` are freed.
+    fn heartbeat(&mut self) {
+        let now = self.now();
+        let engine = &mut self.engine;
+        for client in self
+            .users
+            .iter_mut()
+            .filter(|c| c.is_connected() && c.heartbeat.is_timed_out(now))
+        {
+            warn!("player {} timed out", client.player);
+            if let Err(e) = client.user.sender.close_with_reason("timed out") {
+                error!("failed to close the connection of a user: {}", e);
+            }
+            client.disconnect(engine, now);
+        }
+        let (expired, users) = self
+            .users
+            .drain(..)
+            .partition(|c| matches!(c.disconnected, Some(t) if now > t + RECONNECT_GRACE));
+        self.users = users;
+        for client in expired {
+            info!("the session of player {} expired", client.player);
+            engine.despawn_player(client.player);
+        }
+        for client in self.users.iter_mut().filter(|c| c.is_connected()) {
+            if let Some(id) = client.heartbeat.ping(now) {
+                let mut buf = Vec::new();
+                WebSocketPacket::new(PacketVariant::Ping {
+                    id,
+                    tick: engine.tick_nr(),
+                })
+                .serialize(&mut buf);
+                if let Err(e) = client.user.sender.send(buf) {
+                    error!("failed to ping player {}: {}", client.player, e);
+                }
+            }
+        }
+    }
+
+    /// Score the kills and log the progress of the match.
+    fn handle_events(&mut self) {
+        for event in self.engine.poll_events() {
+            match event {
+                EngineEvent::Kill { killer, victim } => {
+                    if let Some(client) = self.users.iter_mut().find(|c| c.player == killer) {
+                        client.kills += 1;
+                    }
+                    info!("player {} killed player {}", killer, victim);
+                }
+                EngineEvent::MatchPhase {
+                    phase: MatchPhase::RoundOver { winner, .. },
+                } => info!("player {} won the round in group {}", winner, self.group.id),
+                EngineEvent::MatchPhase {
+                    phase: MatchPhase::MatchOver { winner },
+                } => {
+                    let scores: Vec<(u32, u32)> =
+                        self.users.iter().map(|c| (c.player, c.kills)).collect();
+                    info!(
+                        "player {} won the match in group {}, kills by player: {:?}",
+                        winner, self.group.id, scores
+                    );
+                }
+                EngineEvent::MatchPhase { phase } => debug!("match phase {:?}", phase),
+                _ => (),
+            }
+        }
+    }
+
+    /// Do a simulation step with the next input of every user.
+    fn step(&mut self) {
+        let engine = &mut self.engine;
+        for client in self.users.iter_mut() {
+            if let Some((sequence, actions)) = client.inputs.pop_front() {
+                engine.set_actions(client.player, actions);
+                client.applied = sequence;
+            }
+        }
+        engine.step();
+    }
+
+    /// Send a snapshot of the game to every user.
+    fn broadcast(&mut self) {
+        if self.users.is_empty() {
+            return;
+        }
+        let snapshot = self.engine.snapshot();
+        for client in self.users.iter_mut().filter(|c| c.is_connected()) {
+            let data = client.snapshots.encode(snapshot.clone());
+            let mut buf = Vec::new();
+            WebSocketPacket {
+                op_code: op_codes::PUSH_SNAPSHOT,
+                payload: PacketVariant::PushSnapshot {
+                    input: client.applied,
+                    data: &data,
+                },
+            }
+            .serialize(&mut buf);
+            if let Err(e) = client.user.sender.send(buf) {
+                error!(
+                    "failed to send a snapshot to player {}: {}",
+                    client.player, e
+                );
+            }
         }
-        info!("thread killed itself");
     }
 
-    fn add_user(&mut self, user: &User) {
-        self.users.push(user.clone());
+    fn join(&mut self, user: User, session: u64) {
+        // the lowest free player id
+        let player = (0..)
+            .find(|id| self.users.iter().all(|c| c.player != *id))
+            .unwrap();
+        self.engine.spawn_player(player);
+        let now = self.now();
+        self.users.push(Client::new(user, player, session, now));
+        if self.users.len() == 2 && self.engine.match_state().is_none() {
+            info!("starting a match in group {}", self.group.id);
+            self.engine.start_match(MatchRules::default());
+        }
         if let Err(e) = self.level_one(self.users.len() - 1) {
             error!("Error during resoure distribution: {}", e);
         }
+        self.send_session(self.users.len() - 1);
+    }
+
+    /// Resume the session of a user that lost the connection, or add it as a new user if the
+    /// session is unknown or expired.
+    fn resume(&mut self, user: User, session: u64) {
+        let index = match self.users.iter().position(|c| c.session == session) {
+            Some(index) => index,
+            None => {
+                warn!("the session {:x} is unknown or expired", session);
+                return self.join(user, session);
+            }
+        };
+        let now = self.now();
+        let client = &mut self.users[index];
+        if client.is_connected() {
+            // the old connection was not closed yet
+            if let Err(e) = client.user.sender.close_with_reason("session resumed") {
+                error!("failed to close the connection of a user: {}", e);
+            }
+        }
+        info!("player {} resumed the session", client.player);
+        client.reconnect(user, now);
+        if let Err(e) = self.level_one(index) {
+            error!("Error during resoure distribution: {}", e);
+        }
+        self.send_session(index);
+    }
+
+    fn send_session(&self, user_id: usize) {
+        let client = &self.users[user_id];
+        let mut buf = Vec::new();
+        WebSocketPacket::new(PacketVariant::PushSession(client.session)).serialize(&mut buf);
+        if let Err(e) = client.user.sender.send(buf) {
+            error!(
+                "failed to send the session to player {}: {}",
+                client.player, e
+            );
+        }
+    }
+}
+
+impl Game for RaskGame {
+    fn add_user(&mut self, user: User, session: u64) {
+        self.join(user, session)
+    }
+
+    fn resume_user(&mut self, user: User, session: u64) {
+        self.resume(user, session)
+    }
+
+    fn remove_user(&mut self, sender: &Sender) {
+        let now = self.now();
+        let engine = &mut self.engine;
+        if let Some(client) = self
+            .users
+            .iter_mut()
+            .find(|c| c.is_connected() && c.user.sender == *sender)
+        {
+            info!("player {} lost the connection", client.player);
+            client.disconnect(engine, now);
+        }
     }
 
-    fn get_messages(&mut self) -> Vec<Message> {
-        //  info!("receiver {:#?} is still alive", self.group.receiver);
-        let (mut data, control): (Vec<Message>, Vec<Message>) =
-            self.group.receiver.try_iter().partition(Message::is_data);
-        control.iter().for_each(|x| match x {
-            Message::Park => {
-                data = Vec::new();
-                thread::park();
+    /// Handle a packet sent by a user.
+    fn handle_data(&mut self, sender: &Sender, data: &[u8]) {
+        let now = self.now();
+        let client = match self
+            .users
+            .iter_mut()
+            .find(|c| c.is_connected() && c.user.sender == *sender)
+        {
+            Some(client) => client,
+            None => return warn!("received data from an unknown user"),
+        };
+        client.heartbeat.received(now);
+        match WebSocketPacket::deserialize(data).map(|packet| packet.payload) {
+            Ok(PacketVariant::PushInput { sequence, actions }) => {
+                // inputs received twice or too late are dropped
+                let last = client.inputs.back().map_or(client.applied, |(s, _)| *s);
+                if sequence > last {
+                    if client.inputs.len() == MAX_PENDING_INPUTS {
+                        warn!("dropping inputs of player {}", client.player);
+                        client.inputs.pop_front();
+                    }
+                    client.inputs.push_back((sequence, actions));
+                }
             }
-            Message::Kill => self.will_to_live = false,
-            Message::Add(user) => self.add_user(&user),
-            Message::Remove(sender) => {
-                if let Some(pos) = self.users.iter().position(|x| x.sender == *sender) {
-                    self.users.swap_remove(pos);
+            Ok(PacketVariant::AckSnapshot(tick)) => client.snapshots.ack(tick),
+            Ok(PacketVariant::Pong { id, client_time }) => {
+                if let Some(rtt) = client.heartbeat.pong(now, id, client_time) {
+                    debug!(
+                        "round trip time of player {}: {:?}, smoothed {:?}",
+                        client.player,
+                        rtt,
+                        client.heartbeat.rtt()
+                    );
                 }
             }
-            _ => (),
-        });
-        data
+            Ok(packet) => warn!("unexpected packet {:?}", packet),
+            Err(e) => warn!("invalid packet: {}", e),
+        }
+    }
+
+    fn update(&mut self, dt: Duration) {
+        self.heartbeat();
+        for _ in 0..self.engine.advance(dt) {
+            self.step();
+        }
+        // the engine keeps the events until they are polled
+        self.handle_events();
+        self.broadcast();
     }
 }
@@ -1,30 +1,57 @@
+use std::collections::HashMap;
 use std::convert::TryInto;
-use std::sync::mpsc;
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use crate::backend_connection::TokenResponse;
+use crate::connection::Sender;
 use crate::error::ServerError;
 use crate::games;
-use crate::games::{Game, RaskGame};
-use log::info;
-use ws::Sender;
+use crate::registry::Registry;
+use log::{error, info, warn};
+use tokio::sync::mpsc;
 
 pub type GroupId = u32;
 
+/// The time the slot of a client is kept after the connection was lost,
+/// the client can resume its session in the meantime.
+pub const RECONNECT_GRACE: Duration = Duration::from_secs(30);
+
 #[derive(Debug)]
 /// capacity is never allowed to be above usize::MAX
 pub struct Group {
-    pub clients: Vec<Sender>,
-    pub sender: mpsc::Sender<Message>,
+    /// The sessions of the clients by their token, including the sessions waiting to be resumed.
+    sessions: HashMap<u64, Session>,
+    pub sender: mpsc::UnboundedSender<Message>,
     id: GroupId,
     group_type: String,
     name: String,
     capacity: u32,
-    game_thread: JoinHandle<()>,
+    /// The thread running the game, joined when the group is dropped.
+    game_thread: Option<JoinHandle<()>>,
+    /// The instant the group was created or the last client left, `None` while clients are
+    /// connected.
+    empty_since: Option<Instant>,
+    parked: bool,
 }
 
+/// The session of a client, its slot is reserved for `RECONNECT_GRACE` after the connection was
+/// closed.
+#[derive(Debug)]
+struct Session {
+    sender: Sender,
+    /// The instant the connection was closed, `None` while it is open.
+    disconnected: Option<Instant>,
+}
+
+impl Session {
+    fn is_expired(&self) -> bool {
+        matches!(self.disconnected, Some(since) if since.elapsed() > RECONNECT_GRACE)
+    }
+}
+
+/// The description of a group passed to its game.
 pub struct SendGroup {
-    pub receiver: mpsc::Receiver<Message>,
     pub id: GroupId,
     pub group_type: String,
     pub name: String,
@@ -33,27 +60,28 @@ pub struct SendGroup {
 
 #[derive(Debug)]
 pub enum Message {
-    // TODO: flatten tuple
-    Data((String, Vec<u8>)),
+    /// A packet received from a client.
+    Data(Sender, Vec<u8>),
     Park,
     Kill,
-    Add(games::User),
+    /// A user joined with a new session token.
+    Add(games::User, u64),
+    /// A user reconnected with the session token of a previous connection.
+    Resume(games::User, u64),
     Remove(Sender),
 }
 
-impl Message {
-    pub fn is_data(&self) -> bool {
-        match self {
-            Message::Data(_) => true,
-            _ => false,
-        }
-    }
-}
-
 impl Drop for Group {
     fn drop(&mut self) {
-        info!("dropping group {:?}", &self);
+        info!("removing group {} ({})", self.id, self.name);
         let _ = self.sender.send(Message::Kill);
+        if let Some(game_thread) = self.game_thread.take() {
+            // a parked game has to wake up to receive the message
+            game_thread.thread().unpark();
+            if game_thread.join().is_err() {
+                error!("the game of group {} panicked", self.id);
+            }
+        }
     }
 }
 
@@ -72,72 +100,149 @@ impl Group {
         &self.name
     }
 
-    #[allow(dead_code)]
-    pub fn park(self) -> Result<(), ServerError> {
-        Ok(self.sender.send(Message::Park)?)
+    pub fn is_parked(&self) -> bool {
+        self.parked
     }
 
-    #[allow(dead_code)]
-    pub fn unpark(&mut self) {
-        self.game_thread.thread().unpark();
+    /// Returns true if the group had no clients for at least `timeout`.
+    pub fn is_empty_for(&self, timeout: Duration) -> bool {
+        matches!(self.empty_since, Some(since) if since.elapsed() >= timeout)
+    }
+
+    /// Stops the game until the group is unparked, the messages are queued in the meantime.
+    pub fn park(&mut self) -> Result<(), ServerError> {
+        if !self.parked {
+            info!("parking group {}", self.id);
+            self.sender.send(Message::Park)?;
+            self.parked = true;
+        }
+        Ok(())
     }
 
-    pub fn add_client(&mut self, client: Sender) -> Result<mpsc::Sender<Message>, ServerError> {
-        if self.clients.len() >= self.capacity as usize {
-            Err(ServerError::Group(format!(
-                "User limit for {} exceeded",
-                self.id
-            )))
-        } else {
-            self.clients.push(client.clone());
-            self.sender
-                .send(Message::Add(games::User::new("None".to_owned(), client)))
-                .map_err(Into::into)
-                .map(|()| self.sender.clone())
+    pub fn unpark(&mut self) {
+        if self.parked {
+            info!("unparking group {}", self.id);
+            if let Some(game_thread) = &self.game_thread {
+                game_thread.thread().unpark();
+            }
+            self.parked = false;
         }
     }
 
+    /// Adds a client to the group, a client with the token of a known session resumes it.
+    /// The slots of the sessions waiting to be resumed count towards the capacity.
+    pub fn add_client(
+        &mut self,
+        client: Sender,
+        session: Option<u64>,
+    ) -> Result<mpsc::UnboundedSender<Message>, ServerError> {
+        self.sessions.retain(|_, session| !session.is_expired());
+        let user = games::User::new("None".to_owned(), client.clone());
+        let message = match session.filter(|token| self.sessions.contains_key(token)) {
+            Some(token) => {
+                // an open connection of the session is replaced, the game closes it
+                self.sessions.insert(
+                    token,
+                    Session {
+                        sender: client,
+                        disconnected: None,
+                    },
+                );
+                Message::Resume(user, token)
+            }
+            None => {
+                if let Some(token) = session {
+                    warn!("the session {:x} is unknown or expired", token);
+                }
+                if self.sessions.len() >= self.capacity as usize {
+                    return Err(ServerError::Group(format!(
+                        "User limit for {} exceeded",
+                        self.id
+                    )));
+                }
+                let token = self.new_session()?;
+                self.sessions.insert(
+                    token,
+                    Session {
+                        sender: client,
+                        disconnected: None,
+                    },
+                );
+                Message::Add(user, token)
+            }
+        };
+        self.unpark();
+        self.empty_since = None;
+        self.sender
+            .send(message)
+            .map_err(Into::into)
+            .map(|()| self.sender.clone())
+    }
+
     pub fn remove_client(&mut self, client: &Sender) -> Result<(), ServerError> {
-        if let Some(pos) = self.clients.iter().position(|x| *x == *client) {
-            self.clients.swap_remove(pos);
+        if let Some(session) = self
+            .sessions
+            .values_mut()
+            .find(|session| session.disconnected.is_none() && session.sender == *client)
+        {
+            session.disconnected = Some(Instant::now());
+        }
+        let connected = self.sessions.values().any(|s| s.disconnected.is_none());
+        if !connected && self.empty_since.is_none() {
+            info!("the last client left group {}", self.id);
+            self.empty_since = Some(Instant::now());
         }
         self.sender
             .send(Message::Remove(client.clone()))
             .map_err(Into::into)
     }
 
-    pub fn new(response: TokenResponse) -> Result<Self, ServerError> {
-        let (sender, receiver) = mpsc::channel();
+    /// Returns a new session token from the random number generator of the OS,
+    /// distinct from the tokens of the current sessions.
+    fn new_session(&self) -> Result<u64, ServerError> {
+        loop {
+            let mut bytes = [0; 8];
+            getrandom::getrandom(&mut bytes).map_err(|e| {
+                ServerError::Group(format!("failed to generate a session token: {}", e))
+            })?;
+            let token = u64::from_le_bytes(bytes);
+            if !self.sessions.contains_key(&token) {
+                return Ok(token);
+            }
+        }
+    }
+
+    pub fn new(
+        response: TokenResponse,
+        tick_rate: u32,
+        registry: &Registry,
+    ) -> Result<Self, ServerError> {
+        let (sender, receiver) = mpsc::unbounded_channel();
         let (id, name, group_type) = (response.group_id, response.group_name, response.group_type);
-        let capacity = response.user_max.try_into().unwrap_or(std::usize::MAX) as u32;
+        let game_type = registry.get(&group_type)?;
+        let capacity = (response.user_max.try_into().unwrap_or(std::usize::MAX) as u32)
+            .min(game_type.max_users);
         info!("Creating Group{} ({}) with game {}", id, name, group_type);
 
         let send_group = SendGroup {
-            receiver,
             id,
             name: name.clone(),
             group_type: group_type.clone(),
             capacity,
         };
-
-        let game = match group_type.as_str() {
-            "rask" => RaskGame::new(send_group),
-            name => {
-                return Err(ServerError::GroupCreation(format!(
-                    "The game type {} is not implemented",
-                    name
-                )))
-            }
-        };
+        let game_thread = game_type.spawn(send_group, receiver, tick_rate)?;
 
         Ok(Self {
-            clients: Vec::new(),
+            sessions: HashMap::new(),
             sender,
             id,
             group_type,
             name,
             capacity,
-            game_thread: game.run()?,
+            game_thread: Some(game_thread),
+            // a group nobody joins is removed like an abandoned one
+            empty_since: Some(Instant::now()),
+            parked: false,
         })
     }
 }
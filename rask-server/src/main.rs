@@ -1,27 +1,44 @@
 mod backend_connection;
+mod connection;
 mod error;
 mod game_logger;
 mod games;
 mod group;
+mod registry;
+mod replay;
 mod server;
 
 pub use std::error::Error;
 
-use clap::{load_yaml, App};
+use clap::{load_yaml, value_t, App};
 use log::info;
 
-fn main() -> Result<(), error::ServerError> {
+#[tokio::main]
+async fn main() -> Result<(), error::ServerError> {
     game_logger::init_logger();
 
     // load args
     let yaml = load_yaml!("cli.yaml");
     let matches = App::from_yaml(yaml).get_matches();
 
+    if let Some(matches) = matches.subcommand_matches("verify-replay") {
+        return replay::verify(matches.value_of("replay").unwrap());
+    }
+
     // extract values from args
     let addr = matches.value_of("address").unwrap_or("127.0.0.1");
     let port = matches.value_of("port").unwrap_or("5001");
+    let tick_rate = value_t!(matches, "tick-rate", u32).unwrap_or_else(|e| e.exit());
+    let max_groups = value_t!(matches, "max-groups", usize).unwrap_or_else(|e| e.exit());
 
     // start server
     info!("create game server on {:?}", addr);
-    server::run(addr, port).map(|s| s.join().unwrap())
+    server::run(
+        addr,
+        port,
+        tick_rate,
+        max_groups,
+        registry::Registry::with_games(),
+    )
+    .await
 }
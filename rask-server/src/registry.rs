@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::thread;
+use std::thread::JoinHandle;
+
+use crate::error::ServerError;
+use crate::games::{self, Game, RaskGame};
+use crate::group::{Message, SendGroup};
+use log::{error, info, warn};
+
+/// A kind of game groups can play, registered by its name.
+#[derive(Clone, Copy)]
+pub struct GameType {
+    /// The name the backend uses as the `group_type`.
+    pub name: &'static str,
+    /// The maximum number of users of a group, a lower limit of the backend takes precedence.
+    pub max_users: u32,
+    /// Creates the game of a group. It is called by the thread of the group,
+    /// so the game does not need to be `Send`.
+    pub create: fn(SendGroup) -> Result<Box<dyn Game>, ServerError>,
+}
+
+impl std::fmt::Debug for GameType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("GameType")
+            .field("name", &self.name)
+            .field("max_users", &self.max_users)
+            .finish()
+    }
+}
+
+impl GameType {
+    /// Creates the game in a new thread and runs it until the group is killed.
+    pub fn spawn(
+        &self,
+        group: SendGroup,
+        receiver: tokio::sync::mpsc::UnboundedReceiver<Message>,
+        tick_rate: u32,
+    ) -> Result<JoinHandle<()>, ServerError> {
+        let create = self.create;
+        // errors can't be sent between threads, only their description
+        let (result, created) = mpsc::channel();
+        let thread = thread::Builder::new()
+            .name(format!("group{}", group.id))
+            .spawn(move || match create(group) {
+                Ok(game) => {
+                    let _ = result.send(Ok(()));
+                    games::run(game, receiver, tick_rate)
+                }
+                Err(e) => {
+                    error!("failed to create the game: {}", e);
+                    let _ = result.send(Err(e.to_string()));
+                }
+            })
+            .map_err(ServerError::GameCreation)?;
+        match created.recv() {
+            Ok(Ok(())) => Ok(thread),
+            Ok(Err(e)) => Err(ServerError::GroupCreation(e)),
+            Err(_) => Err(ServerError::GroupCreation(format!(
+                "the game {} panicked during its creation",
+                self.name
+            ))),
+        }
+    }
+}
+
+/// The game types groups can be created with.
+#[derive(Debug, Default)]
+pub struct Registry {
+    types: HashMap<&'static str, GameType>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a registry containing all game types of the server.
+    pub fn with_games() -> Self {
+        let mut registry = Self::new();
+        registry.register(RaskGame::game_type());
+        registry
+    }
+
+    /// Adds a game type, an already registered type of the same name is replaced.
+    pub fn register(&mut self, game_type: GameType) {
+        info!("registering the game type {}", game_type.name);
+        if self.types.insert(game_type.name, game_type).is_some() {
+            warn!("the game type {} was registered twice", game_type.name);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<&GameType, ServerError> {
+        self.types.get(name).ok_or_else(|| {
+            ServerError::GroupCreation(format!("The game type {} is not implemented", name))
+        })
+    }
+}
@@ -0,0 +1,23 @@
+use log::info;
+use rask_engine::engine::Replay;
+
+use crate::error::ServerError;
+
+/// Play a replay file without a client and compare the final checksum with the recorded one.
+pub fn verify(path: &str) -> Result<(), ServerError> {
+    let replay = Replay::deserialize(&std::fs::read(path)?)?;
+    info!(
+        "verifying the replay {:?}: {} players in {:?}, {} steps",
+        path,
+        replay.players.len(),
+        replay.level.name,
+        replay.steps()
+    );
+    // the recorded checksums are compared while playing
+    let checksum = replay.verify()?;
+    match replay.checksum() {
+        Some(_) => info!("the final checksum {:016x} matches", checksum),
+        None => info!("the replay has no final checksum, it is {:016x}", checksum),
+    }
+    Ok(())
+}
@@ -1,176 +1,352 @@
 use std::collections::HashMap;
-use std::sync::mpsc;
-use std::sync::{Arc, Mutex};
-use std::thread;
-use std::thread::JoinHandle;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
 
 use crate::backend_connection::*;
+use crate::connection::{Outgoing, Sender};
 use crate::error::ServerError;
 use crate::group::{Group, GroupId, Message as GroupMessage};
-use log::{error, info, warn};
-use ws::{listen, CloseCode, Handler, Handshake, Message, Request, Response, Sender};
+use crate::registry::Registry;
+use futures_util::{SinkExt, StreamExt};
+use log::{debug, error, info, warn};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::{HeaderValue, StatusCode};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
 
 const PROTOCOL: &str = "tuesday";
+const PROTOCOL_HEADER: &str = "Sec-WebSocket-Protocol";
+/// The prefix of the protocol containing the session token of a reconnecting client.
+const SESSION_PREFIX: &str = "Session-";
+/// The maximum time the backend may take to verify a token.
+const BACKEND_TIMEOUT: Duration = Duration::from_secs(10);
+/// The time between two checks of the lifecycle of the groups.
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(1);
+/// The time after which a group without clients is parked.
+const PARK_TIMEOUT: Duration = Duration::from_secs(5);
+/// The time after which a group without clients is removed,
+/// longer than the time sessions can be resumed in, see `group::RECONNECT_GRACE`.
+const REMOVE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The state shared by the tasks of all connections.
+struct Server {
+    groups: Mutex<HashMap<GroupId, Group>>,
+    /// The number of game updates per second.
+    tick_rate: u32,
+    /// The maximum number of groups existing at the same time.
+    max_groups: usize,
+    /// The game types groups can be created with.
+    registry: Registry,
+}
+
 // WebSocket connection handler for the server connection
-pub struct Socket {
+struct Socket {
     ws: Sender,
-    group: mpsc::Sender<GroupMessage>,
-    groups: Arc<Mutex<HashMap<u32, Group>>>,
-    ip: String,
+    group: mpsc::UnboundedSender<GroupMessage>,
+    ip: SocketAddr,
     id: GroupId,
 }
 
-pub fn run(address: &str, port: &str) -> Result<JoinHandle<()>, ServerError> {
-    let count = Arc::new(Mutex::new(HashMap::new()));
-    let (sender, _) = mpsc::channel();
-    let url = format!("{}:{}", address, port);
-    thread::Builder::new()
-        .name("server".to_owned())
-        .spawn(move || {
-            listen(url, |out| Socket {
-                ws: out,
-                group: sender.clone(),
-                groups: count.clone(),
-                ip: "No ip".to_owned(),
-                id: 0,
+/// Accept connections until the listener fails, every connection is handled by its own task.
+pub async fn run(
+    address: &str,
+    port: &str,
+    tick_rate: u32,
+    max_groups: usize,
+    registry: Registry,
+) -> Result<(), ServerError> {
+    let listener = TcpListener::bind(format!("{}:{}", address, port))
+        .await
+        .map_err(ServerError::WebSocketCreation)?;
+    let server = Arc::new(Server {
+        groups: Mutex::new(HashMap::new()),
+        tick_rate,
+        max_groups,
+        registry,
+    });
+    tokio::spawn(server.clone().maintain());
+    loop {
+        let (stream, ip) = listener.accept().await?;
+        tokio::spawn(server.clone().connect(stream, ip));
+    }
+}
+
+impl Server {
+    /// Handle a connection from the handshake until it is closed.
+    async fn connect(self: Arc<Self>, stream: TcpStream, ip: SocketAddr) {
+        let mut credentials = None;
+        let accepted = tokio_tungstenite::accept_hdr_async(stream, |req: &Request, res| {
+            handshake(req, res).map(|(res, token, session)| {
+                credentials = Some((token, session));
+                res
             })
-            .unwrap()
         })
-        .map_err(ServerError::WebSocketCreation)
-}
+        .await;
+        let (mut stream, (token, session)) = match (accepted, credentials) {
+            (Ok(stream), Some(credentials)) => (stream, credentials),
+            (Err(e), _) => return warn!("the handshake with {} failed: {}", ip, e),
+            (Ok(_), None) => return,
+        };
 
-impl Handler for Socket {
-    // called when the socket connection is created
-    fn on_open(&mut self, handshake: Handshake) -> ws::Result<()> {
-        if let Ok(Some(ip)) = handshake.remote_addr() {
-            self.ip = ip;
-        }
-        Ok(())
+        let (ws, outgoing) = Sender::new();
+        // the error is not `Send`, only its description is kept across the await
+        let joined = self
+            .join(token, session, ws, ip)
+            .await
+            .map_err(|err| format!("Client {}: {}", ip, err));
+        let socket = match joined {
+            Ok(socket) => socket,
+            Err(reason) => {
+                warn!("{}", reason);
+                return close(&mut stream, CloseCode::Policy, reason).await;
+            }
+        };
+        socket.serve(stream, outgoing).await;
+        self.leave(&socket);
     }
 
-    fn on_message(&mut self, msg: Message) -> ws::Result<()> {
-        info!("Socket got message '{}'. ", msg);
+    /// Verify the token of a client and add it to the group of the token.
+    async fn join(
+        &self,
+        token: i32,
+        session: Option<u64>,
+        ws: Sender,
+        ip: SocketAddr,
+    ) -> Result<Socket, ServerError> {
+        info!("received token: {}", token);
+        let response = tokio::time::timeout(BACKEND_TIMEOUT, verify_token(token))
+            .await
+            .map_err(|_| {
+                ServerError::InvalidToken("the backend did not answer in time".to_owned())
+            })??;
+        let id = response.group_id;
+        // creating a group waits for its game to be created
+        let group = tokio::task::block_in_place(|| self.handle_token(response, &ws, session))?;
+        Ok(Socket { ws, group, ip, id })
+    }
 
-        self.group
-            .send(GroupMessage::Data((self.ip.clone(), msg.into_data())))
-            .unwrap_or_else(|err| {
-                let err = format!("failed to deliver internal message {}", err);
-                error!("{}", err);
-                self.ws
-                    .close_with_reason(CloseCode::Error, err)
-                    .unwrap_or_else(|e| error!("failed to send message to client {}", e));
-            });
+    fn handle_token(
+        &self,
+        response: TokenResponse,
+        ws: &Sender,
+        session: Option<u64>,
+    ) -> Result<mpsc::UnboundedSender<GroupMessage>, ServerError> {
+        let id = response.group_id;
+        if !self.lock_groups()?.contains_key(&id) {
+            self.check_group_limit(id, self.lock_groups()?.len())?;
+            // the lock is not held while the game loads its level
+            let group = Group::new(response, self.tick_rate, &self.registry)?;
+            let rejected = {
+                let mut guard = self.lock_groups()?;
+                if guard.contains_key(&id) {
+                    // another client created the group in the meantime
+                    Some(group)
+                } else {
+                    self.check_group_limit(id, guard.len())?;
+                    guard.insert(id, group);
+                    None
+                }
+            };
+            // the game thread of a rejected group is joined after the lock is released
+            drop(rejected);
+        }
+
+        match self.lock_groups()?.get_mut(&id) {
+            Some(group) => group.add_client(ws.clone(), session),
+            None => Err(ServerError::Group(format!(
+                "group {} was removed before client {:?} joined",
+                id, ws
+            ))),
+        }
+    }
 
+    fn check_group_limit(&self, id: GroupId, groups: usize) -> Result<(), ServerError> {
+        if groups >= self.max_groups {
+            warn!(
+                "refusing to create group {}, the limit of {} groups is reached",
+                id, self.max_groups
+            );
+            return Err(ServerError::Group(format!(
+                "the limit of {} groups is reached",
+                self.max_groups
+            )));
+        }
         Ok(())
     }
 
-    fn on_close(&mut self, _: CloseCode, _: &str) {
+    fn lock_groups(&self) -> Result<MutexGuard<'_, HashMap<GroupId, Group>>, ServerError> {
+        self.groups
+            .lock()
+            .map_err(|e| ServerError::Group(format!("failed to lock the groups: {}", e)))
+    }
+
+    fn leave(&self, socket: &Socket) {
         if let Ok(mut guard) = self.groups.lock() {
-            if let Some(group) = guard.get_mut(&self.id) {
-                if group.remove_client(&self.ws).is_err() {
+            if let Some(group) = guard.get_mut(&socket.id) {
+                if group.remove_client(&socket.ws).is_err() {
                     warn!("failed to remove Client from Game");
                 }
-                if group.clients.is_empty() {
-                    //guard.remove(&self.id); // TODO
-                }
             }
         }
     }
 
-    // low-level handling of requests
-    fn on_request(&mut self, req: &Request) -> ws::Result<Response> {
-        let (res, token) = handshake(req);
-        Ok(
-            match token
-                .and_then(|token| {
-                    info!("received token: {}", token);
-                    crate::backend_connection::verify_token(token)
-                })
-                .and_then(move |response| self.handle_token(response))
-            {
-                Ok(()) => res,
-                Err(err) => fail_response(
-                    res,
-                    format!("Client {:?}: {:?}", req.client_addr(), err).as_str(),
-                ),
-            },
-        )
+    /// Park the groups without clients and remove them after `REMOVE_TIMEOUT`,
+    /// a group is unparked when a client joins it.
+    async fn maintain(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(MAINTENANCE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let removed = self.expire_groups();
+            if !removed.is_empty() {
+                // the game threads are joined without blocking the runtime
+                tokio::task::spawn_blocking(move || drop(removed));
+            }
+        }
+    }
+
+    fn expire_groups(&self) -> Vec<Group> {
+        let mut guard = match self.groups.lock() {
+            Ok(guard) => guard,
+            Err(e) => {
+                error!("failed to lock the groups: {}", e);
+                return Vec::new();
+            }
+        };
+        for group in guard.values_mut() {
+            if !group.is_parked() && group.is_empty_for(PARK_TIMEOUT) {
+                group
+                    .park()
+                    .unwrap_or_else(|e| error!("failed to park group {}: {}", group.id(), e));
+            }
+        }
+        let expired: Vec<GroupId> = guard
+            .values()
+            .filter(|group| group.is_empty_for(REMOVE_TIMEOUT))
+            .map(Group::id)
+            .collect();
+        expired.iter().filter_map(|id| guard.remove(id)).collect()
     }
 }
 
 impl Socket {
-    fn handle_token(&mut self, response: TokenResponse) -> Result<(), ServerError> {
-        match self.groups.lock() {
-            Ok(mut guard) => {
-                self.id = response.group_id;
-                if !guard.contains_key(&response.group_id) {
-                    let group = Group::new(response)?;
-                    self.group = group.sender.clone();
-                    guard.insert(group.id(), group);
-                }
-
-                // panics if any thread panicked while using the mutex
-                let group = &mut self.group;
-                guard
-                    .get_mut(&self.id)
-                    .unwrap()
-                    .add_client(self.ws.clone())
-                    .map(|s| *group = s)
+    /// Forward the messages of the client to its group and send the messages of the game,
+    /// until either side closes the connection.
+    async fn serve(
+        &self,
+        stream: WebSocketStream<TcpStream>,
+        mut outgoing: mpsc::UnboundedReceiver<Outgoing>,
+    ) {
+        let (mut sink, mut stream) = stream.split();
+        loop {
+            tokio::select! {
+                message = outgoing.recv() => match message {
+                    Some(Outgoing::Binary(data)) => {
+                        if let Err(e) = sink.send(Message::Binary(data)).await {
+                            return error!("failed to send message to client {}: {}", self.ip, e);
+                        }
+                    }
+                    Some(Outgoing::Close(reason)) => {
+                        let frame = CloseFrame {
+                            code: CloseCode::Away,
+                            reason: reason.into(),
+                        };
+                        let _ = sink.send(Message::Close(Some(frame))).await;
+                        return;
+                    }
+                    None => return,
+                },
+                message = stream.next() => match message {
+                    Some(Ok(Message::Binary(data))) => self.deliver(data),
+                    Some(Ok(Message::Text(text))) => self.deliver(text.into_bytes()),
+                    Some(Ok(Message::Close(_))) | None => return,
+                    Some(Ok(_)) => (),
+                    Some(Err(e)) => return warn!("the connection to {} failed: {}", self.ip, e),
+                },
             }
-            Err(e) => Err(ServerError::Group(format!(
-                "cold not add client {:?}  to group {}: {}",
-                self.ws, response.group_id, e
-            ))),
         }
     }
+
+    fn deliver(&self, data: Vec<u8>) {
+        debug!("Socket {} got message of {} bytes. ", self.ip, data.len());
+
+        self.group
+            .send(GroupMessage::Data(self.ws.clone(), data))
+            .unwrap_or_else(|err| {
+                let err = format!("failed to deliver internal message {}", err);
+                error!("{}", err);
+                self.ws
+                    .close_with_reason(&err)
+                    .unwrap_or_else(|e| error!("failed to send message to client {}", e));
+            });
+    }
 }
 
-fn handshake(req: &Request) -> (Response, Result<i32, ServerError>) {
-    let mut res = Response::from_request(req).unwrap();
-    if let Ok(protocols) = req.protocols() {
-        if protocols.iter().any(|pro| pro.contains(PROTOCOL)) {
-            res.set_protocol(PROTOCOL)
-        } else {
-            return (
-                fail_response(
-                    res,
-                    format!("does not support the {} protocol", PROTOCOL).as_str(),
-                ),
-                Err(ServerError::InvalidProtocol),
-            );
-        }
-        let token = protocols.iter().find(|pro| pro.starts_with("Token-"));
-        match token {
-            Some(token) => {
-                let (_, token) = token.split_at(6);
-                if let Ok(token) = token.parse::<i32>() {
-                    (res, Ok(token))
-                } else {
-                    (
-                        fail_response(res, "token is no valid i32"),
-                        Err(ServerError::InvalidTokenFormat),
-                    )
-                }
-            }
-            None => (
-                fail_response(res, "no token in protocols"),
-                Err(ServerError::InvalidToken(
-                    "No Token was passed as a Protocol in the Sec-WebSocket-Protocol Header"
-                        .to_string(),
-                )),
-            ),
+async fn close(stream: &mut WebSocketStream<TcpStream>, code: CloseCode, reason: String) {
+    let frame = CloseFrame {
+        code,
+        reason: reason.into(),
+    };
+    if let Err(e) = stream.close(Some(frame)).await {
+        warn!("failed to close the connection: {}", e);
+    }
+}
+
+/// Check the protocols of a request and extract the token and the session token of the client.
+fn handshake(
+    req: &Request,
+    mut res: Response,
+) -> Result<(Response, i32, Option<u64>), ErrorResponse> {
+    let protocols: Vec<&str> = req
+        .headers()
+        .get_all(PROTOCOL_HEADER)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+    if protocols.is_empty() {
+        return Err(fail_response("failed to retrieve protocols"));
+    }
+    if !protocols.iter().any(|pro| pro.contains(PROTOCOL)) {
+        return Err(fail_response(&format!(
+            "does not support the {} protocol",
+            PROTOCOL
+        )));
+    }
+    res.headers_mut()
+        .insert(PROTOCOL_HEADER, HeaderValue::from_static(PROTOCOL));
+
+    let token = match protocols.iter().find_map(|pro| pro.strip_prefix("Token-")) {
+        Some(token) => token
+            .parse::<i32>()
+            .map_err(|_| fail_response("token is no valid i32"))?,
+        None => return Err(fail_response("no token in protocols")),
+    };
+    Ok((res, token, session(&protocols)))
+}
+
+/// Returns the session token of a reconnecting client, it is passed as a protocol like the token.
+fn session(protocols: &[&str]) -> Option<u64> {
+    let session = protocols
+        .iter()
+        .find_map(|pro| pro.strip_prefix(SESSION_PREFIX))?;
+    match u64::from_str_radix(session, 16) {
+        Ok(session) => Some(session),
+        Err(_) => {
+            warn!("session token {} is no valid hex number", session);
+            None
         }
-    } else {
-        (
-            fail_response(res, "failed to retrieve protocols"),
-            Err(ServerError::InvalidProtocol),
-        )
     }
 }
 
-fn fail_response(mut res: Response, reason: &str) -> Response {
-    res.set_status(400);
-    res.set_reason(reason);
+fn fail_response(reason: &str) -> ErrorResponse {
+    let mut res = ErrorResponse::new(Some(reason.to_owned()));
+    *res.status_mut() = StatusCode::BAD_REQUEST;
     warn!("{}", reason);
     res
 }
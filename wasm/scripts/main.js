@@ -5,6 +5,10 @@ const WEBSOCKET_URI = 'ws://localhost:5001/'
 const MESSAGE_ITEM_SIZE = 32;
 const RESOURCE_PREFIX = '../../res/'
 const MEMORY_MB = 32;
+// snapshots are uploaded into buffers with ids starting here, see `wasm/src/logic/mod.rs`
+const SNAPSHOT_BUFFERS = 0x80000000;
+const SNAPSHOT_BUFFER_COUNT = 64;
+let snapshot_count = 0;
 let decoder = new TextDecoder('utf-8', {ignoreBOM: true, fatal: true});
 let SYNCHRONIZATION_MEMORY;
 let MESSAGE_QUEUE = null;
@@ -22,6 +26,11 @@ let memory;  // global for debugging
 let ws = new WebSocket(WEBSOCKET_URI, [token, "tuesday"]);
 ws.binaryType = 'arraybuffer';
 let connected = false
+// the session token received from the server, used to resume the session after a reconnect
+let session = null;
+const RECONNECT_DELAY = 1000;
+const MAX_RECONNECTS = 20;
+let reconnects = 0;
 
 let mousex = 0;
 let mousey = 0;
@@ -174,6 +183,8 @@ function LogicMessage(e) {
     let optcode = x[0];
     if (optcode === PUSH_ENGINE_EVENT) {
         ws.send(x.slice(1));
+    } else if (optcode === PUSH_INPUT || optcode === ACK_SNAPSHOT) {
+        if (connected) ws.send(x.slice(0, 3));
     } else if (optcode === FETCH_RESOURCE) {
         let res = fetch(RESOURCE_PREFIX + str_from_mem(x[2], x[3]));
         res.then(async function (data) {
@@ -299,6 +310,7 @@ function setup_ws() {
     ws.addEventListener('open', () => {
         console.log('ws connection to ' + WEBSOCKET_URI + ' established');
         connected = true;
+        reconnects = 0;
     });
     ws.addEventListener('error', event => {
         console.error('ws error occurred: "' + event + '"');
@@ -307,12 +319,26 @@ function setup_ws() {
     ws.addEventListener('close', event => {
         console.error('ws is closed now: ' + event);
         connected = false;
+        if (session !== null && reconnects++ < MAX_RECONNECTS) {
+            setTimeout(reconnect, RECONNECT_DELAY);
+        }
     });
     ws.addEventListener('message', e => {
         let data = new Uint32Array(e.data, 0, 1);
         let opcode = data[0];
         if (opcode === PUSH_RESOURCE) {
             upload_resource(e.data);
+        } else if (opcode === PUSH_SNAPSHOT) {
+            let id = SNAPSHOT_BUFFERS + snapshot_count++ % SNAPSHOT_BUFFER_COUNT;
+            upload_resource(id, e.data);
+        } else if (opcode === PING) {
+            // answer immediately with the time of the client clock in microseconds
+            let time = Math.floor(performance.now() * 1000);
+            let id = new Uint32Array(e.data, 4, 1)[0];
+            ws.send(new Uint32Array([PONG, id, time % 0x100000000, Math.floor(time / 0x100000000)]).buffer);
+        } else if (opcode === PUSH_SESSION) {
+            let token = new Uint32Array(e.data, 4, 2);
+            session = token[1].toString(16).padStart(8, '0') + token[0].toString(16).padStart(8, '0');
         } else if (opcode === PUSH_GAME_STATE) {
             Atomics.store(memoryView32, SYNC_OTHER_STATE, data[1]);
             Atomics.store(memoryView32, SYNC_OTHER_STATE + 1, data[2]);
@@ -324,6 +350,13 @@ function setup_ws() {
 }
 setup_ws();
 
+function reconnect() {
+    console.log('reconnecting to ' + WEBSOCKET_URI);
+    ws = new WebSocket(WEBSOCKET_URI, [token, "tuesday", "Session-" + session]);
+    ws.binaryType = 'arraybuffer';
+    setup_ws();
+}
+
 function hashCode(str) {
     var hash = 0;
     if (str.length === 0) {
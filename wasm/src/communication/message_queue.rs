@@ -24,6 +24,10 @@ pub enum Message {
     TextMode(bool) = op_codes::SET_TEXT_MODE,
     /// Wrapper for game events to be relayed to the server.
     EngineEvent(Event) = op_codes::PUSH_ENGINE_EVENT,
+    /// An input frame to be relayed to the server, the sequence number and the encoded actions.
+    PushInput(u32, u32) = op_codes::PUSH_INPUT,
+    /// Acknowledge a snapshot to the server, the tick is split into the lower and upper half.
+    AckSnapshot(u32, u32) = op_codes::ACK_SNAPSHOT,
 
     // Resorce Handling
     RequestAlloc {
@@ -3,16 +3,37 @@
 
 mod resource_parser;
 use crate::{
-    communication::{Message, MessageQueue, Sprite, DOUBLE_BUFFER, SYNCHRONIZATION_MEMORY},
+    communication::{
+        GameState, Message, MessageQueue, Sprite, DOUBLE_BUFFER, SYNCHRONIZATION_MEMORY,
+    },
     error::ClientError,
 };
 use rask_engine::{
-    engine::{GameEngine, RaskEngine},
+    engine::{components::*, GameEngine, Level, MatchRules, RaskEngine},
     events::{Event, Key},
+    math::Mat3,
+    network::{
+        packet::{PacketVariant, WebSocketPacket},
+        prediction::{PredictionConfig, Predictor},
+        snapshot::SnapshotReceiver,
+    },
     resources::registry,
     resources::GetStore,
 };
 use resource_parser::ResourceParser;
+use specs::{Entity, WorldExt};
+
+/// The level played until levels can be selected.
+const LEVEL: &str = include_str!("../../../res/levels/bridge.json");
+/// Half of the visible area in world units.
+const VIEW_SIZE: (f32, f32) = (8.0, 4.5);
+/// The player controlled by this client, until the server assigns the players.
+const LOCAL_PLAYER: u32 = 0;
+/// `main.js` uploads the snapshots received from the server into buffers with ids starting at
+/// this one, so they don't collide with the resources.
+const SNAPSHOT_BUFFERS: u32 = 0x8000_0000;
+/// The number of snapshots kept to decode the deltas.
+const SNAPSHOT_HISTORY: usize = 32;
 
 pub struct LogicContext {
     engine: RaskEngine,
@@ -21,9 +42,10 @@ pub struct LogicContext {
     tick_nr: u64,
     pub message_queue: MessageQueue,
     res_parser: ResourceParser,
-    angle: i32,
-    angle_mod: i32,
-    anim_tick_nr: u32,
+    players: Vec<Entity>,
+    resources_loaded: bool,
+    predictor: Predictor,
+    snapshots: SnapshotReceiver,
 }
 
 /// The logic context stores everything necessary for event handling and the game engine.
@@ -34,16 +56,21 @@ impl LogicContext {
         res_parser.fetch_resource(registry::THIEF)?;
         res_parser.fetch_resource(registry::SOUND)?;
         res_parser.fetch_character_resource(registry::CHAR)?;
+        let mut engine = RaskEngine::new(std::sync::Arc::new(pool));
+        engine.load_level(Level::from_json(LEVEL)?)?;
+        let players = vec![engine.spawn_player(0), engine.spawn_player(1)];
+        engine.start_match(MatchRules::default());
         Ok(Self {
-            engine: RaskEngine::new(std::sync::Arc::new(pool)),
+            engine,
             last_timestamp: unsafe { SYNCHRONIZATION_MEMORY.elapsed_ms },
             state: Vec::new(),
             tick_nr: 0,
             message_queue: MessageQueue::new(),
             res_parser,
-            angle: 0,
-            angle_mod: 0,
-            anim_tick_nr: 0,
+            players,
+            resources_loaded: false,
+            predictor: Predictor::new(LOCAL_PLAYER, PredictionConfig::default()),
+            snapshots: SnapshotReceiver::new(SNAPSHOT_HISTORY),
         })
     }
 
@@ -61,17 +88,13 @@ impl LogicContext {
             }
             log::debug!("{:?}", msg);
             event = self.handle_message(msg)?;
-        }
-        if let Some(ref event) = event {
-            self.engine.handle_event(event.clone())?;
+            if let Some(ref event) = event {
+                self.engine.handle_event(event.clone())?;
+            }
         }
         match event {
-            Some(Event::KeyDown(_, Key::ARROW_LEFT)) => self.angle_mod = -1,
-            Some(Event::KeyDown(_, Key::ARROW_RIGHT)) => self.angle_mod = 1,
-            Some(Event::KeyUp(_, Key::ARROW_RIGHT)) => self.angle_mod = 0,
-            Some(Event::KeyUp(_, Key::ARROW_LEFT)) => self.angle_mod = 0,
             Some(Event::KeyDown(_, Key::KEY_P)) => Message::PlaySound(registry::SOUND.id).send(),
-            Some(Event::KeyDown(_, Key::KEY_S)) => Message::StopSound(registry::SOUND.id).send(),
+            Some(Event::KeyDown(_, Key::KEY_O)) => Message::StopSound(registry::SOUND.id).send(),
             Some(Event::KeyDown(_, Key::DIGIT1)) => log::set_max_level(log::LevelFilter::Info),
             Some(Event::KeyDown(_, Key::DIGIT2)) => log::set_max_level(log::LevelFilter::Debug),
             Some(Event::KeyDown(_, Key::DIGIT3)) => log::set_max_level(log::LevelFilter::Trace),
@@ -81,13 +104,13 @@ impl LogicContext {
                 self.res_parser.fetch_character_resource(registry::CHAR)?;
                 self.res_parser.fetch_resource(registry::SOUND)?;
             }
-            Some(Event::KeyDown(_, Key::KEY_W)) => {
+            Some(Event::KeyDown(_, Key::MINUS)) => {
                 let mut src = crate::communication::SCREEN_RECT_SCALE.write();
                 if *src > 1.025 {
                     *src -= 0.05;
                 }
             }
-            Some(Event::KeyDown(_, Key::KEY_E)) => {
+            Some(Event::KeyDown(_, Key::EQUAL)) => {
                 let mut src = crate::communication::SCREEN_RECT_SCALE.write();
                 if *src < 1.975 {
                     *src += 0.05;
@@ -95,70 +118,112 @@ impl LogicContext {
             }
             _ => (),
         }
-        self.angle += self.angle_mod;
 
-        // TODO: Remove this temporary sprite loading. Replace it with some kind of
-        // "resource complete" event
-        if self.state.len() < 2 {
-            let res = crate::communication::RESOURCE_TABLE.read();
-            let texid1 = registry::EMPTY.id;
-            let texid2 = registry::THIEF.id;
-            let charid = registry::CHAR.id;
-            let tex1: Result<&rask_engine::resources::Texture, _> = res.get(texid1 as usize);
-            let tex2: Result<&rask_engine::resources::Texture, _> = res.get(texid2 as usize);
-            let charc: Result<&Box<rask_engine::resources::Character>, _> =
-                res.get(charid as usize);
-            if let (Ok(_), Ok(_), Ok(charc)) = (tex1, tex2, charc) {
-                log::info!("loaded all resoucres");
-                let mut guard = crate::communication::TEXTURE_IDS.lock();
-                for &(id, mat) in &[
-                    (texid1, rask_engine::math::Mat3::identity()),
-                    (texid2, rask_engine::math::Mat3::identity()),
-                ] {
-                    guard.ids.push(id);
-                    self.state
-                        .push(crate::communication::Sprite::new(mat, id, 0));
-                }
-                let sprites = charc.interpolate(0.0, "walking")?;
-                guard.ids.push(charid);
-                for sprite in sprites {
-                    self.state
-                        .push(crate::communication::Sprite::from_animation_state(
-                            sprite?, charid,
-                        ));
-                }
-                guard.reset_notify = 1;
-            }
+        let now = unsafe { SYNCHRONIZATION_MEMORY.elapsed_ms };
+        let frames = self.predictor.tick(
+            &mut self.engine,
+            core::time::Duration::from_millis((now - self.last_timestamp) as u64),
+        );
+        self.last_timestamp = now;
+        for frame in frames {
+            Message::PushInput(frame.sequence, frame.actions.to_bits()).send();
         }
-        log::trace!("angle: {}", self.angle);
-        if self.state.len() >= 3 {
-            self.state[1].transform = rask_engine::math::Mat3::rotation(0.02 * self.angle as f32)
-                * rask_engine::math::Mat3::scaling(0.0, 0.0);
-            let res = crate::communication::RESOURCE_TABLE.read();
-            let charid = rask_engine::resources::registry::CHAR.id;
-            let charc: &Box<rask_engine::resources::Character> = res.get(charid as usize).unwrap();
-            let sprites = charc.interpolate(self.tick_nr as f32 * 0.018, "walking")?;
-            for (i, sprite) in sprites.enumerate() {
-                self.state[2 + i] =
-                    crate::communication::Sprite::from_animation_state(sprite?, charid);
-                self.state[2 + i].transform = rask_engine::math::Mat3::translation(
-                    self.tick_nr as f32 / (90.0 * 2.0) % 2.2 - 1.1,
-                    -0.72,
-                ) * self.state[2 + i].transform;
-            }
+        if let Some(player) = self.engine.snapshot().player(LOCAL_PLAYER) {
+            let state = GameState {
+                player_x: player.pos.x(),
+                player_y: player.pos.y(),
+                player_state: player.player.map_or(0, |p| p.state as i32),
+            };
+            unsafe { SYNCHRONIZATION_MEMORY.player = state }
+        }
+        for event in self.engine.poll_events() {
+            log::debug!("engine event: {:?}", event);
         }
 
-        let now = unsafe { SYNCHRONIZATION_MEMORY.elapsed_ms };
-        self.engine.tick(core::time::Duration::from_millis(
-            (now - self.last_timestamp) as u64,
-        ));
-        self.last_timestamp = now;
+        // TODO: Replace this with some kind of "resource complete" event
+        if !self.resources_loaded {
+            self.register_textures();
+        }
+        if self.resources_loaded {
+            self.render()?;
+        }
 
         self.push_state();
         self.tick_nr += 1;
         Ok(())
     }
 
+    /// Decode a snapshot packet received from the server and correct the prediction.
+    fn receive_snapshot(&mut self, buf: &[u8]) -> Result<(), ClientError> {
+        let (input, data) = match WebSocketPacket::deserialize(buf)?.payload {
+            PacketVariant::PushSnapshot { input, data } => (input, data),
+            _ => return Err(ClientError::EngineError("expected a snapshot".into())),
+        };
+        let snapshot = self.snapshots.decode(data)?.clone();
+        Message::AckSnapshot(snapshot.tick as u32, (snapshot.tick >> 32) as u32).send();
+        if self.predictor.reconcile(&mut self.engine, &snapshot, input) {
+            log::debug!("corrected the prediction after input {}", input);
+        }
+        Ok(())
+    }
+
+    /// Register the textures used for rendering, once all of them are loaded.
+    fn register_textures(&mut self) {
+        let res = crate::communication::RESOURCE_TABLE.read();
+        let tex: Result<&rask_engine::resources::Texture, _> = res.get(registry::EMPTY.id as usize);
+        let charc: Result<&Box<rask_engine::resources::Character>, _> =
+            res.get(registry::CHAR.id as usize);
+        if let (Ok(_), Ok(_)) = (tex, charc) {
+            log::info!("loaded all resoucres");
+            let mut guard = crate::communication::TEXTURE_IDS.lock();
+            guard.ids.push(registry::EMPTY.id);
+            guard.ids.push(registry::CHAR.id);
+            guard.reset_notify = 1;
+            self.resources_loaded = true;
+        }
+    }
+
+    /// Build the sprites from the current state of the engine.
+    fn render(&mut self) -> Result<(), ClientError> {
+        let res = crate::communication::RESOURCE_TABLE.read();
+        let charid = registry::CHAR.id;
+        let charc: &Box<rask_engine::resources::Character> = res.get(charid as usize)?;
+        let time = self.tick_nr as f32 * 0.018;
+
+        self.state.clear();
+        self.state
+            .push(Sprite::new(Mat3::identity(), registry::EMPTY.id, 0));
+        // the camera shows the current screen of the match
+        let camera = self.engine.match_state().map_or(0.0, |state| {
+            let (left, right) = state.screen_bounds();
+            (left + right) / 2.0
+        });
+        let world = self.engine.world();
+        let player_storage = world.read_storage::<Player>();
+        for &entity in self.players.iter() {
+            let (player, pos) = match (
+                player_storage.get(entity),
+                self.predictor.display_pos(&self.engine, entity),
+            ) {
+                (Some(player), Some(pos)) => (player, pos),
+                _ => continue,
+            };
+            let animation = match player.state {
+                PlayerState::Run | PlayerState::Lunge => "walking",
+                _ => "standing",
+            };
+            let transform =
+                Mat3::translation((pos.x() - camera) / VIEW_SIZE.0, pos.y() / VIEW_SIZE.1)
+                    * Mat3::scaling(player.facing.sign(), 1.0);
+            for sprite in charc.interpolate(time, animation)? {
+                let mut sprite = Sprite::from_animation_state(sprite?, charid);
+                sprite.transform = transform * sprite.transform;
+                self.state.push(sprite);
+            }
+        }
+        Ok(())
+    }
+
     fn handle_message(&mut self, message: Message) -> Result<Option<Event>, ClientError> {
         match message {
             Message::KeyDown(modifier, hash) => Ok(Some(Event::KeyDown(modifier, Key::from(hash)))),
@@ -172,6 +237,10 @@ impl LogicContext {
                 Ok(None)
             }
             Message::RequestAlloc { id, size } => self.res_parser.alloc(id, size).map(|_| None),
+            Message::DoneWritingResource(id) if id >= SNAPSHOT_BUFFERS => {
+                let buf = self.res_parser.take_buffer(id)?;
+                self.receive_snapshot(&buf).map(|_| None)
+            }
             Message::DoneWritingResource(id) => self.res_parser.parse(id).map(|_| None),
             _ => Err(ClientError::EngineError("Unknown Message Type".into())),
         }
@@ -119,6 +119,16 @@ impl ResourceParser {
         }
     }
 
+    /// Returns the content of the buffer `id` and frees it, e.g. for a packet that is not a
+    /// resource.
+    pub fn take_buffer(&mut self, id: u32) -> Result<Vec<u8>, ClientError> {
+        let mut buffer = self
+            .pop_buffer(id)
+            .ok_or_else(|| ClientError::ResourceError(format!("buffer {} is not allocated", id)))?;
+        unsafe { buffer.set_len(buffer.capacity()) }
+        Ok(buffer)
+    }
+
     fn parse_ws_package(&mut self, id: u32) -> Result<(), ClientError> {
         let data = self.pop_buffer(id).unwrap();
         let msg = packet::WebSocketPacket::deserialize(data.as_slice())?;